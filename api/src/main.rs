@@ -67,7 +67,7 @@ async fn main() -> anyhow::Result<()> {
 
     for contract_id in &contracts {
         match storage.load_contract(contract_id) {
-            Ok(content) => match validation::validate_contract(&content) {
+            Ok(content) => match validation::validate_contract(contract_id, &content) {
                 Ok(_) => {
                     valid_count += 1;
                 }
@@ -95,6 +95,10 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Serving {} valid contract(s)", valid_count);
 
+    for diagnostic in validation::validate_contracts(storage.load_all_contracts()) {
+        warn!("{}", diagnostic);
+    }
+
     // Create app state
     let app_state = AppState::new(storage, cli.dev, cli.dev_server_url.clone());
 
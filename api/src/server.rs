@@ -1,17 +1,20 @@
 use axum::{
     body::Body,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{header, HeaderMap, StatusCode, Uri},
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use promise_tracker::impact::impact;
+use promise_tracker::selector::{Labels, Selector};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 
 use crate::static_files::serve_static_or_proxy;
 use crate::storage::{DirectoryEntry, EntryType, Storage};
-use crate::validation::validate_contract;
+use crate::validation::{load_tracker, validate_contract_with};
 
 /// Application state containing the storage
 #[derive(Clone)]
@@ -271,6 +274,9 @@ pub fn create_router(state: AppState) -> Router {
             "/contracts/*contract_id",
             get(get_contract).put(put_contract),
         )
+        .route("/agents", get(list_agents))
+        .route("/resolutions/*behavior_name", get(get_resolution))
+        .route("/impact", get(get_impact))
         .layer(cors_layer)
         // Fallback to static files for non-API routes
         .fallback(static_file_handler)
@@ -431,8 +437,9 @@ async fn put_contract(
         }
     };
 
-    // Validate the contract
-    match validate_contract(&content) {
+    // Validate the contract against everything else that is being served
+    let others = state.storage.read().await.load_all_contracts();
+    match validate_contract_with(&contract_id, &content, others) {
         Ok(_) => {
             // Save the contract
             let mut storage = state.storage.write().await;
@@ -491,3 +498,102 @@ async fn put_contract(
         }
     }
 }
+
+/// GET /resolutions/{behavior_name} - Resolve a behavior against every contract.
+/// Behaviors that nothing provides come back with suggestions of what was
+/// probably meant.
+async fn get_resolution(
+    State(state): State<AppState>,
+    Path(behavior_name): Path<String>,
+) -> impl IntoResponse {
+    // URL decode the behavior name (axum Path doesn't decode automatically)
+    let behavior_name = urlencoding::decode(&behavior_name)
+        .map(|decoded| decoded.to_string())
+        .unwrap_or(behavior_name);
+
+    let contracts = state.storage.read().await.load_all_contracts();
+    let resolution = load_tracker(contracts).resolve(&behavior_name);
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_string(&resolution).unwrap()))
+        .unwrap()
+}
+
+/// Query parameters of GET /agents
+#[derive(Debug, Deserialize)]
+struct AgentsQuery {
+    /// e.g. `team=payments,tier!=batch`
+    selector: Option<String>,
+}
+
+/// An entry of the GET /agents listing
+#[derive(Debug, Serialize)]
+struct AgentEntry<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Labels::is_empty")]
+    labels: &'a Labels,
+}
+
+/// GET /agents?selector=... - The working agents built from all contracts,
+/// with their labels, optionally only those matching a label selector
+async fn list_agents(
+    State(state): State<AppState>,
+    Query(query): Query<AgentsQuery>,
+) -> impl IntoResponse {
+    let selector: Selector = match query.selector.as_deref().unwrap_or("").parse() {
+        Ok(selector) => selector,
+        Err(e) => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header(header::CONTENT_TYPE, "text/plain")
+                .body(Body::from(format!("Invalid selector: {}", e)))
+                .unwrap();
+        }
+    };
+    let contracts = state.storage.read().await.load_all_contracts();
+    let tracker = load_tracker(contracts);
+    let empty = Labels::new();
+    let entries: Vec<AgentEntry> = tracker
+        .select_agent_names(&selector)
+        .into_iter()
+        .map(|name| AgentEntry {
+            name,
+            labels: tracker.get_agent_labels(name).unwrap_or(&empty),
+        })
+        .collect();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_string(&entries).unwrap()))
+        .unwrap()
+}
+
+/// Query parameters of GET /impact
+#[derive(Debug, Deserialize)]
+struct ImpactQuery {
+    /// Comma separated agent, SuperAgent and SuperAgent instance names, e.g.
+    /// `db,cache`
+    agents: String,
+}
+
+/// GET /impact?agents=... - Which wants across all contracts break if the
+/// given agents go away
+async fn get_impact(
+    State(state): State<AppState>,
+    Query(query): Query<ImpactQuery>,
+) -> impl IntoResponse {
+    let agent_names: Vec<&str> = query
+        .agents
+        .split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .collect();
+    let contracts = state.storage.read().await.load_all_contracts();
+    let impact = impact(&load_tracker(contracts), &agent_names);
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_string(&impact).unwrap()))
+        .unwrap()
+}
@@ -170,6 +170,17 @@ impl Storage {
             .with_context(|| format!("Failed to read contract file: {:?}", absolute_path))
     }
 
+    /// Load every contract that can be read, as (id, content) pairs sorted by id
+    pub fn load_all_contracts(&self) -> Vec<(String, String)> {
+        self.list_contracts()
+            .into_iter()
+            .filter_map(|id| {
+                let content = self.load_contract(&id).ok()?;
+                Some((id, content))
+            })
+            .collect()
+    }
+
     /// Save a contract to the file system
     /// Creates directories as needed
     pub fn save_contract(&mut self, contract_id: &str, content: &str) -> Result<()> {
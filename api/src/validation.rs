@@ -1,12 +1,15 @@
 use anyhow::Result;
 use promise_tracker::components::Item;
-use serde::Deserialize;
+use promise_tracker::loader::load_items;
+use promise_tracker::validate::{validate, Diagnostic};
+use promise_tracker::Tracker;
 
 /// Validation error type
 #[derive(Debug)]
 pub enum ValidationError {
     Yaml(serde_yaml::Error),
     InvalidContent(String),
+    Diagnostics(Vec<Diagnostic>),
 }
 
 impl std::fmt::Display for ValidationError {
@@ -14,6 +17,13 @@ impl std::fmt::Display for ValidationError {
         match self {
             ValidationError::Yaml(e) => write!(f, "YAML parsing error: {}", e),
             ValidationError::InvalidContent(msg) => write!(f, "Invalid content: {}", msg),
+            ValidationError::Diagnostics(diagnostics) => {
+                write!(f, "Invalid contract:")?;
+                for d in diagnostics {
+                    write!(f, "\n{}", d)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -21,26 +31,9 @@ impl std::fmt::Display for ValidationError {
 impl std::error::Error for ValidationError {}
 
 /// Validate a contract (multidoc YAML) containing only Agents and SuperAgents
-/// Returns the parsed Items if valid
-pub fn validate_contract(content: &str) -> Result<Vec<Item>, ValidationError> {
-    let mut items: Vec<Item> = vec![];
-
-    // Parse multidoc YAML
-    for document in serde_yaml::Deserializer::from_str(content) {
-        match Item::deserialize(document) {
-            Ok(item) => {
-                // Ensure it's either Agent or SuperAgent
-                match &item {
-                    Item::Agent(_) | Item::SuperAgent(_) => {
-                        items.push(item);
-                    }
-                }
-            }
-            Err(e) => {
-                return Err(ValidationError::Yaml(e));
-            }
-        }
-    }
+/// Returns the parsed Items if valid, with `path` recorded as where they came from
+pub fn validate_contract(path: &str, content: &str) -> Result<Vec<Item>, ValidationError> {
+    let items = load_items(path, content).map_err(ValidationError::Yaml)?;
 
     if items.is_empty() {
         return Err(ValidationError::InvalidContent(
@@ -51,6 +44,46 @@ pub fn validate_contract(content: &str) -> Result<Vec<Item>, ValidationError> {
     Ok(items)
 }
 
+/// Validate a contract along with the other contracts (id, content) it will
+/// be served with, so that it can refer to agents defined elsewhere. Only
+/// errors in this contract make it invalid.
+pub fn validate_contract_with(
+    path: &str,
+    content: &str,
+    others: Vec<(String, String)>,
+) -> Result<Vec<Item>, ValidationError> {
+    let items = validate_contract(path, content)?;
+    let others = others.into_iter().filter(|(id, _)| id != path).collect();
+    let mut tracker = load_tracker(others);
+    tracker.add_source_items(path, items.clone());
+    let errors: Vec<Diagnostic> = validate(&tracker)
+        .into_iter()
+        .filter(|d| d.is_error() && d.location.as_ref().is_some_and(|l| l.path == path))
+        .collect();
+    if !errors.is_empty() {
+        return Err(ValidationError::Diagnostics(errors));
+    }
+    Ok(items)
+}
+
+/// Every problem across a set of contracts (id, content). Contracts that do
+/// not parse are left out.
+pub fn validate_contracts(contracts: Vec<(String, String)>) -> Vec<Diagnostic> {
+    validate(&load_tracker(contracts))
+}
+
+/// A Tracker of every contract (id, content) that parses
+pub(crate) fn load_tracker(contracts: Vec<(String, String)>) -> Tracker {
+    let mut tracker = Tracker::new();
+    tracker.replace_sources(
+        contracts
+            .into_iter()
+            .filter_map(|(id, content)| Some((id.clone(), load_items(&id, &content).ok()?)))
+            .collect(),
+    );
+    tracker
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,7 +91,7 @@ mod tests {
     #[test]
     fn test_validate_valid_agent() {
         let content = "kind: Agent\nname: test";
-        let result = validate_contract(content);
+        let result = validate_contract("test.yaml", content);
         assert!(result.is_ok());
         let items = result.unwrap();
         assert_eq!(items.len(), 1);
@@ -67,7 +100,7 @@ mod tests {
     #[test]
     fn test_validate_valid_superagent() {
         let content = "kind: SuperAgent\nname: test";
-        let result = validate_contract(content);
+        let result = validate_contract("test.yaml", content);
         assert!(result.is_ok());
         let items = result.unwrap();
         assert_eq!(items.len(), 1);
@@ -76,7 +109,7 @@ mod tests {
     #[test]
     fn test_validate_multidoc() {
         let content = "kind: Agent\nname: a1\n---\nkind: Agent\nname: a2";
-        let result = validate_contract(content);
+        let result = validate_contract("test.yaml", content);
         assert!(result.is_ok());
         let items = result.unwrap();
         assert_eq!(items.len(), 2);
@@ -85,14 +118,44 @@ mod tests {
     #[test]
     fn test_validate_invalid_yaml() {
         let content = "invalid: yaml: content: [";
-        let result = validate_contract(content);
+        let result = validate_contract("test.yaml", content);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_validate_empty() {
         let content = "";
-        let result = validate_contract(content);
+        let result = validate_contract("test.yaml", content);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_validate_with_other_contracts() {
+        let sa = "kind: SuperAgent\nname: sa\nagents: [a1]";
+        let others = vec![(
+            String::from("a1.yaml"),
+            String::from("kind: Agent\nname: a1"),
+        )];
+        assert!(validate_contract_with("sa.yaml", sa, others).is_ok());
+
+        let result = validate_contract_with("sa.yaml", sa, vec![]);
+        match result {
+            Err(ValidationError::Diagnostics(diagnostics)) => {
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(
+                    diagnostics[0].to_string(),
+                    "sa.yaml:1: error[unknown-superagent-member]: SuperAgent sa contains agent a1, which is not defined"
+                );
+            }
+            _ => panic!("expected diagnostics"),
+        }
+
+        // problems in other contracts don't count against this one
+        let others = vec![(
+            String::from("other.yaml"),
+            String::from("kind: SuperAgent\nname: other\nagents: [missing]"),
+        )];
+        assert!(validate_contract_with("a1.yaml", "kind: Agent\nname: a1", others.clone()).is_ok());
+        assert_eq!(validate_contracts(others).len(), 1);
+    }
 }
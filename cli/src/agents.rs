@@ -7,9 +7,24 @@ pub struct Parameters {
     /// The file(s) and dir(s) to check
     #[clap(short, long = "file")]
     files: Vec<String>,
+
+    /// Only list agents whose labels match, e.g. team=payments,tier!=batch
+    #[clap(short = 'l', long)]
+    selector: Option<String>,
+
+    /// Show the labels of each agent
+    #[clap(long)]
+    show_labels: bool,
 }
 
 pub fn command(parameters: &Parameters) {
+    let selector = match cli::parse_selector(&parameters.selector) {
+        Ok(selector) => selector,
+        Err(e) => {
+            println!("Invalid selector: {}", e);
+            process::exit(1);
+        }
+    };
     let mut tracker = Tracker::new();
     let todo = cli::ManifestList::new(&parameters.files).unwrap();
     for file in todo.files {
@@ -21,9 +36,16 @@ pub fn command(parameters: &Parameters) {
             }
         }
     }
-    let mut agent_names = tracker.get_agent_names();
-    agent_names.sort();
-    for agent_name in agent_names {
-        println!("{}", agent_name);
+    // an empty selector matches every working agent, so a selector only ever
+    // narrows the list
+    for agent_name in tracker.select_agent_names(&selector) {
+        match tracker.get_agent_labels(agent_name) {
+            Some(labels) if parameters.show_labels && !labels.is_empty() => {
+                let labels: Vec<String> =
+                    labels.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                println!("{} {}", agent_name, labels.join(","));
+            }
+            _ => println!("{}", agent_name),
+        }
     }
 }
@@ -1,6 +1,5 @@
 use clap::Parser;
 use promise_tracker::Tracker;
-use std::collections::HashSet;
 use std::process;
 
 #[derive(Parser)]
@@ -12,9 +11,30 @@ pub struct Parameters {
     /// Show single line outputs per provides/conditions
     #[clap(short, long)]
     compressed: bool,
+
+    /// Print the full satisfaction report as JSON
+    #[clap(long)]
+    json: bool,
+
+    /// Also check the demand of wants against the capacity of providers,
+    /// failing if any provider is oversubscribed
+    #[clap(long)]
+    allocate: bool,
+
+    /// Only check the wants of agents whose labels match, e.g.
+    /// team=payments,tier!=batch
+    #[clap(short = 'l', long)]
+    selector: Option<String>,
 }
 
 pub fn command(parameters: &Parameters) {
+    let selector = match cli::parse_selector(&parameters.selector) {
+        Ok(selector) => selector,
+        Err(e) => {
+            println!("Invalid selector: {}", e);
+            process::exit(1);
+        }
+    };
     let mut tracker = Tracker::new();
     let todo = cli::ManifestList::new(&parameters.files).unwrap();
     for file in todo.files {
@@ -26,24 +46,51 @@ pub fn command(parameters: &Parameters) {
             }
         }
     }
-    let mut wants = HashSet::new();
-    for agent_name in tracker.get_working_agent_names() {
-        for want in tracker.get_agent_wants(agent_name.clone()) {
-            wants.insert(want);
-        }
+    let mut report = tracker.resolve_selected(&selector);
+    if parameters.allocate {
+        report = report.with_allocation(tracker.allocate());
     }
-    let mut wants_ordered: Vec<_> = wants.iter().collect();
-    wants_ordered.sort();
-    for want in wants_ordered {
-        let r = tracker.resolve(&want);
-        if parameters.compressed {
-            for line in r.to_colorized_compressed_strings() {
-                println!("{}", line);
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        for want in report.get_distinct_wants() {
+            let r = &want.resolution;
+            if parameters.compressed {
+                for line in r.to_colorized_compressed_strings() {
+                    println!("{}", line);
+                }
+            } else {
+                for line in r.to_colorized_strings() {
+                    println!("{}", line);
+                }
             }
-        } else {
-            for line in r.to_colorized_strings() {
-                println!("{}", line);
+            if let Some(blocked_by) = r.to_blocked_by_string() {
+                println!("  {}", blocked_by);
+                if !want.criticality.is_required() {
+                    println!("  wanted as: {}", want.criticality);
+                }
             }
         }
+        for skipped in report.get_skipped_wants() {
+            println!(
+                "{} skips {}: {}",
+                skipped.agent_name,
+                skipped.behavior_name,
+                skipped.get_reason()
+            );
+        }
+        println!("{}", report.totals.to_summary_string());
+        if let Some(unmet) = report.totals.to_unmet_string() {
+            println!("{}", unmet);
+        }
+        if let Some(allocation) = &report.allocation {
+            for load in &allocation.loads {
+                println!("{}", load);
+            }
+        }
+    }
+    // Only wants that an agent can't work without are failures
+    if report.totals.has_unmet_required() || report.is_oversubscribed() {
+        process::exit(1);
     }
 }
@@ -0,0 +1,54 @@
+use clap::Parser;
+use promise_tracker::impact::impact;
+use promise_tracker::Tracker;
+use std::process;
+
+#[derive(Parser)]
+pub struct Parameters {
+    /// The file(s) or dir(s) to check
+    #[clap(short, long = "file")]
+    files: Vec<String>,
+
+    /// The agent(s), SuperAgent(s) or SuperAgent instance(s) to take away
+    #[clap(short, long = "agent", required = true)]
+    agents: Vec<String>,
+
+    /// Print the impact as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+pub fn command(parameters: &Parameters) {
+    let mut tracker = Tracker::new();
+    let todo = cli::ManifestList::new(&parameters.files).unwrap();
+    for file in todo.files {
+        match cli::process_file(&file, &mut tracker) {
+            Ok(_) => {}
+            Err(e) => {
+                println!("Error processing {}: {}", file, e);
+                process::exit(1);
+            }
+        }
+    }
+    let agent_names: Vec<&str> = parameters.agents.iter().map(String::as_str).collect();
+    let impact = impact(&tracker, &agent_names);
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&impact).unwrap());
+    } else {
+        for name in &impact.unknown {
+            println!("No agent, SuperAgent or instance named {}", name);
+        }
+        for broken in &impact.broken {
+            println!("{}", broken);
+            println!("  {}", broken.to_blocked_by_string());
+            if !broken.criticality.is_required() {
+                println!("  wanted as: {}", broken.criticality);
+            }
+        }
+        println!("{}", impact.to_summary_string());
+    }
+    // Only losing wants that an agent can't work without is a failure
+    if impact.has_broken_required() {
+        process::exit(1);
+    }
+}
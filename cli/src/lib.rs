@@ -1,6 +1,7 @@
 use promise_tracker::components::Item;
+use promise_tracker::loader::load_items;
+use promise_tracker::selector::Selector;
 use promise_tracker::Tracker;
-use serde::Deserialize;
 use std::collections::HashSet;
 
 #[derive(Debug)]
@@ -83,20 +84,18 @@ pub fn check_file(path: &str) -> Result<Vec<Item>, AddError> {
         Ok(contents) => contents,
         Err(e) => return Err(AddError::Io(e)),
     };
-    let mut ret: Vec<Item> = vec![];
-    for document in serde_yaml::Deserializer::from_str(&contents) {
-        match Item::deserialize(document) {
-            Ok(item) => ret.push(item),
-            Err(e) => return Err(AddError::Yaml(e)),
-        }
-    }
-    Ok(ret)
+    load_items(path, &contents).map_err(AddError::Yaml)
 }
 
 pub fn process_file(path: &str, tracker: &mut Tracker) -> Result<(), AddError> {
-    let items = check_file(path)?;
-    for item in items {
-        tracker.add_item(item);
-    }
+    tracker.add_source_items(path, check_file(path)?);
     Ok(())
 }
+
+/// Parses a `--selector` option; no option selects every agent
+pub fn parse_selector(selector: &Option<String>) -> Result<Selector, String> {
+    match selector {
+        Some(s) => s.parse(),
+        None => Ok(Selector::default()),
+    }
+}
@@ -0,0 +1,97 @@
+use clap::Parser;
+use promise_tracker::lint::{fix_contract, lint, lint_contract, LintConfig, RULES};
+use promise_tracker::loader::load_items;
+use promise_tracker::validate::DiagnosticCode;
+use promise_tracker::Tracker;
+use std::process;
+
+#[derive(Parser)]
+pub struct Parameters {
+    /// The file(s) or dir(s) to lint
+    #[clap(short, long = "file")]
+    files: Vec<String>,
+
+    /// Rule to skip; can be given more than once. One of unwanted-provide,
+    /// self-condition, factorable-global-conditions or similar-behavior-names
+    #[clap(short, long = "disable")]
+    disabled: Vec<String>,
+
+    /// Rewrite the files to fix what can be fixed safely
+    #[clap(long)]
+    fix: bool,
+
+    /// Print the diagnostics as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+fn config(parameters: &Parameters) -> LintConfig {
+    let mut config = LintConfig::new();
+    for id in &parameters.disabled {
+        match id.parse::<DiagnosticCode>() {
+            Ok(rule) if RULES.contains(&rule) => config = config.with_disabled(rule),
+            _ => {
+                println!(
+                    "Unknown rule {}, expected one of: {}",
+                    id,
+                    RULES.map(|r| r.as_str()).join(", ")
+                );
+                process::exit(1);
+            }
+        }
+    }
+    config
+}
+
+pub fn command(parameters: &Parameters) {
+    let config = config(parameters);
+    let mut tracker = Tracker::new();
+    let mut diagnostics = vec![];
+    let todo = cli::ManifestList::new(&parameters.files).unwrap();
+    for file in todo.files {
+        let mut contents = match std::fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("Error in {}: {}", file, e);
+                process::exit(1);
+            }
+        };
+        if parameters.fix {
+            let fix = fix_contract(&contents, &config);
+            if let Some(fixed) = fix.fixed {
+                if let Err(e) = std::fs::write(&file, &fixed) {
+                    println!("Error writing {}: {}", file, e);
+                    process::exit(1);
+                }
+                if !parameters.json {
+                    println!("Fixed {}", file);
+                }
+                contents = fixed;
+            }
+            if !parameters.json {
+                for reason in &fix.skipped {
+                    println!("Not fixing {}: {}", file, reason);
+                }
+            }
+        }
+        match load_items(&file, &contents) {
+            Ok(items) => tracker.add_source_items(&file, items),
+            Err(e) => {
+                println!("Error in {}: {}", file, e);
+                process::exit(1);
+            }
+        }
+        diagnostics.extend(lint_contract(&file, &contents, &config));
+    }
+    diagnostics.extend(lint(&tracker, &config));
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&diagnostics).unwrap());
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+    }
+    if !diagnostics.is_empty() {
+        process::exit(1);
+    }
+}
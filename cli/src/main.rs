@@ -3,8 +3,13 @@ use clap::{Parser, Subcommand};
 mod agents;
 mod behaviors;
 mod check_unsatisfied;
+mod impact;
+mod lint;
+mod minimal_deployment;
+mod redundancy;
 mod schema;
 mod simulate;
+mod startup_order;
 mod validate;
 mod who_provides;
 
@@ -17,16 +22,26 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Command {
-    /// List agents
+    /// List agents (after resolving SuperAgents)
     Agents(agents::Parameters),
     /// List behaviors (after resolving SuperAgents)
     Behavior(behaviors::Parameters),
     /// See what wants aren't satisfied
     CheckUnsatisfied(check_unsatisfied::Parameters),
+    /// See which wants break if the given agent(s) go away
+    Impact(impact::Parameters),
+    /// Look for clutter in the given file(s), optionally fixing it
+    Lint(lint::Parameters),
+    /// Work out the fewest other agents that the given agent(s) need
+    MinimalDeployment(minimal_deployment::Parameters),
+    /// See what a single agent failing can break
+    Redundancy(redundancy::Parameters),
     /// Display the json_schema for Agents et al
     Schema {},
     /// Check which behaviors are covered by the given file(s)
     Simulate(simulate::Parameters),
+    /// Order agents into waves that can start in parallel
+    StartupOrder(startup_order::Parameters),
     /// Check that the given file(s) are yaml and contain valid Agents et al
     Validate(validate::Parameters),
     /// Show who provides stuff
@@ -46,12 +61,27 @@ fn main() {
         Some(Command::CheckUnsatisfied(parameters)) => {
             check_unsatisfied::command(parameters);
         }
+        Some(Command::Impact(parameters)) => {
+            impact::command(parameters);
+        }
+        Some(Command::Lint(parameters)) => {
+            lint::command(parameters);
+        }
+        Some(Command::MinimalDeployment(parameters)) => {
+            minimal_deployment::command(parameters);
+        }
+        Some(Command::Redundancy(parameters)) => {
+            redundancy::command(parameters);
+        }
         Some(Command::Schema {}) => {
             schema::command();
         }
         Some(Command::Simulate(parameters)) => {
             simulate::command(parameters);
         }
+        Some(Command::StartupOrder(parameters)) => {
+            startup_order::command(parameters);
+        }
         Some(Command::Validate(parameters)) => {
             validate::command(parameters);
         }
@@ -0,0 +1,56 @@
+use clap::Parser;
+use promise_tracker::deployment::minimal_deployment;
+use promise_tracker::Tracker;
+use std::process;
+
+#[derive(Parser)]
+pub struct Parameters {
+    /// The file(s) or dir(s) to check
+    #[clap(short, long = "file")]
+    files: Vec<String>,
+
+    /// The agent(s) that have to ship
+    #[clap(short, long = "agent", required = true)]
+    agents: Vec<String>,
+
+    /// Print the deployment as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+pub fn command(parameters: &Parameters) {
+    let mut tracker = Tracker::new();
+    let todo = cli::ManifestList::new(&parameters.files).unwrap();
+    for file in todo.files {
+        match cli::process_file(&file, &mut tracker) {
+            Ok(_) => {}
+            Err(e) => {
+                println!("Error processing {}: {}", file, e);
+                process::exit(1);
+            }
+        }
+    }
+    let roots: Vec<&str> = parameters.agents.iter().map(String::as_str).collect();
+    let deployment = minimal_deployment(&tracker, &roots);
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&deployment).unwrap());
+    } else {
+        for name in &deployment.unknown {
+            println!("No agent named {}", name);
+        }
+        println!(
+            "Deploy {} along with: {}",
+            deployment.roots.join(", "),
+            deployment.get_set().join(", ")
+        );
+        for alternative in deployment.get_alternatives() {
+            println!("  or: {}", alternative.join(", "));
+        }
+        for unsatisfiable in &deployment.unsatisfiable {
+            println!("{}", unsatisfiable);
+        }
+    }
+    if !deployment.unsatisfiable.is_empty() {
+        process::exit(1);
+    }
+}
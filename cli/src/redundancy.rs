@@ -0,0 +1,49 @@
+use clap::Parser;
+use promise_tracker::redundancy::redundancy;
+use promise_tracker::Tracker;
+use std::process;
+
+#[derive(Parser)]
+pub struct Parameters {
+    /// The file(s) or dir(s) to check
+    #[clap(short, long = "file")]
+    files: Vec<String>,
+
+    /// Only show the wants that a single agent can break
+    #[clap(short, long)]
+    single_points: bool,
+
+    /// Print the full redundancy report as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+pub fn command(parameters: &Parameters) {
+    let mut tracker = Tracker::new();
+    let todo = cli::ManifestList::new(&parameters.files).unwrap();
+    for file in todo.files {
+        match cli::process_file(&file, &mut tracker) {
+            Ok(_) => {}
+            Err(e) => {
+                println!("Error processing {}: {}", file, e);
+                process::exit(1);
+            }
+        }
+    }
+    let report = redundancy(&tracker);
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return;
+    }
+    for want in &report.wants {
+        if parameters.single_points && !want.is_single_point_of_failure() {
+            continue;
+        }
+        println!("{}", want);
+    }
+    if !parameters.single_points {
+        for agent in &report.agents {
+            println!("{}", agent);
+        }
+    }
+}
@@ -10,6 +10,23 @@ pub struct Parameters {
 
     /// The behavior to validate
     behavior: String,
+
+    /// List the complete chains of agents that can satisfy the behavior
+    /// instead, a page at a time
+    #[clap(short, long)]
+    assignments: bool,
+
+    /// How many assignments to skip
+    #[clap(long, default_value_t = 0)]
+    offset: usize,
+
+    /// How many assignments to list
+    #[clap(long, default_value_t = 10)]
+    limit: usize,
+
+    /// Print the page of assignments as JSON
+    #[clap(long)]
+    json: bool,
 }
 
 pub fn command(parameters: &Parameters) {
@@ -24,5 +41,25 @@ pub fn command(parameters: &Parameters) {
             }
         }
     }
-    println!("{:?}", tracker.resolve(&parameters.behavior));
+    if !parameters.assignments {
+        println!("{:?}", tracker.resolve(&parameters.behavior));
+        return;
+    }
+    let page = tracker
+        .resolve_graph(&parameters.behavior)
+        .get_assignments(parameters.offset, parameters.limit);
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&page).unwrap());
+        return;
+    }
+    if page.assignments.is_empty() && page.offset == 0 {
+        println!("Nothing can satisfy {}", parameters.behavior);
+        process::exit(1);
+    }
+    for (i, assignment) in page.assignments.iter().enumerate() {
+        println!("{}. {}", page.offset + i + 1, assignment);
+    }
+    if page.has_more {
+        println!("more: --offset {}", page.offset + page.assignments.len());
+    }
 }
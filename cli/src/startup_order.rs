@@ -0,0 +1,40 @@
+use clap::Parser;
+use promise_tracker::Tracker;
+use std::process;
+
+#[derive(Parser)]
+pub struct Parameters {
+    /// The file(s) or dir(s) to check
+    #[clap(short, long = "file")]
+    files: Vec<String>,
+
+    /// Print the startup order as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+pub fn command(parameters: &Parameters) {
+    let mut tracker = Tracker::new();
+    let todo = cli::ManifestList::new(&parameters.files).unwrap();
+    for file in todo.files {
+        match cli::process_file(&file, &mut tracker) {
+            Ok(_) => {}
+            Err(e) => {
+                println!("Error processing {}: {}", file, e);
+                process::exit(1);
+            }
+        }
+    }
+    let order = tracker.get_startup_order();
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&order).unwrap());
+    } else {
+        for line in order.to_strings() {
+            println!("{}", line);
+        }
+    }
+    // Agents that wait for each other can't be brought up one after another
+    if order.has_cycles() {
+        process::exit(1);
+    }
+}
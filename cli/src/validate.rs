@@ -1,4 +1,6 @@
 use clap::Parser;
+use promise_tracker::validate::validate;
+use promise_tracker::Tracker;
 use std::process;
 
 #[derive(Parser)]
@@ -6,16 +8,24 @@ pub struct Parameters {
     /// File(s) to validate
     #[clap(short, long = "file")]
     files: Vec<String>,
+
+    /// Print the diagnostics as JSON
+    #[clap(long)]
+    json: bool,
 }
 
 pub fn command(parameters: &Parameters) {
+    let mut tracker = Tracker::new();
     let todo = cli::ManifestList::new(&parameters.files).unwrap();
     for file in todo.files {
         match cli::check_file(&file) {
             Ok(items) => {
-                for item in items {
-                    println!("Found: {}", item.get_name());
+                if !parameters.json {
+                    for item in &items {
+                        println!("Found: {}", item.get_name());
+                    }
                 }
+                tracker.add_source_items(&file, items);
             }
             Err(e) => {
                 println!("Error in {}: {}", file, e);
@@ -23,4 +33,15 @@ pub fn command(parameters: &Parameters) {
             }
         }
     }
+    let diagnostics = validate(&tracker);
+    if parameters.json {
+        println!("{}", serde_json::to_string_pretty(&diagnostics).unwrap());
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+    }
+    if diagnostics.iter().any(|d| d.is_error()) {
+        process::exit(1);
+    }
 }
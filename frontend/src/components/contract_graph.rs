@@ -1,5 +1,5 @@
 use leptos::prelude::*;
-use promise_tracker::diagram::diagram;
+use promise_tracker::diagram::diagram_graph;
 use promise_tracker::Tracker;
 use wasm_bindgen::prelude::*;
 
@@ -176,8 +176,8 @@ pub fn ContractGraph(
         }
 
         // Generate the actual diagram
-        let resolution = pt.resolve(&behavior);
-        diagram(&component, &behavior, &resolution)
+        let resolution = pt.resolve_graph(&behavior);
+        diagram_graph(&component, &behavior, &resolution)
     });
 
     let source_signal = Signal::derive(move || diagram_source.get());
@@ -1,8 +1,11 @@
 use gloo_timers::callback::Timeout;
 use leptos::prelude::*;
 use promise_tracker::components::Item;
+use promise_tracker::impact::{impact_with_report, Impact};
+use promise_tracker::loader::load_documents;
+use promise_tracker::report::{SatisfactionReport, WantStatus};
+use promise_tracker::selector::Selector;
 use promise_tracker::Tracker;
-use serde::Deserialize;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -15,9 +18,9 @@ use super::contract_text::ContractText;
 use super::promise_network_graph::PromiseNetworkGraph;
 use super::simulation_controls::SimulationControls;
 
-/// Build a Tracker from contract contents
-fn build_tracker_from_contracts(contracts: &[Contract], filter_sims: Option<&str>) -> Tracker {
-    let mut tracker = Tracker::new();
+/// Parse the items of every contract that belongs in the Tracker, keyed by contract id
+fn contract_items(contracts: &[Contract], filter_sims: Option<&str>) -> HashMap<String, Vec<Item>> {
+    let mut ret = HashMap::new();
 
     for contract in contracts {
         // Skip contracts with errors
@@ -38,14 +41,66 @@ fn build_tracker_from_contracts(contracts: &[Contract], filter_sims: Option<&str
         }
 
         // Parse and add items from the contract
-        for document in serde_yaml::Deserializer::from_str(&contract.content) {
-            if let Ok(item) = Item::deserialize(document) {
-                tracker.add_item(item);
-            }
+        let items = load_documents(&contract.filename, &contract.content)
+            .into_iter()
+            .filter_map(|item| item.ok())
+            .collect();
+        ret.insert(contract.id.clone(), items);
+    }
+
+    ret
+}
+
+/// Bring a Tracker in line with the contract contents. Only contracts that
+/// were added, changed or removed since the last sync are swapped out.
+fn sync_tracker(tracker: &mut Tracker, contracts: &[Contract], filter_sims: Option<&str>) {
+    let current = contract_items(contracts, filter_sims);
+
+    // Removed contracts are swapped for nothing
+    let mut changes: Vec<(String, Vec<Item>)> = tracker
+        .get_source_names()
+        .into_iter()
+        .filter(|id| !current.contains_key(*id))
+        .map(|id| (id.clone(), vec![]))
+        .collect();
+    for (id, items) in current {
+        let existing = tracker.get_source_items(&id);
+        let unchanged = items.iter().all(|i| existing.contains(i))
+            && existing.iter().all(|i| items.contains(i));
+        if !unchanged {
+            changes.push((id, items));
         }
     }
 
-    tracker
+    if !changes.is_empty() {
+        tracker.replace_sources(changes);
+    }
+}
+
+/// Options for the behavior dropdown of a component, marking wants that
+/// aren't satisfied. Returns None if the component isn't in the report.
+fn want_options(report: &SatisfactionReport, component: &str) -> Option<Vec<(String, String)>> {
+    let agent = report.get_agent(component)?;
+    if agent.wants.is_empty() {
+        return Some(vec![(
+            "---".to_string(),
+            "This component has no wants entries".to_string(),
+        )]);
+    }
+    let mut options = vec![("---".to_string(), "Select a behavior".to_string())];
+    options.extend(agent.wants.iter().map(|w| {
+        let label = match w.status {
+            WantStatus::Satisfied => w.behavior_name.clone(),
+            WantStatus::Unsatisfied => format!("{} (unsatisfied)", w.behavior_name),
+            WantStatus::NoProvider => format!("{} (no provider)", w.behavior_name),
+            WantStatus::VersionMismatch => format!("{} (version mismatch)", w.behavior_name),
+            WantStatus::PlacementMismatch => {
+                format!("{} (placement mismatch)", w.behavior_name)
+            }
+        };
+        (w.behavior_name.clone(), label)
+    }));
+    Some(options)
 }
 
 /// Main visualization container with tabs for overview and detailed view.
@@ -65,10 +120,14 @@ pub fn ContractGrapher(
     // Selected component and behavior for detailed view
     let (d_component, set_d_component) = signal("---".to_string());
     let (d_behavior, set_d_behavior) = signal("---".to_string());
+    // Label selector narrowing the component dropdown, e.g. team=payments
+    let (d_selector, set_d_selector) = signal(String::new());
 
     // Tracker instances - one main and one per simulation
     let (main_tracker, set_main_tracker) = signal::<Option<Tracker>>(None);
     let (sim_trackers, set_sim_trackers) = signal::<HashMap<String, Tracker>>(HashMap::new());
+    // Impact of the selected component, once asked for
+    let (component_impact, set_component_impact) = signal::<Option<Impact>>(None);
 
     // Store the debounce timeout handle
     let debounce_handle: Rc<RefCell<Option<Timeout>>> = Rc::new(RefCell::new(None));
@@ -90,6 +149,7 @@ pub fn ContractGrapher(
             if current_contracts.is_empty() {
                 set_main_tracker.set(None);
                 set_sim_trackers.set(HashMap::new());
+                set_component_impact.set(None);
                 set_d_component.set("---".to_string());
                 set_d_behavior.set("---".to_string());
                 return;
@@ -100,28 +160,41 @@ pub fn ContractGrapher(
                 return;
             }
 
-            // Build main tracker (no simulation filter)
-            let main = build_tracker_from_contracts(&current_contracts, None);
-            set_main_tracker.set(Some(main));
-
-            // Build per-simulation trackers
-            let mut sim_map = HashMap::new();
-            for sim in &sims {
-                let tracker = build_tracker_from_contracts(&current_contracts, Some(sim));
-                sim_map.insert(sim.clone(), tracker);
-            }
-            set_sim_trackers.set(sim_map);
+            // Update main tracker (no simulation filter). An impact worked
+            // out before is out of date now.
+            set_component_impact.set(None);
+            set_main_tracker.update(|main| {
+                sync_tracker(
+                    main.get_or_insert_with(Tracker::new),
+                    &current_contracts,
+                    None,
+                )
+            });
+
+            // Update per-simulation trackers
+            set_sim_trackers.update(|sim_map| {
+                sim_map.retain(|sim, _| sims.contains(sim));
+                for sim in &sims {
+                    let tracker = sim_map.entry(sim.clone()).or_insert_with(Tracker::new);
+                    sync_tracker(tracker, &current_contracts, Some(sim));
+                }
+            });
         });
 
         *debounce_handle_clone.borrow_mut() = Some(handle);
     });
 
-    // Get component names from the main tracker
+    // The parsed label selector, or why it doesn't parse
+    let selector = move || d_selector.get().parse::<Selector>();
+
+    // Get component names from the main tracker, narrowed by the selector
+    // (all of them while it doesn't parse)
     let components = move || {
+        let selector = selector().unwrap_or_default();
         main_tracker
             .get()
             .map(|t| {
-                t.get_working_agent_names()
+                t.select_agent_names(&selector)
                     .into_iter()
                     .cloned()
                     .collect::<Vec<_>>()
@@ -129,6 +202,9 @@ pub fn ContractGrapher(
             .unwrap_or_default()
     };
 
+    // Resolve every want once, so the dropdowns and the summary agree
+    let report = Memo::new(move |_| main_tracker.get().map(|t| t.resolve_all()));
+
     // Get wants (behaviors) for the selected component
     let wants = move || {
         let component = d_component.get();
@@ -136,29 +212,8 @@ pub fn ContractGrapher(
             return vec![("---".to_string(), "Select Component First".to_string())];
         }
 
-        main_tracker
-            .get()
-            .and_then(|t| {
-                if t.has_agent(component.clone()) {
-                    let agent_wants: Vec<String> =
-                        t.get_agent_wants(component).into_iter().collect();
-                    if agent_wants.is_empty() {
-                        Some(vec![(
-                            "---".to_string(),
-                            "This component has no wants entries".to_string(),
-                        )])
-                    } else {
-                        let mut options =
-                            vec![("---".to_string(), "Select a behavior".to_string())];
-                        let mut sorted_wants = agent_wants;
-                        sorted_wants.sort();
-                        options.extend(sorted_wants.into_iter().map(|w| (w.clone(), w)));
-                        Some(options)
-                    }
-                } else {
-                    None
-                }
-            })
+        report
+            .with(|r| r.as_ref().and_then(|r| want_options(r, &component)))
             .unwrap_or_else(|| vec![("---".to_string(), "Select Component First".to_string())])
     };
 
@@ -169,10 +224,34 @@ pub fn ContractGrapher(
             return false;
         }
 
-        main_tracker
-            .get()
-            .map(|t| t.has_agent(component.clone()) && !t.get_agent_wants(component).is_empty())
-            .unwrap_or(false)
+        report.with(|r| {
+            r.as_ref()
+                .and_then(|r| r.get_agent(&component))
+                .map(|a| !a.wants.is_empty())
+                .unwrap_or(false)
+        })
+    };
+
+    // One line summary of the whole network
+    let summary = move || {
+        report.with(|r| {
+            r.as_ref()
+                .map(|r| r.totals.to_summary_string())
+                .unwrap_or_default()
+        })
+    };
+
+    // What breaks across the network if the selected component goes away.
+    // Only worked out when asked for, since it resolves the network again.
+    let on_show_impact = move |_| {
+        let component = d_component.get_untracked();
+        let component_impact = main_tracker.with_untracked(|t| {
+            report.with_untracked(|r| match (t, r) {
+                (Some(t), Some(r)) => Some(impact_with_report(t, r, &[component.as_str()])),
+                _ => None,
+            })
+        });
+        set_component_impact.set(component_impact);
     };
 
     // Handle component dropdown change
@@ -180,6 +259,13 @@ pub fn ContractGrapher(
         let target = event_target::<web_sys::HtmlSelectElement>(&ev);
         set_d_component.set(target.value());
         set_d_behavior.set("---".to_string());
+        set_component_impact.set(None);
+    };
+
+    // Handle label selector input
+    let on_selector_input = move |ev: web_sys::Event| {
+        let target = event_target::<web_sys::HtmlInputElement>(&ev);
+        set_d_selector.set(target.value());
     };
 
     // Handle behavior dropdown change
@@ -303,9 +389,24 @@ pub fn ContractGrapher(
                         <p style="color: #666; font-size: 0.9em; margin-bottom: 1rem;">
                             "Select a component and behavior to see how promises are resolved."
                         </p>
+                        <p class="contract-grapher-summary" style="font-size: 0.9em; margin-bottom: 1rem;">
+                            {summary}
+                        </p>
 
-                        // Component and Behavior dropdowns
+                        // Label selector, then Component and Behavior dropdowns
                         <div class="mb-3">
+                            <input
+                                type="text"
+                                class="form-control mb-1"
+                                placeholder="Filter components by label, e.g. team=payments,tier!=batch"
+                                prop:value=move || d_selector.get()
+                                on:input=on_selector_input
+                            />
+                            <Show when=move || selector().is_err()>
+                                <div class="text-danger mb-2" style="font-size: 0.85em;">
+                                    {move || selector().err().unwrap_or_default()}
+                                </div>
+                            </Show>
                             <select
                                 class="form-select mb-2"
                                 on:change=on_component_change
@@ -355,6 +456,43 @@ pub fn ContractGrapher(
                             </select>
                         </div>
 
+                        // Blast radius of the selected component
+                        <Show when=move || d_component.get() != "---">
+                            <button
+                                class="btn btn-outline-secondary btn-sm mb-3"
+                                on:click=on_show_impact
+                            >
+                                "Show what breaks without it"
+                            </button>
+                        </Show>
+                        {move || {
+                            component_impact
+                                .get()
+                                .map(|i| {
+                                    view! {
+                                        <div class="contract-grapher-impact">
+                                            <div>{i.to_summary_string()}</div>
+                                            <ul>
+                                                {i
+                                                    .broken
+                                                    .iter()
+                                                    .map(|b| {
+                                                        view! {
+                                                            <li>
+                                                                {b.to_string()}
+                                                                <span class="contract-text-blocked-by">
+                                                                    " (" {b.to_blocked_by_string()} ")"
+                                                                </span>
+                                                            </li>
+                                                        }
+                                                    })
+                                                    .collect::<Vec<_>>()}
+                                            </ul>
+                                        </div>
+                                    }
+                                })
+                        }}
+
                         // Simulation panels
                         <div style="display: flex; gap: 1rem; margin-top: 1rem;">
                             {move || {
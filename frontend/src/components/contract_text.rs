@@ -1,11 +1,37 @@
 use leptos::prelude::*;
-use promise_tracker::resolve::{Offer, Resolution};
+use promise_tracker::resolution_graph::{
+    AssignmentPage, OfferNode, ResolutionGraph, ResolutionId, ResolutionNode,
+};
 use promise_tracker::Tracker;
+use std::collections::HashSet;
+
+/// How many assignments are listed at a time
+const ASSIGNMENTS_PER_PAGE: usize = 5;
+
+/// The label of a behavior that nothing provides, with the closest provided
+/// behaviors if there are any, or of one only provided at the wrong version
+/// or in the wrong place
+fn unsatisfied_label(prefix: &str, node: &ResolutionNode) -> String {
+    if let Some(mismatch) = node.to_mismatch_string() {
+        return format!("{} VERSION MISMATCH ({})", prefix, mismatch);
+    }
+    if let Some(rejection) = node.to_rejection_string() {
+        return format!("{} PLACEMENT MISMATCH ({})", prefix, rejection);
+    }
+    match node.to_suggestion_string() {
+        Some(suggestions) => format!("{} UNSATISFIED ({})", prefix, suggestions),
+        None => format!("{} UNSATISFIED", prefix),
+    }
+}
 
 /// Renders a single offer as a list item with optional nested conditions
-fn render_offer(offer: &Offer, is_satisfied: bool) -> impl IntoView {
+fn render_offer(
+    graph: &ResolutionGraph,
+    offer: &OfferNode,
+    is_satisfied: bool,
+    expanded: &mut HashSet<ResolutionId>,
+) -> impl IntoView {
     let agent_name = offer.get_agent_name().to_string();
-    let conditions = offer.get_resolved_conditions();
 
     let css_class = if is_satisfied {
         "contract-text-option"
@@ -19,7 +45,7 @@ fn render_offer(offer: &Offer, is_satisfied: bool) -> impl IntoView {
         format!("POSSIBLE: {}", agent_name)
     };
 
-    if conditions.is_empty() {
+    if offer.is_unconditional() {
         // No conditions - just render the label
         view! {
             <li class=css_class>{label}</li>
@@ -27,7 +53,32 @@ fn render_offer(offer: &Offer, is_satisfied: bool) -> impl IntoView {
         .into_any()
     } else {
         // Has conditions - render with nested children
-        let children: Vec<_> = conditions.iter().map(render_resolution_li).collect();
+        let mut children: Vec<AnyView> = offer
+            .get_conditions()
+            .iter()
+            .map(|c| render_resolution_li(graph, *c, expanded).into_any())
+            .collect();
+        // Each anyOf group is listed on its own; one of them has to be met
+        for (i, group) in offer.get_any_of().iter().enumerate() {
+            let group_class = if group.iter().all(|c| graph.get_node(*c).is_satisfied()) {
+                "contract-text-option"
+            } else {
+                "contract-text-possible"
+            };
+            let members: Vec<_> = group
+                .iter()
+                .map(|c| render_resolution_li(graph, *c, expanded))
+                .collect();
+            children.push(
+                view! {
+                    <li class=group_class>
+                        {format!("ANY OF {}", i + 1)}
+                        <ul class="contract-text-list">{members}</ul>
+                    </li>
+                }
+                .into_any(),
+            );
+        }
 
         view! {
             <li class=css_class>
@@ -39,31 +90,22 @@ fn render_offer(offer: &Offer, is_satisfied: bool) -> impl IntoView {
     }
 }
 
-/// Renders a Resolution as a list item with its satisfying/unsatisfying offers
-fn render_resolution_li(resolution: &Resolution) -> impl IntoView {
-    let behavior_name = resolution.get_behavior_name().to_string();
-    let satisfying = resolution.get_satisfying_offers();
-    let unsatisfying = resolution.get_unsatisfying_offers();
-
-    // If no offers at all, render as unsatisfied
-    if satisfying.is_empty() && unsatisfying.is_empty() {
-        return view! {
-            <li class="contract-text-possible">{format!("{} UNSATISFIED", behavior_name)}</li>
-        }
-        .into_any();
-    }
-
-    // Collect all options (satisfied first, then unsatisfied)
+/// Renders the satisfying then unsatisfying offers of a resolution
+fn render_options(
+    graph: &ResolutionGraph,
+    id: ResolutionId,
+    expanded: &mut HashSet<ResolutionId>,
+) -> Vec<AnyView> {
+    let resolution = graph.get_node(id);
     let mut options: Vec<AnyView> = Vec::new();
 
-    for offer in satisfying.iter() {
-        options.push(render_offer(offer, true).into_any());
+    for offer in resolution.get_satisfying_offers().iter() {
+        options.push(render_offer(graph, offer, true, expanded).into_any());
     }
 
-    for offer in unsatisfying.iter() {
+    for offer in resolution.get_unsatisfying_offers().iter() {
         // Check if the offer has no resolved conditions - this is an error case
-        let conditions = offer.get_resolved_conditions();
-        if conditions.is_empty() {
+        if offer.is_unconditional() {
             options.push(
                 view! {
                     <li class="contract-text-error">{format!("ERROR: {}", offer.get_agent_name())}</li>
@@ -71,17 +113,58 @@ fn render_resolution_li(resolution: &Resolution) -> impl IntoView {
                 .into_any(),
             );
         } else {
-            options.push(render_offer(offer, false).into_any());
+            options.push(render_offer(graph, offer, false, expanded).into_any());
+        }
+    }
+
+    options
+}
+
+/// Renders a Resolution as a list item with its satisfying/unsatisfying offers.
+/// Resolutions shared by several offers are only listed out the first time.
+fn render_resolution_li(
+    graph: &ResolutionGraph,
+    id: ResolutionId,
+    expanded: &mut HashSet<ResolutionId>,
+) -> impl IntoView {
+    let resolution = graph.get_node(id);
+    let behavior_name = resolution.get_behavior_name().to_string();
+
+    // A loop back onto the resolution path is rendered as an error with the loop
+    if resolution.is_cyclic() {
+        return view! {
+            <li class="contract-text-error">
+                {format!("{} CYCLE: {}", behavior_name, resolution.get_cycle().join(" -> "))}
+            </li>
+        }
+        .into_any();
+    }
+
+    // If no offers at all, render as unsatisfied
+    if !resolution.has_offers() {
+        return view! {
+            <li class="contract-text-possible">{unsatisfied_label(&behavior_name, resolution)}</li>
         }
+        .into_any();
     }
 
     // Determine the CSS class based on whether there are any satisfying offers
-    let contract_class = if !satisfying.is_empty() {
+    let contract_class = if resolution.is_satisfied() {
         "contract-text-option"
     } else {
         "contract-text-possible"
     };
 
+    // Already listed further up
+    if !expanded.insert(id) {
+        return view! {
+            <li class=contract_class>{format!("{} (see above)", behavior_name)}</li>
+        }
+        .into_any();
+    }
+
+    let options = render_options(graph, id, expanded);
+
     view! {
         <li class=contract_class>
             {behavior_name}
@@ -92,44 +175,25 @@ fn render_resolution_li(resolution: &Resolution) -> impl IntoView {
 }
 
 /// Renders the root resolution (component --> behavior) as a list item
-fn render_contract_text(component: &str, resolution: &Resolution) -> impl IntoView {
+fn render_contract_text(component: &str, graph: &ResolutionGraph) -> impl IntoView {
+    let resolution = graph.get_root_node();
     let behavior_name = resolution.get_behavior_name().to_string();
-    let satisfying = resolution.get_satisfying_offers();
-    let unsatisfying = resolution.get_unsatisfying_offers();
 
     // If no offers at all, render as unsatisfied
-    if satisfying.is_empty() && unsatisfying.is_empty() {
+    if !resolution.has_offers() {
         return view! {
             <li class="contract-text-possible">
-                {format!("{} --> {} UNSATISFIED", component, behavior_name)}
+                {unsatisfied_label(&format!("{} --> {}", component, behavior_name), resolution)}
             </li>
         }
         .into_any();
     }
 
-    // Collect all options (satisfied first, then unsatisfied)
-    let mut options: Vec<AnyView> = Vec::new();
-
-    for offer in satisfying.iter() {
-        options.push(render_offer(offer, true).into_any());
-    }
-
-    for offer in unsatisfying.iter() {
-        let conditions = offer.get_resolved_conditions();
-        if conditions.is_empty() {
-            options.push(
-                view! {
-                    <li class="contract-text-error">{format!("ERROR: {}", offer.get_agent_name())}</li>
-                }
-                .into_any(),
-            );
-        } else {
-            options.push(render_offer(offer, false).into_any());
-        }
-    }
+    let mut expanded = HashSet::from([graph.get_root()]);
+    let options = render_options(graph, graph.get_root(), &mut expanded);
 
     // Determine the CSS class based on whether there are any satisfying offers
-    let contract_class = if !satisfying.is_empty() {
+    let contract_class = if resolution.is_satisfied() {
         "contract-text-option"
     } else {
         "contract-text-possible"
@@ -144,6 +208,39 @@ fn render_contract_text(component: &str, resolution: &Resolution) -> impl IntoVi
     .into_any()
 }
 
+/// Lists a page of the complete chains of agents that satisfy the behavior,
+/// with buttons to move between pages
+fn render_assignments(page: AssignmentPage, set_offset: WriteSignal<usize>) -> impl IntoView {
+    let offset = page.offset;
+    let items: Vec<_> = page
+        .assignments
+        .iter()
+        .map(|a| view! { <li>{a.to_string()}</li> })
+        .collect();
+    view! {
+        <div class="contract-text-assignments">
+            <div class="contract-text-option">"ASSIGNMENTS"</div>
+            <ol class="contract-text-list" start=offset + 1>{items}</ol>
+            <div style="display: flex; gap: 0.5rem;">
+                <button
+                    class="btn btn-outline-secondary btn-sm"
+                    on:click=move |_| set_offset.update(|o| *o = o.saturating_sub(ASSIGNMENTS_PER_PAGE))
+                    disabled=offset == 0
+                >
+                    "Previous"
+                </button>
+                <button
+                    class="btn btn-outline-secondary btn-sm"
+                    on:click=move |_| set_offset.update(|o| *o += ASSIGNMENTS_PER_PAGE)
+                    disabled=!page.has_more
+                >
+                    "Next"
+                </button>
+            </div>
+        </div>
+    }
+}
+
 /// Displays promise resolution as hierarchical text/list view.
 #[component]
 pub fn ContractText(
@@ -151,6 +248,15 @@ pub fn ContractText(
     selected_component: ReadSignal<String>,
     selected_behavior: ReadSignal<String>,
 ) -> impl IntoView {
+    // Which page of assignments is shown, back to the first one whenever
+    // the selection changes
+    let (offset, set_offset) = signal(0usize);
+    Effect::new(move |_| {
+        selected_component.track();
+        selected_behavior.track();
+        set_offset.set(0);
+    });
+
     let content = move || {
         let tracker_opt = tracker.get();
         let component = selected_component.get();
@@ -207,13 +313,21 @@ pub fn ContractText(
         }
 
         // Resolve the behavior and render the result
-        let resolution = pt.resolve(&behavior);
+        let resolution = pt.resolve_graph(&behavior);
         let contract_text = render_contract_text(&component, &resolution);
+        let blocked_by = resolution.to_blocked_by_string().map(|b| {
+            view! { <div class="contract-text-blocked-by">{b}</div> }
+        });
+        let page = resolution.get_assignments(offset.get(), ASSIGNMENTS_PER_PAGE);
+        let assignments = (!page.assignments.is_empty() || page.offset > 0)
+            .then(|| render_assignments(page, set_offset));
 
         view! {
             <div class="card">
                 <div class="card-body contract-text-card">
+                    {blocked_by}
                     <ul class="contract-text-list">{contract_text}</ul>
+                    {assignments}
                 </div>
             </div>
         }
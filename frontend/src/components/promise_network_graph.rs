@@ -49,6 +49,14 @@ fn NetworkLegend() -> impl IntoView {
                 <span style="color: #C62828;">"┅┅"</span>
                 <span>"Relationship (unsatisfied)"</span>
             </div>
+            <div class="promise-network-legend-item">
+                <span style="color: #F57C00;">"┈┈"</span>
+                <span>"Relationship (cycle)"</span>
+            </div>
+            <div class="promise-network-legend-item">
+                <span style="color: #90A4AE;">"──"</span>
+                <span>"Contains (click a ringed SuperAgent to expand)"</span>
+            </div>
         </div>
     }
 }
@@ -9,13 +9,18 @@ const COLORS = {
     component: '#1976D2',    // Blue for components
     satisfied: '#4CAF50',    // Green for satisfied
     unsatisfied: '#C62828',  // Red for unsatisfied
+    cyclic: '#F57C00',       // Orange for conditions that loop back
+    contains: '#90A4AE',     // Grey for SuperAgent members
 };
 
 /**
  * Create and render a force-directed graph
  * @param {string} containerId - ID of the container element
- * @param {Array} nodes - Array of node objects {id, label, type, satisfied}
- * @param {Array} links - Array of link objects {source, target, type, satisfied}
+ * @param {Array} nodes - Array of node objects {id, label, type, satisfied, parent}
+ * @param {Array} links - Array of link objects {source, target, type, satisfied, cyclic, criticality}
+ *
+ * Nodes with a parent are members of a SuperAgent component. They start out
+ * hidden; clicking the component shows or hides them.
  */
 export function create_force_graph(containerId, nodes, links) {
     const container = document.getElementById(containerId);
@@ -66,9 +71,21 @@ export function create_force_graph(containerId, nodes, links) {
         target: nodeMap.get(l.target) || l.target,
     }));
 
+    // SuperAgent components that have members, and those currently expanded
+    const groups = new Set(graphNodes.filter(n => n.parent).map(n => n.parent));
+    const expanded = new Set();
+    const isVisible = node => {
+        for (let p = node.parent; p; p = nodeMap.get(p)?.parent) {
+            if (!expanded.has(p)) return false;
+        }
+        return true;
+    };
+    let visibleNodes = graphNodes.filter(isVisible);
+    let visibleLinks = graphLinks.filter(l => isVisible(l.source) && isVisible(l.target));
+
     // Create D3 force simulation
-    const simulation = d3.forceSimulation(graphNodes)
-        .force('link', d3.forceLink(graphLinks)
+    const simulation = d3.forceSimulation(visibleNodes)
+        .force('link', d3.forceLink(visibleLinks)
             .id(d => d.id)
             .distance(100)
             .strength(0.5))
@@ -87,18 +104,29 @@ export function create_force_graph(containerId, nodes, links) {
         ctx.clearRect(0, 0, width, height);
 
         // Draw links
-        graphLinks.forEach(link => {
+        visibleLinks.forEach(link => {
             if (!link.source.x || !link.target.x) return;
 
             ctx.beginPath();
             ctx.moveTo(link.source.x, link.source.y);
             ctx.lineTo(link.target.x, link.target.y);
 
-            ctx.strokeStyle = link.satisfied ? COLORS.satisfied : COLORS.unsatisfied;
+            const linkColor = link.type === 'contains'
+                ? COLORS.contains
+                : (link.cyclic
+                    ? COLORS.cyclic
+                    : (link.satisfied ? COLORS.satisfied : COLORS.unsatisfied));
+            ctx.strokeStyle = linkColor;
             ctx.lineWidth = link.satisfied ? 2 : 1.5;
-
-            // Dashed line for unsatisfied
-            if (!link.satisfied) {
+            // Fade the wants a component can do without
+            ctx.globalAlpha = link.criticality === 'optional'
+                ? 0.4
+                : (link.criticality === 'degraded' ? 0.7 : 1);
+
+            // Dotted line for cycles, dashed line for unsatisfied
+            if (link.cyclic) {
+                ctx.setLineDash([2, 3]);
+            } else if (!link.satisfied) {
                 ctx.setLineDash([5, 5]);
             } else {
                 ctx.setLineDash([]);
@@ -129,12 +157,13 @@ export function create_force_graph(containerId, nodes, links) {
                 arrowY - arrowLength * Math.sin(angle + arrowWidth)
             );
             ctx.closePath();
-            ctx.fillStyle = link.satisfied ? COLORS.satisfied : COLORS.unsatisfied;
+            ctx.fillStyle = linkColor;
             ctx.fill();
+            ctx.globalAlpha = 1;
         });
 
         // Draw nodes
-        graphNodes.forEach(node => {
+        visibleNodes.forEach(node => {
             if (!node.x || !node.y) return;
 
             const radius = node.type === 'component' ? 12 : 10;
@@ -155,6 +184,17 @@ export function create_force_graph(containerId, nodes, links) {
             ctx.lineWidth = 2;
             ctx.stroke();
 
+            // Dashed ring around SuperAgents that can be expanded
+            if (groups.has(node.id)) {
+                ctx.beginPath();
+                ctx.arc(node.x, node.y, radius + 4, 0, 2 * Math.PI);
+                ctx.strokeStyle = COLORS.component;
+                ctx.lineWidth = 1;
+                ctx.setLineDash(expanded.has(node.id) ? [] : [3, 3]);
+                ctx.stroke();
+                ctx.setLineDash([]);
+            }
+
             // Draw label
             ctx.fillStyle = '#333';
             ctx.font = '11px sans-serif';
@@ -167,6 +207,31 @@ export function create_force_graph(containerId, nodes, links) {
     // Update on each tick
     simulation.on('tick', render);
 
+    // Clicking a SuperAgent shows or hides its members
+    canvas.addEventListener('click', event => {
+        const bounds = canvas.getBoundingClientRect();
+        const x = (event.clientX - bounds.left) * (canvas.width / bounds.width);
+        const y = (event.clientY - bounds.top) * (canvas.height / bounds.height);
+        const clicked = visibleNodes.find(n =>
+            groups.has(n.id) && Math.hypot(n.x - x, n.y - y) <= 16);
+        if (!clicked) return;
+        if (expanded.has(clicked.id)) {
+            expanded.delete(clicked.id);
+        } else {
+            expanded.add(clicked.id);
+            // members start out next to their SuperAgent
+            graphNodes.filter(n => n.parent === clicked.id).forEach(n => {
+                n.x = clicked.x + (Math.random() - 0.5) * 20;
+                n.y = clicked.y + (Math.random() - 0.5) * 20;
+            });
+        }
+        visibleNodes = graphNodes.filter(isVisible);
+        visibleLinks = graphLinks.filter(l => isVisible(l.source) && isVisible(l.target));
+        simulation.nodes(visibleNodes);
+        simulation.force('link').links(visibleLinks);
+        simulation.alpha(0.5).restart();
+    });
+
     // Handle resize
     function handleResize() {
         const newRect = container.getBoundingClientRect();
@@ -0,0 +1,124 @@
+//! Capacity allocation.
+//!
+//! Provides can promise a `capacity` (e.g. connections or QPS) and wants a
+//! `demand` on it. An `Allocation` hands every satisfied want to one of the
+//! providers that satisfy it and adds up the demand on each of them, so that
+//! a provider that is promised to more consumers than it can handle shows up
+//! as oversubscribed rather than as satisfying all of them.
+
+use serde::{Deserialize, Serialize};
+
+/// A want that was handed to a provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consumer {
+    pub agent_name: String,
+    pub demand: u64,
+}
+
+/// Everything handed to one capacity-limited provide of an agent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderLoad {
+    pub agent_name: String,
+    pub behavior_name: String,
+    pub capacity: u64,
+    /// In the order they were handed out
+    pub consumers: Vec<Consumer>,
+}
+
+impl ProviderLoad {
+    pub fn get_demand(&self) -> u64 {
+        self.consumers.iter().map(|c| c.demand).sum()
+    }
+
+    pub fn get_remaining(&self) -> i64 {
+        self.capacity as i64 - self.get_demand() as i64
+    }
+
+    pub fn is_oversubscribed(&self) -> bool {
+        self.get_demand() > self.capacity
+    }
+}
+
+impl std::fmt::Display for ProviderLoad {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} provides {}: {} of {} used by {} consumer{}",
+            self.agent_name,
+            self.behavior_name,
+            self.get_demand(),
+            self.capacity,
+            self.consumers.len(),
+            if self.consumers.len() == 1 { "" } else { "s" }
+        )?;
+        if self.is_oversubscribed() {
+            write!(f, " (oversubscribed)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The load on every capacity-limited provide, sorted by behavior and then
+/// agent name. Providers without a capacity can take any demand, so they
+/// are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Allocation {
+    pub loads: Vec<ProviderLoad>,
+}
+
+impl Allocation {
+    pub fn get_load(&self, agent_name: &str, behavior_name: &str) -> Option<&ProviderLoad> {
+        self.loads
+            .iter()
+            .find(|l| l.agent_name == agent_name && l.behavior_name == behavior_name)
+    }
+
+    pub fn get_oversubscribed(&self) -> Vec<&ProviderLoad> {
+        self.loads
+            .iter()
+            .filter(|l| l.is_oversubscribed())
+            .collect()
+    }
+
+    pub fn is_oversubscribed(&self) -> bool {
+        self.loads.iter().any(|l| l.is_oversubscribed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let mut load = ProviderLoad {
+            agent_name: String::from("pg"),
+            behavior_name: String::from("db"),
+            capacity: 3,
+            consumers: vec![Consumer {
+                agent_name: String::from("app"),
+                demand: 2,
+            }],
+        };
+        assert_eq!(load.get_remaining(), 1);
+        assert!(!load.is_oversubscribed());
+        assert_eq!(
+            load.to_string(),
+            "pg provides db: 2 of 3 used by 1 consumer"
+        );
+        load.consumers.push(Consumer {
+            agent_name: String::from("web"),
+            demand: 2,
+        });
+        assert_eq!(load.get_remaining(), -1);
+        assert_eq!(
+            load.to_string(),
+            "pg provides db: 4 of 3 used by 2 consumers (oversubscribed)"
+        );
+        let allocation = Allocation { loads: vec![load] };
+        assert!(allocation.is_oversubscribed());
+        assert_eq!(allocation.get_oversubscribed().len(), 1);
+        assert!(allocation.get_load("pg", "db").is_some());
+        assert!(allocation.get_load("pg", "cache").is_none());
+    }
+}
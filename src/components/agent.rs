@@ -1,4 +1,7 @@
-use crate::components::behavior::Behavior;
+use crate::components::behavior::{Behavior, Criticality};
+use crate::components::location::{Location, Provenance};
+use crate::placement::Placement;
+use crate::selector::Labels;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -12,6 +15,9 @@ pub struct IntermediateAgent {
     #[serde(skip_serializing_if = "String::is_empty")]
     pub comment: String,
     #[serde(default)]
+    #[serde(skip_serializing_if = "Labels::is_empty")]
+    pub labels: Labels,
+    #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub provides: Vec<Behavior>,
     #[serde(default)]
@@ -26,36 +32,28 @@ impl From<Agent> for IntermediateAgent {
     fn from(value: Agent) -> Self {
         let mut conditions = value.get_conditions().into_iter().collect::<Vec<String>>();
         conditions.sort();
-        let mut global_conditions = vec![];
-        for c in conditions {
-            let mut inuse = true;
-            for p in value.provides.iter() {
-                if !p.get_conditions().contains(&c) {
-                    inuse = false;
-                    break;
-                }
-            }
-            if inuse {
-                global_conditions.push(c);
-            }
-        }
+        // a condition is global if every provide needs it, whichever of its
+        // anyOf groups is used
+        let global_conditions: Vec<String> = conditions
+            .into_iter()
+            .filter(|c| value.provides.iter().all(|p| p.requires(c)))
+            .collect();
         let provides = value
             .provides
             .iter()
             .map(|p| {
-                let conditions = p
-                    .get_conditions()
-                    .iter()
-                    .filter(|c| !global_conditions.contains(c))
-                    .cloned()
-                    .collect();
-                Behavior::new(p.get_name().clone()).with_conditions(conditions)
+                let mut p = p.clone();
+                for c in &global_conditions {
+                    p.remove_condition(c);
+                }
+                p
             })
             .collect::<Vec<Behavior>>();
 
         IntermediateAgent {
             name: value.name,
             comment: value.comment,
+            labels: value.labels,
             provides: provides,
             wants: value.wants,
             global_conditions: global_conditions,
@@ -71,11 +69,18 @@ pub struct Agent {
     #[serde(default)]
     comment: String,
 
+    // Free form metadata (owner team, tier, ...), see `Selector`
+    #[serde(default)]
+    labels: Labels,
+
     #[serde(default)]
     provides: Vec<Behavior>,
 
     #[serde(default)]
     wants: Vec<Behavior>,
+
+    #[serde(skip)]
+    provenance: Provenance,
 }
 
 impl TryFrom<IntermediateAgent> for Agent {
@@ -92,8 +97,10 @@ impl TryFrom<IntermediateAgent> for Agent {
         Ok(Agent {
             name: value.name,
             comment: value.comment,
+            labels: value.labels,
             provides: provides,
             wants: value.wants,
+            provenance: Provenance::default(),
         })
     }
 }
@@ -103,8 +110,10 @@ impl Agent {
         Agent {
             name: name,
             comment: String::from(""),
+            labels: Labels::new(),
             provides: vec![],
             wants: vec![],
+            provenance: Provenance::default(),
         }
     }
 
@@ -122,12 +131,63 @@ impl Agent {
         self
     }
 
+    pub fn with_labels(mut self, labels: Labels) -> Agent {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_label(mut self, key: &str, value: &str) -> Agent {
+        self.labels.insert(String::from(key), String::from(value));
+        self
+    }
+
+    pub fn with_location(mut self, location: Location) -> Agent {
+        self.provenance.add(location);
+        self
+    }
+
     // Does not provide a global_conditions since that could be modified after the fact
 
     pub fn get_name(&self) -> &String {
         &self.name
     }
 
+    pub fn get_comment(&self) -> &String {
+        &self.comment
+    }
+
+    pub fn get_labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    pub fn get_label(&self, key: &str) -> Option<&String> {
+        self.labels.get(key)
+    }
+
+    // Adds the labels this agent doesn't have yet; existing ones win
+    pub(crate) fn add_labels(&mut self, labels: &Labels) {
+        for (k, v) in labels {
+            self.labels.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+    }
+
+    /// Every definition that went into this agent, including merged ones
+    pub fn get_provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    pub(crate) fn add_provenance(&mut self, provenance: &Provenance) {
+        self.provenance.extend(provenance);
+    }
+
+    pub(crate) fn get_provides_mut(&mut self) -> &mut [Behavior] {
+        &mut self.provides
+    }
+
+    pub(crate) fn get_wants_mut(&mut self) -> &mut [Behavior] {
+        &mut self.wants
+    }
+
     pub fn is_wants_empty(&self) -> bool {
         self.wants.len() == 0
     }
@@ -148,7 +208,7 @@ impl Agent {
     pub fn get_conditions(&self) -> HashSet<String> {
         let mut ret = HashSet::new();
         for p in &self.provides {
-            for c in p.get_conditions() {
+            for c in p.get_all_conditions() {
                 ret.insert(c.clone());
             }
         }
@@ -163,6 +223,53 @@ impl Agent {
         ret
     }
 
+    /// The most critical level `behavior_name` is wanted at, if it is wanted
+    pub fn get_want_criticality(&self, behavior_name: &str) -> Option<Criticality> {
+        self.wants
+            .iter()
+            .filter(|w| w.get_name() == behavior_name)
+            .map(|w| w.get_criticality())
+            .min()
+    }
+
+    /// The largest demand `behavior_name` is wanted with, if it is wanted
+    pub fn get_want_demand(&self, behavior_name: &str) -> Option<u64> {
+        self.wants
+            .iter()
+            .filter(|w| w.get_name() == behavior_name)
+            .map(|w| w.get_demand())
+            .max()
+    }
+
+    /// The version requirement `behavior_name` is wanted with, if any
+    pub fn get_want_version(&self, behavior_name: &str) -> Option<&str> {
+        self.wants
+            .iter()
+            .filter(|w| w.get_name() == behavior_name)
+            .find_map(|w| w.get_version())
+    }
+
+    /// Where the provider of `behavior_name` has to be, if anywhere in
+    /// particular
+    pub fn get_want_placement(&self, behavior_name: &str) -> Option<Placement> {
+        self.wants
+            .iter()
+            .filter(|w| w.get_name() == behavior_name)
+            .map(|w| w.get_placement())
+            .find(|p| !p.is_empty())
+    }
+
+    /// The largest capacity `behavior_name` is provided with. None if it is
+    /// not provided or no capacity is given (i.e. it is unlimited).
+    pub fn get_provide_capacity(&self, behavior_name: &str) -> Option<u64> {
+        self.provides
+            .iter()
+            .filter(|p| p.get_name() == behavior_name)
+            .map(|p| p.get_capacity())
+            .max()
+            .flatten()
+    }
+
     pub fn get_provides(&self, behavior_name: &str) -> Option<HashSet<Behavior>> {
         let mut ret = HashSet::new();
         for b in self.provides.iter() {
@@ -177,6 +284,15 @@ impl Agent {
         }
     }
 
+    // In declaration order and without copying, for building lookup indexes
+    pub(crate) fn iter_provides(&self) -> impl Iterator<Item = &Behavior> {
+        self.provides.iter()
+    }
+
+    pub(crate) fn iter_wants(&self) -> impl Iterator<Item = &Behavior> {
+        self.wants.iter()
+    }
+
     pub fn get_all_provides(&self) -> HashSet<Behavior> {
         let mut ret = HashSet::new();
         for b in self.provides.iter() {
@@ -189,7 +305,7 @@ impl Agent {
         let mut ret = HashSet::new();
         for p in &self.provides {
             ret.insert(p.get_name().clone());
-            for c in p.get_conditions() {
+            for c in p.get_all_conditions() {
                 ret.insert(c.clone());
             }
         }
@@ -199,54 +315,98 @@ impl Agent {
         ret
     }
 
+    // Duplicates are only kept once, but remember where each copy came from.
+    // Labels already set are kept.
     pub fn merge(&mut self, other: &Agent) {
+        self.provenance.extend(&other.provenance);
+        self.add_labels(&other.labels);
         for p in &other.provides {
-            if self.provides.contains(p) {
-                continue;
+            match self.provides.iter_mut().find(|existing| *existing == p) {
+                Some(existing) => existing.add_provenance(p.get_provenance()),
+                None => self.provides.push(p.clone()),
             }
-            self.provides.push(p.clone());
         }
         for w in &other.wants {
-            if self.wants.contains(w) {
-                continue;
+            match self.wants.iter_mut().find(|existing| *existing == w) {
+                Some(existing) => existing.add_provenance(w.get_provenance()),
+                None => self.wants.push(w.clone()),
             }
-            self.wants.push(w.clone());
         }
     }
 
     // for each condition that is internally provided, replace it with the conditions required to internally provide it
+    //
+    // A condition that several internal provides (or anyOf groups of one)
+    // can meet expands into one alternative for each of them, and each
+    // alternative becomes a provide of its own. A condition that loops back
+    // onto a behavior that is already being expanded can't be met
+    // internally, so that alternative is dropped; a behavior with no
+    // alternative left isn't provided at all.
     pub fn reduce(&mut self) {
         let internal_provides: HashSet<String> =
             self.provides.iter().map(|p| p.get_name().clone()).collect();
-        let mut todo_provides = self.provides.clone();
         let mut reduced_provides = vec![];
-        while todo_provides.len() > 0 {
-            let p = todo_provides.remove(0);
+        for p in &self.provides {
             if p.is_unconditional() || p.has_none_of_these_conditions(&internal_provides) {
-                reduced_provides.push(p);
+                reduced_provides.push(p.clone());
                 continue;
             }
-            // Otherwise, one or more conditions need to be expanded
-            // If a condition is provided internally by multiple options, technically should expand each of them.
-            // Right now, just expand the first one.
-            let mut new_conditions: HashSet<String> = HashSet::new();
-            for c in p.get_conditions() {
+            for alternative in
+                self.expand_alternatives(p, &internal_provides, &mut vec![p.get_name().clone()])
+            {
+                let mut reduced = Behavior::new_with_conditions(p.get_name().clone(), alternative);
+                reduced.inherit(p);
+                reduced_provides.push(reduced);
+            }
+        }
+        reduced_provides.sort();
+        reduced_provides.dedup();
+        self.provides = reduced_provides;
+    }
+
+    // The sets of external conditions under which `p` can be provided.
+    // `path` holds the internal behaviors currently being expanded.
+    fn expand_alternatives(
+        &self,
+        p: &Behavior,
+        internal_provides: &HashSet<String>,
+        path: &mut Vec<String>,
+    ) -> Vec<Vec<String>> {
+        let mut alternatives = vec![];
+        for alternative in p.get_alternatives() {
+            let mut expanded: Vec<Vec<String>> = vec![vec![]];
+            for c in alternative {
+                if path.contains(&c) {
+                    expanded.clear();
+                    break;
+                }
                 // If this condition is not provided internally, just pass it through as is
                 if !internal_provides.contains(&c) {
-                    new_conditions.insert(c.clone());
+                    expanded.iter_mut().for_each(|e| e.push(c.clone()));
                     continue;
                 }
-                for ro in self.provides.iter().filter(|x| x.get_name() == &c).take(1) {
-                    new_conditions.extend(ro.get_conditions());
-                }
+                path.push(c.clone());
+                let options: Vec<Vec<String>> = self
+                    .provides
+                    .iter()
+                    .filter(|x| x.get_name() == &c)
+                    .flat_map(|x| self.expand_alternatives(x, internal_provides, path))
+                    .collect();
+                path.pop();
+                expanded = expanded
+                    .iter()
+                    .flat_map(|e| {
+                        options.iter().map(move |o| {
+                            let mut e = e.clone();
+                            e.extend(o.iter().cloned());
+                            e
+                        })
+                    })
+                    .collect();
             }
-            todo_provides.push(Behavior::new_with_conditions(
-                p.get_name().clone(),
-                new_conditions.iter().map(|x| x.clone()).collect(),
-            ));
+            alternatives.extend(expanded);
         }
-        reduced_provides.sort();
-        self.provides = reduced_provides;
+        minimal_alternatives(alternatives)
     }
 
     pub fn make_instance(
@@ -256,16 +416,45 @@ impl Agent {
         conditions_tags: &String,
     ) -> Agent {
         Agent::new(instance_name.clone())
+            .with_labels(self.labels.clone())
             .with_provides(
                 self.provides
                     .iter()
                     .map(|p| p.make_instance(provides_tags, conditions_tags))
                     .collect(),
             )
-            .with_wants(self.wants.clone())
+            .with_wants(
+                self.wants
+                    .iter()
+                    .map(|w| w.make_want_instance(provides_tags))
+                    .collect(),
+            )
     }
 }
 
+// Drops repeated conditions within an alternative, then alternatives that
+// need everything another one needs (the first of equal ones is kept)
+fn minimal_alternatives(alternatives: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let alternatives: Vec<Vec<String>> = alternatives
+        .into_iter()
+        .map(|mut a| {
+            let mut seen = HashSet::new();
+            a.retain(|c| seen.insert(c.clone()));
+            a
+        })
+        .collect();
+    alternatives
+        .iter()
+        .enumerate()
+        .filter(|(i, a)| {
+            !alternatives.iter().enumerate().any(|(j, b)| {
+                j != *i && b.iter().all(|c| a.contains(c)) && (b.len() < a.len() || j < *i)
+            })
+        })
+        .map(|(_, a)| a.clone())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -411,6 +600,8 @@ globalConditions:
         let a = Agent {
             name: String::from("foo"),
             comment: String::from(""),
+            labels: Labels::new(),
+            provenance: Provenance::default(),
             provides: vec![
                 Behavior::new(String::from("p1")).with_conditions(vec![String::from("gc1")]),
                 Behavior::new(String::from("p2")).with_conditions(vec![
@@ -429,6 +620,116 @@ globalConditions:
         assert_eq!(s, expected);
     }
 
+    #[test]
+    fn to_yaml_keeps_provide_comments() {
+        let yaml = "name: foo\nprovides:\n- name: p1\n  comment: first\n- name: p2\nglobalConditions:\n- gc1\n";
+        let a: Agent = serde_yaml::from_str(yaml).expect("Unable to parse");
+        let s = serde_yaml::to_string(&a).expect("Unable to serialize");
+        assert_eq!(s, yaml);
+    }
+
+    #[test]
+    fn to_yaml_with_any_of() {
+        // gc1 is needed whichever group is used, so it is global
+        let a: Agent = serde_yaml::from_str(
+            "name: foo
+provides:
+- name: p1
+  anyOf:
+  - [gc1, cache]
+  - [gc1, local]
+- name: p2
+  conditions: [gc1]
+",
+        )
+        .expect("Unable to parse");
+        let s = serde_yaml::to_string(&a).expect("Unable to serialize");
+        let expected = "name: foo\nprovides:\n- name: p1\n  anyOf:\n  - - cache\n  - - local\n- name: p2\nglobalConditions:\n- gc1\n";
+        assert_eq!(s, expected);
+        let b: Agent = serde_yaml::from_str(&s).unwrap();
+        assert_eq!(b.get_conditions(), a.get_conditions());
+        assert_eq!(
+            b.provides[0].get_alternatives(),
+            vec![vec!["gc1", "cache"], vec!["gc1", "local"]]
+        );
+    }
+
+    #[test]
+    fn test_want_criticality() {
+        let mut a: Agent = serde_yaml::from_str(
+            "name: foo
+wants:
+  - name: w1
+    criticality: optional
+  - name: w2
+",
+        )
+        .unwrap();
+        assert_eq!(a.get_want_criticality("w1"), Some(Criticality::Optional));
+        assert_eq!(a.get_want_criticality("w2"), Some(Criticality::Required));
+        assert_eq!(a.get_want_criticality("w3"), None);
+        a.merge(&Agent::build("foo").with_wants(vec![
+            Behavior::build("w1").with_criticality(Criticality::Degraded),
+        ]));
+        assert_eq!(a.get_want_criticality("w1"), Some(Criticality::Degraded));
+    }
+
+    #[test]
+    fn test_labels() {
+        let mut a: Agent = serde_yaml::from_str(
+            "name: foo
+labels:
+  team: payments
+  tier: web
+",
+        )
+        .unwrap();
+        assert_eq!(a.get_label("team"), Some(&String::from("payments")));
+        assert_eq!(
+            serde_yaml::to_string(&a).unwrap(),
+            "name: foo\nlabels:\n  team: payments\n  tier: web\n"
+        );
+        a.merge(
+            &Agent::build("foo")
+                .with_label("team", "other")
+                .with_label("env", "prod"),
+        );
+        assert_eq!(a.get_label("team"), Some(&String::from("payments")));
+        assert_eq!(a.get_label("env"), Some(&String::from("prod")));
+        let i = a.make_instance(&String::from("i1"), &String::from(""), &String::from(""));
+        assert_eq!(i.get_labels(), a.get_labels());
+    }
+
+    #[test]
+    fn test_capacity_and_demand() {
+        let mut a: Agent = serde_yaml::from_str(
+            "name: foo
+provides:
+  - name: db
+    capacity: 10
+    version: 2.0.0
+    conditions: [disk]
+  - name: disk
+wants:
+  - name: w1
+    demand: 3
+  - name: w2
+",
+        )
+        .unwrap();
+        assert_eq!(a.get_want_demand("w1"), Some(3));
+        assert_eq!(a.get_want_demand("w2"), Some(1));
+        assert_eq!(a.get_want_demand("w3"), None);
+        assert_eq!(a.get_provide_capacity("disk"), None);
+        a.reduce();
+        assert_eq!(a.get_provide_capacity("db"), Some(10));
+        assert_eq!(
+            a.provides[0].get_version(),
+            Some("2.0.0"),
+            "reduced provides keep their version"
+        );
+    }
+
     #[test]
     fn get_conditions() {
         let a: Agent = serde_yaml::from_str(
@@ -564,16 +865,148 @@ provides:
         )
         .unwrap();
         a.reduce();
+        // b2 is provided internally in two ways, so b1 can be too
         assert_eq!(
             a.provides,
             vec!(
                 Behavior::build("b1").with_conditions(vec!(String::from("b3"))),
+                Behavior::build("b1").with_conditions(vec!(String::from("b4"))),
                 Behavior::build("b2").with_conditions(vec!(String::from("b3"))),
                 Behavior::build("b2").with_conditions(vec!(String::from("b4"))),
             )
         );
     }
 
+    #[test]
+    fn test_reduce_multiple_providers() {
+        // two replicas of the database, one of them without a condition
+        let mut a: Agent = serde_yaml::from_str(
+            "name: foo
+provides:
+  - name: api
+    conditions: [db, auth]
+  - name: db
+    conditions: [disk]
+  - name: db
+  - name: auth
+    conditions: [db, ldap]
+",
+        )
+        .unwrap();
+        a.reduce();
+        // needing disk on top of nothing is not a separate alternative
+        assert_eq!(
+            a.provides,
+            vec!(
+                Behavior::build("api").with_conditions(vec![String::from("ldap")]),
+                Behavior::build("auth").with_conditions(vec![String::from("ldap")]),
+                Behavior::build("db"),
+                Behavior::build("db").with_conditions(vec![String::from("disk")]),
+            )
+        );
+
+        let mut a: Agent = serde_yaml::from_str(
+            "name: foo
+provides:
+  - name: api
+    conditions: [db]
+  - name: db
+    conditions: [disk, net]
+  - name: db
+    conditions: [net, disk]
+  - name: db
+    conditions: [s3]
+",
+        )
+        .unwrap();
+        a.reduce();
+        assert_eq!(
+            a.provides[..2],
+            [
+                Behavior::build("api")
+                    .with_conditions(vec![String::from("disk"), String::from("net")]),
+                Behavior::build("api").with_conditions(vec![String::from("s3")]),
+            ],
+            "alternatives that are the same set of conditions are only listed once"
+        );
+    }
+
+    #[test]
+    fn test_reduce_loop() {
+        let mut a: Agent = serde_yaml::from_str(
+            "name: foo
+provides:
+  - name: b1
+    conditions: [b2]
+  - name: b2
+    conditions: [b3, ext]
+  - name: b3
+    conditions: [b2]
+  - name: b3
+    conditions: [s3]
+  - name: b4
+    conditions: [b5]
+  - name: b5
+    conditions: [b4]
+",
+        )
+        .unwrap();
+        a.reduce();
+        // going around the loop between b2 and b3 never gets anywhere, so
+        // only the way out through s3 is left, and b4 and b5 can't be
+        // provided at all
+        assert_eq!(
+            a.provides,
+            vec!(
+                Behavior::build("b1")
+                    .with_conditions(vec![String::from("s3"), String::from("ext")]),
+                Behavior::build("b2")
+                    .with_conditions(vec![String::from("s3"), String::from("ext")]),
+                Behavior::build("b3").with_conditions(vec![String::from("s3")]),
+            )
+        );
+        assert!(a
+            .provides
+            .iter()
+            .all(|p| !p.get_all_conditions().contains(p.get_name())));
+    }
+
+    #[test]
+    fn test_reduce_any_of() {
+        let mut a: Agent = serde_yaml::from_str(
+            "name: foo
+provides:
+  - name: b1
+    conditions: [b2]
+    anyOf:
+      - [cache]
+      - [b3]
+  - name: b2
+    conditions: [db]
+  - name: b3
+    anyOf:
+      - [disk]
+      - [s3]
+",
+        )
+        .unwrap();
+        a.reduce();
+        // one provide of b1 for each way of meeting its anyOf
+        assert_eq!(
+            a.provides,
+            vec!(
+                Behavior::build("b1")
+                    .with_conditions(vec![String::from("db"), String::from("cache")]),
+                Behavior::build("b1")
+                    .with_conditions(vec![String::from("db"), String::from("disk")]),
+                Behavior::build("b1").with_conditions(vec![String::from("db"), String::from("s3")]),
+                Behavior::build("b2").with_conditions(vec!(String::from("db"))),
+                Behavior::build("b3")
+                    .with_any_of(vec![vec![String::from("disk")], vec![String::from("s3")],]),
+            )
+        );
+    }
+
     #[test]
     fn test_make_instance() {
         let a = Agent::new(String::from("a1"))
@@ -613,6 +1046,7 @@ provides:
         let ia = IntermediateAgent {
             name: String::from("a1"),
             comment: String::from(""),
+            labels: Labels::new(),
             provides: vec![Behavior::new(String::from("p1"))
                 .with_conditions(vec![String::from("p1c1"), String::from("p1c2")])],
             wants: vec![Behavior::new(String::from("w1"))],
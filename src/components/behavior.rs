@@ -1,7 +1,58 @@
+use crate::components::behavior_id::BehaviorId;
+use crate::components::location::{Location, Provenance};
+use crate::placement::Placement;
+use crate::selector::Labels;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 
+/// How badly an agent needs one of its wants, most critical first
+#[derive(
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Deserialize,
+    Serialize,
+    Clone,
+    Copy,
+    Hash,
+    JsonSchema,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Criticality {
+    /// The agent can't work without it
+    #[default]
+    Required,
+    /// The agent works without it, but not fully
+    Degraded,
+    /// Nice to have
+    Optional,
+}
+
+impl Criticality {
+    pub fn is_required(&self) -> bool {
+        *self == Criticality::Required
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Criticality::Required => "required",
+            Criticality::Degraded => "degraded",
+            Criticality::Optional => "optional",
+        }
+    }
+}
+
+impl std::fmt::Display for Criticality {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(
     Debug, PartialEq, Eq, Deserialize, Serialize, Clone, Hash, JsonSchema, PartialOrd, Ord,
 )]
@@ -15,6 +66,71 @@ pub struct Behavior {
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     conditions: Vec<String>,
+
+    // Alternative sets of conditions, one of which must be met on top of
+    // `conditions`
+    #[serde(default)]
+    #[serde(rename = "anyOf")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    any_of: Vec<Vec<String>>,
+
+    // Only used for wants: behaviors the wanting agent has to provide itself
+    // for the want to apply, on top of `conditions`
+    #[serde(default)]
+    #[serde(rename = "ownConditions")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    own_conditions: Vec<String>,
+
+    // Only used for wants
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Criticality::is_required")]
+    criticality: Criticality,
+
+    // Only used for provides: how much of it can be handed out (e.g.
+    // connections or QPS), unlimited if not given
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capacity: Option<u64>,
+
+    // Only used for wants: how much of the provider's capacity it takes up
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    demand: Option<u64>,
+
+    // On provides, the semver version it is provided at (e.g. 2.3.0). On
+    // wants, the version requirement (e.g. ^2.1).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+
+    // Condition -> version requirement it has to be provided at
+    #[serde(default)]
+    #[serde(rename = "conditionVersions")]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    condition_versions: BTreeMap<String, String>,
+
+    // Only used for wants: labels the provider must share with the wanting
+    // agent (e.g. zone)
+    #[serde(default)]
+    #[serde(rename = "sameAs")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    same_as: Vec<String>,
+
+    // Only used for wants: labels the provider must have
+    #[serde(default)]
+    #[serde(rename = "match")]
+    #[serde(skip_serializing_if = "Labels::is_empty")]
+    match_labels: Labels,
+
+    // Condition -> where it has to be provided, relative to the providing
+    // agent
+    #[serde(default)]
+    #[serde(rename = "conditionPlacements")]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    condition_placements: BTreeMap<String, Placement>,
+
+    #[serde(skip)]
+    provenance: Provenance,
 }
 
 impl Behavior {
@@ -23,6 +139,17 @@ impl Behavior {
             name: name,
             comment: String::from(""),
             conditions: vec![],
+            any_of: vec![],
+            own_conditions: vec![],
+            criticality: Criticality::Required,
+            capacity: None,
+            demand: None,
+            version: None,
+            condition_versions: BTreeMap::new(),
+            same_as: vec![],
+            match_labels: Labels::new(),
+            condition_placements: BTreeMap::new(),
+            provenance: Provenance::default(),
         }
     }
 
@@ -31,9 +158,56 @@ impl Behavior {
             name: name,
             comment: String::from(""),
             conditions: conditions,
+            any_of: vec![],
+            own_conditions: vec![],
+            criticality: Criticality::Required,
+            capacity: None,
+            demand: None,
+            version: None,
+            condition_versions: BTreeMap::new(),
+            same_as: vec![],
+            match_labels: Labels::new(),
+            condition_placements: BTreeMap::new(),
+            provenance: Provenance::default(),
         }
     }
 
+    /// Builds a Behavior that can be provided under any one of `alternatives`
+    /// (each a full set of conditions). Conditions shared by every
+    /// alternative become plain conditions; the rest become `anyOf` groups.
+    pub fn from_alternatives(name: String, alternatives: Vec<Vec<String>>) -> Behavior {
+        let mut alternatives: Vec<Vec<String>> = alternatives
+            .into_iter()
+            .map(|mut a| {
+                let mut seen = HashSet::new();
+                a.retain(|c| seen.insert(c.clone()));
+                a
+            })
+            .collect();
+        alternatives.dedup();
+        let conditions: Vec<String> = match alternatives.first() {
+            Some(first) => first
+                .iter()
+                .filter(|c| alternatives.iter().all(|a| a.contains(c)))
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        let b = Behavior::new_with_conditions(name, conditions);
+        if alternatives.len() < 2 {
+            return b;
+        }
+        let groups = alternatives
+            .into_iter()
+            .map(|a| {
+                a.into_iter()
+                    .filter(|c| !b.conditions.contains(c))
+                    .collect()
+            })
+            .collect();
+        b.with_any_of(groups)
+    }
+
     pub fn build(name: &str) -> Behavior {
         Behavior::new(String::from(name))
     }
@@ -42,6 +216,104 @@ impl Behavior {
         self
     }
 
+    /// Replaces the `anyOf` groups. An empty group is always met, which makes
+    /// the whole `anyOf` met, so groups are dropped if there is one.
+    pub fn with_any_of(mut self, any_of: Vec<Vec<String>>) -> Behavior {
+        self.set_any_of(any_of);
+        self
+    }
+
+    fn set_any_of(&mut self, any_of: Vec<Vec<String>>) {
+        self.any_of = if any_of.iter().any(|g| g.is_empty()) {
+            vec![]
+        } else {
+            any_of
+        };
+    }
+
+    pub fn with_own_conditions(mut self, own_conditions: Vec<String>) -> Behavior {
+        self.own_conditions = own_conditions;
+        self
+    }
+
+    pub fn with_criticality(mut self, criticality: Criticality) -> Behavior {
+        self.criticality = criticality;
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: u64) -> Behavior {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    pub fn with_demand(mut self, demand: u64) -> Behavior {
+        self.demand = Some(demand);
+        self
+    }
+
+    pub fn with_version(mut self, version: &str) -> Behavior {
+        self.version = Some(String::from(version));
+        self
+    }
+
+    pub fn with_condition_version(mut self, condition: &str, requirement: &str) -> Behavior {
+        self.condition_versions
+            .insert(String::from(condition), String::from(requirement));
+        self
+    }
+
+    pub fn with_same_as(mut self, label: &str) -> Behavior {
+        self.same_as.push(String::from(label));
+        self
+    }
+
+    pub fn with_match_label(mut self, label: &str, value: &str) -> Behavior {
+        self.match_labels
+            .insert(String::from(label), String::from(value));
+        self
+    }
+
+    pub fn with_condition_placement(mut self, condition: &str, placement: Placement) -> Behavior {
+        self.condition_placements
+            .insert(String::from(condition), placement);
+        self
+    }
+
+    // Carries over what `Agent::reduce` keeps of `other` when it rebuilds
+    // its conditions
+    pub(crate) fn inherit(&mut self, other: &Behavior) {
+        self.capacity = other.capacity;
+        self.version = other.version.clone();
+        for (c, r) in &other.condition_versions {
+            self.condition_versions
+                .entry(c.clone())
+                .or_insert_with(|| r.clone());
+        }
+        for (c, p) in &other.condition_placements {
+            self.condition_placements
+                .entry(c.clone())
+                .or_insert_with(|| p.clone());
+        }
+        self.add_provenance(other.get_provenance());
+    }
+
+    pub fn with_location(mut self, location: Location) -> Behavior {
+        self.provenance.add(location);
+        self
+    }
+
+    pub fn get_provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    pub(crate) fn add_provenance(&mut self, provenance: &Provenance) {
+        self.provenance.extend(provenance);
+    }
+
+    pub(crate) fn add_location(&mut self, location: Location) {
+        self.provenance.add(location);
+    }
+
     pub fn add_condition(&mut self, c: String) {
         if self.conditions.contains(&c) {
             return;
@@ -55,6 +327,25 @@ impl Behavior {
         self.conditions.push(c)
     }
 
+    // Drops `c` everywhere it is required, including from `anyOf` groups
+    pub(crate) fn remove_condition(&mut self, c: &str) {
+        self.conditions.retain(|x| x != c);
+        let any_of = std::mem::take(&mut self.any_of)
+            .into_iter()
+            .map(|mut g| {
+                g.retain(|x| x != c);
+                g
+            })
+            .collect();
+        self.set_any_of(any_of);
+    }
+
+    /// Returns true if `c` has to be met whichever alternative is used
+    pub fn requires(&self, c: &str) -> bool {
+        self.conditions.iter().any(|x| x == c)
+            || (!self.any_of.is_empty() && self.any_of.iter().all(|g| g.iter().any(|x| x == c)))
+    }
+
     pub fn get_name(&self) -> &String {
         &self.name
     }
@@ -63,36 +354,168 @@ impl Behavior {
         self.conditions.clone()
     }
 
+    /// The behaviors a wanting agent has to provide itself for this want to
+    /// apply
+    pub fn get_own_conditions(&self) -> &[String] {
+        &self.own_conditions
+    }
+
+    pub fn get_criticality(&self) -> Criticality {
+        self.criticality
+    }
+
+    pub fn get_capacity(&self) -> Option<u64> {
+        self.capacity
+    }
+
+    /// The version of a provide, or the version requirement of a want
+    pub fn get_version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// The version requirement on condition `c`, if any
+    pub fn get_condition_version(&self, c: &str) -> Option<&str> {
+        self.condition_versions.get(c).map(|r| r.as_str())
+    }
+
+    pub fn get_condition_versions(&self) -> &BTreeMap<String, String> {
+        &self.condition_versions
+    }
+
+    /// Where the provider of this want has to be, relative to the wanting
+    /// agent
+    pub fn get_placement(&self) -> Placement {
+        Placement {
+            same_as: self.same_as.clone(),
+            match_labels: self.match_labels.clone(),
+        }
+    }
+
+    /// Where condition `c` has to be provided, relative to the providing
+    /// agent, if anywhere in particular
+    pub fn get_condition_placement(&self, c: &str) -> Option<&Placement> {
+        self.condition_placements.get(c)
+    }
+
+    pub fn get_condition_placements(&self) -> &BTreeMap<String, Placement> {
+        &self.condition_placements
+    }
+
+    /// How much of the provider's capacity this want takes up; 1 if not given
+    pub fn get_demand(&self) -> u64 {
+        self.demand.unwrap_or(1)
+    }
+
+    pub fn get_any_of(&self) -> &[Vec<String>] {
+        &self.any_of
+    }
+
+    /// Every condition mentioned, whether required or in an `anyOf` group
+    pub fn get_all_conditions(&self) -> Vec<String> {
+        let mut ret = self.conditions.clone();
+        for c in self.any_of.iter().flatten() {
+            if !ret.contains(c) {
+                ret.push(c.clone());
+            }
+        }
+        ret
+    }
+
+    /// Each full set of conditions under which this is provided: the plain
+    /// conditions plus one `anyOf` group
+    pub fn get_alternatives(&self) -> Vec<Vec<String>> {
+        if self.any_of.is_empty() {
+            return vec![self.conditions.clone()];
+        }
+        self.any_of
+            .iter()
+            .map(|g| {
+                let mut alternative = self.conditions.clone();
+                alternative.extend(g.iter().filter(|c| !self.conditions.contains(c)).cloned());
+                alternative
+            })
+            .collect()
+    }
+
     pub fn is_unconditional(&self) -> bool {
-        self.conditions.len() == 0
+        self.conditions.len() == 0 && self.any_of.is_empty()
     }
 
     pub fn has_none_of_these_conditions(&self, conditions: &HashSet<String>) -> bool {
-        !self.conditions.iter().any(|c| conditions.contains(c))
+        !self
+            .conditions
+            .iter()
+            .chain(self.any_of.iter().flatten())
+            .any(|c| conditions.contains(c))
     }
 
     pub fn has_behavior(&self, behavior_name: &String) -> bool {
-        self.name == *behavior_name || self.conditions.iter().any(|x| x == behavior_name)
+        self.name == *behavior_name
+            || self
+                .conditions
+                .iter()
+                .chain(self.any_of.iter().flatten())
+                .any(|x| x == behavior_name)
     }
 
+    /// The name split into its base name and instance tags
+    pub fn get_id(&self) -> BehaviorId {
+        BehaviorId::from(self.name.as_str())
+    }
+
+    /// Tags the name with `suffix` and every condition with
+    /// `condition_suffix`; an empty one leaves them as they are
     pub fn make_instance(&self, suffix: &str, condition_suffix: &str) -> Behavior {
+        let tag = |c: &String| tagged(c, condition_suffix);
         Behavior {
-            name: if suffix == "" {
-                self.name.clone()
-            } else {
-                format!("{} | {}", self.name, suffix)
-            },
+            name: tagged(&self.name, suffix),
             comment: self.comment.clone(),
-            conditions: if condition_suffix == "" {
-                self.conditions.clone()
-            } else {
-                self.conditions
-                    .iter()
-                    .map(|c| format!("{} | {}", c, condition_suffix))
-                    .collect()
-            },
+            conditions: self.conditions.iter().map(tag).collect(),
+            any_of: self
+                .any_of
+                .iter()
+                .map(|g| g.iter().map(tag).collect())
+                .collect(),
+            own_conditions: self.own_conditions.clone(),
+            criticality: self.criticality,
+            capacity: self.capacity,
+            demand: self.demand,
+            version: self.version.clone(),
+            condition_versions: self
+                .condition_versions
+                .iter()
+                .map(|(c, r)| (tag(c), r.clone()))
+                .collect(),
+            same_as: self.same_as.clone(),
+            match_labels: self.match_labels.clone(),
+            condition_placements: self
+                .condition_placements
+                .iter()
+                .map(|(c, p)| (tag(c), p.clone()))
+                .collect(),
+            provenance: self.provenance.clone(),
         }
     }
+
+    // A want of an instance: what the agent provides itself is tagged the
+    // same way as its provides
+    pub(crate) fn make_want_instance(&self, provides_suffix: &str) -> Behavior {
+        let mut ret = self.clone();
+        ret.own_conditions = self
+            .own_conditions
+            .iter()
+            .map(|c| tagged(c, provides_suffix))
+            .collect();
+        ret
+    }
+}
+
+// `name | tag`, or just `name` if the tag is empty
+fn tagged(name: &str, tag: &str) -> String {
+    if tag.is_empty() {
+        return String::from(name);
+    }
+    BehaviorId::from(name).with_tag(tag).to_string()
 }
 
 #[cfg(test)]
@@ -125,12 +548,175 @@ mod tests {
         assert!(!p.has_behavior(&String::from("blah")));
     }
 
+    #[test]
+    fn any_of_from_yaml() {
+        let p: Behavior = serde_yaml::from_str(
+            "name: foo\nconditions: [db]\nanyOf:\n  - [cache]\n  - [disk, local]",
+        )
+        .expect("Unable to parse");
+        assert_eq!(p.get_any_of(), [vec!["cache"], vec!["disk", "local"]]);
+        assert_eq!(
+            p.get_alternatives(),
+            vec![vec!["db", "cache"], vec!["db", "disk", "local"]]
+        );
+        assert_eq!(p.get_all_conditions(), ["db", "cache", "disk", "local"]);
+        assert!(!p.is_unconditional());
+        assert!(p.has_behavior(&String::from("local")));
+        assert!(p.requires("db"));
+        assert!(!p.requires("cache"));
+        assert_eq!(
+            serde_yaml::to_string(&p).unwrap(),
+            "name: foo\nconditions:\n- db\nanyOf:\n- - cache\n- - disk\n  - local\n"
+        );
+    }
+
+    #[test]
+    fn capacity_and_demand_from_yaml() {
+        let p: Behavior = serde_yaml::from_str("name: db\ncapacity: 10").expect("Unable to parse");
+        assert_eq!(p.get_capacity(), Some(10));
+        assert_eq!(p.get_demand(), 1);
+        let w: Behavior = serde_yaml::from_str("name: db\ndemand: 4").expect("Unable to parse");
+        assert_eq!(w.get_capacity(), None);
+        assert_eq!(w.get_demand(), 4);
+        assert_eq!(serde_yaml::to_string(&w).unwrap(), "name: db\ndemand: 4\n");
+        assert_eq!(
+            p.make_instance("i1", "").get_capacity(),
+            Some(10),
+            "instances keep their capacity"
+        );
+    }
+
+    #[test]
+    fn versions_from_yaml() {
+        let p: Behavior = serde_yaml::from_str(
+            "name: orders\nversion: 2.3.0\nconditions: [db]\nconditionVersions:\n  db: ^14",
+        )
+        .expect("Unable to parse");
+        assert_eq!(p.get_version(), Some("2.3.0"));
+        assert_eq!(p.get_condition_version("db"), Some("^14"));
+        assert_eq!(p.get_condition_version("cache"), None);
+        assert_eq!(
+            p.make_instance("i1", "i1").get_condition_version("db | i1"),
+            Some("^14")
+        );
+        assert_eq!(
+            serde_yaml::to_string(&p).unwrap(),
+            "name: orders\nconditions:\n- db\nversion: 2.3.0\nconditionVersions:\n  db: ^14\n"
+        );
+    }
+
+    #[test]
+    fn placements_from_yaml() {
+        let w: Behavior = serde_yaml::from_str("name: db\nsameAs: [zone]\nmatch: {region: eu}")
+            .expect("Unable to parse");
+        assert_eq!(
+            w.get_placement(),
+            Placement {
+                same_as: vec![String::from("zone")],
+                match_labels: Labels::from([(String::from("region"), String::from("eu"))]),
+            }
+        );
+        assert_eq!(
+            w,
+            Behavior::build("db")
+                .with_same_as("zone")
+                .with_match_label("region", "eu")
+        );
+        assert!(Behavior::build("db").get_placement().is_empty());
+
+        let p: Behavior = serde_yaml::from_str(
+            "name: orders\nconditions: [db]\nconditionPlacements:\n  db: {sameAs: [zone]}",
+        )
+        .expect("Unable to parse");
+        assert_eq!(p.get_condition_placement("db").unwrap().same_as, ["zone"]);
+        assert!(p
+            .make_instance("i1", "i1")
+            .get_condition_placement("db | i1")
+            .is_some());
+        assert!(serde_yaml::from_str::<Behavior>(
+            "name: orders\nconditionPlacements:\n  db: {near: [zone]}"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_alternatives() {
+        let p = Behavior::from_alternatives(
+            String::from("foo"),
+            vec![
+                vec![String::from("db"), String::from("cache")],
+                vec![String::from("local"), String::from("db")],
+            ],
+        );
+        assert_eq!(p.conditions, ["db"]);
+        assert_eq!(p.any_of, [vec!["cache"], vec!["local"]]);
+
+        // a single alternative has no anyOf
+        let p = Behavior::from_alternatives(
+            String::from("foo"),
+            vec![vec![String::from("db"), String::from("db")]],
+        );
+        assert_eq!(
+            p,
+            Behavior::build("foo").with_conditions(vec![String::from("db")])
+        );
+
+        // an alternative without extra conditions makes the others moot
+        let p = Behavior::from_alternatives(
+            String::from("foo"),
+            vec![
+                vec![String::from("db")],
+                vec![String::from("db"), String::from("cache")],
+            ],
+        );
+        assert_eq!(
+            p,
+            Behavior::build("foo").with_conditions(vec![String::from("db")])
+        );
+    }
+
+    #[test]
+    fn test_remove_condition() {
+        let mut p = Behavior::build("foo").with_any_of(vec![
+            vec![String::from("gc"), String::from("cache")],
+            vec![String::from("gc")],
+        ]);
+        assert!(p.requires("gc"));
+        p.remove_condition("gc");
+        assert!(p.is_unconditional());
+    }
+
+    #[test]
+    fn criticality_from_yaml() {
+        let p: Behavior = serde_yaml::from_str("name: foo").unwrap();
+        assert_eq!(p.get_criticality(), Criticality::Required);
+        let p: Behavior = serde_yaml::from_str("name: foo\ncriticality: degraded").unwrap();
+        assert_eq!(p.get_criticality(), Criticality::Degraded);
+        assert_eq!(
+            serde_yaml::to_string(&p).unwrap(),
+            "name: foo\ncriticality: degraded\n"
+        );
+        assert!(serde_yaml::from_str::<Behavior>("name: foo\ncriticality: urgent").is_err());
+        assert!(Criticality::Required < Criticality::Optional);
+    }
+
     #[test]
     fn add_condition() {
         let mut p = Behavior {
             name: String::from("a"),
             comment: String::from(""),
             conditions: [].to_vec(),
+            any_of: vec![],
+            own_conditions: vec![],
+            criticality: Criticality::Required,
+            capacity: None,
+            demand: None,
+            version: None,
+            condition_versions: BTreeMap::new(),
+            same_as: vec![],
+            match_labels: Labels::new(),
+            condition_placements: BTreeMap::new(),
+            provenance: Provenance::default(),
         };
         p.add_condition(String::from("c1"));
         assert!(p.conditions == ["c1"]);
@@ -153,6 +739,17 @@ mod tests {
             name: String::from("a"),
             comment: String::from(""),
             conditions: [].to_vec(),
+            any_of: vec![],
+            own_conditions: vec![],
+            criticality: Criticality::Required,
+            capacity: None,
+            demand: None,
+            version: None,
+            condition_versions: BTreeMap::new(),
+            same_as: vec![],
+            match_labels: Labels::new(),
+            condition_placements: BTreeMap::new(),
+            provenance: Provenance::default(),
         };
         assert!(p.is_unconditional());
         p.add_condition(String::from("c1"));
@@ -165,6 +762,17 @@ mod tests {
             name: String::from("b1"),
             comment: String::from(""),
             conditions: [String::from("c1"), String::from("c2")].to_vec(),
+            any_of: vec![],
+            own_conditions: vec![],
+            criticality: Criticality::Required,
+            capacity: None,
+            demand: None,
+            version: None,
+            condition_versions: BTreeMap::new(),
+            same_as: vec![],
+            match_labels: Labels::new(),
+            condition_placements: BTreeMap::new(),
+            provenance: Provenance::default(),
         };
         let mut conditions = HashSet::new();
         assert!(p.has_none_of_these_conditions(&conditions));
@@ -181,10 +789,30 @@ mod tests {
             name: String::from("b1"),
             comment: String::from(""),
             conditions: [String::from("c1"), String::from("c2")].to_vec(),
+            any_of: vec![],
+            own_conditions: vec![],
+            criticality: Criticality::Required,
+            capacity: None,
+            demand: None,
+            version: None,
+            condition_versions: BTreeMap::new(),
+            same_as: vec![],
+            match_labels: Labels::new(),
+            condition_placements: BTreeMap::new(),
+            provenance: Provenance::default(),
         };
         let p2 = p.make_instance("suf", "csuf");
         assert_eq!(p2.name, "b1 | suf");
         assert_eq!(p2.conditions, ["c1 | csuf", "c2 | csuf"]);
+
+        let p = Behavior::build("b1").with_any_of(vec![vec![String::from("c3")]]);
+        assert_eq!(p.make_instance("suf", "csuf").any_of, [vec!["c3 | csuf"]]);
+
+        // a want's own conditions follow the agent's provides
+        let w: Behavior =
+            serde_yaml::from_str("name: tls\nownConditions: [web]").expect("Unable to parse");
+        assert_eq!(w.get_own_conditions(), ["web"]);
+        assert_eq!(w.make_want_instance("suf").own_conditions, ["web | suf"]);
     }
 
     // #[test]
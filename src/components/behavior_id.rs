@@ -0,0 +1,175 @@
+//! Structured behavior names.
+//!
+//! SuperAgent instances tag the behaviors they provide, so `db-write`
+//! provided by instance `shard-3` is named `db-write | shard-3`. A
+//! `BehaviorId` keeps the base name apart from the tags, and lets a want or
+//! condition ask for any instance with `db-write | *`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// The tag a want or condition ends with to accept any instance of a
+/// behavior, e.g. `db-write | *`
+pub const ANY_INSTANCE: &str = "*";
+
+/// The name of a behavior, split into its base name and the tags that
+/// SuperAgent instances added to it. It is written, and stored in behavior
+/// names, as the base name followed by ` | tag` for each tag, e.g.
+/// `db-write | shard-3`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BehaviorId {
+    base: String,
+    tags: Vec<String>,
+}
+
+impl BehaviorId {
+    pub fn new(base: &str) -> BehaviorId {
+        BehaviorId {
+            base: String::from(base.trim()),
+            tags: vec![],
+        }
+    }
+
+    /// Adds a tag at the end. Empty tags are ignored, so that instances
+    /// without a tag keep the name as is.
+    pub fn with_tag(mut self, tag: &str) -> BehaviorId {
+        self.tags.extend(
+            tag.split('|')
+                .map(|t| String::from(t.trim()))
+                .filter(|t| !t.is_empty()),
+        );
+        self
+    }
+
+    /// The same behavior, standing for any instance of it
+    pub fn any_instance(&self) -> BehaviorId {
+        self.clone().with_tag(ANY_INSTANCE)
+    }
+
+    pub fn get_base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn is_tagged(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    /// True if it ends in `| *`
+    pub fn is_any_instance(&self) -> bool {
+        self.tags.last().is_some_and(|t| t == ANY_INSTANCE)
+    }
+
+    /// Whether a provide of `provided` meets a want or condition of this:
+    /// it has to be the same behavior, or, for `name | *`, `name` itself or
+    /// any instance of it (with more tags)
+    pub fn matches(&self, provided: &BehaviorId) -> bool {
+        if !self.is_any_instance() {
+            return self == provided;
+        }
+        let prefix = &self.tags[..self.tags.len() - 1];
+        self.base == provided.base && provided.tags.starts_with(prefix)
+    }
+
+    /// Every `| *` name that `matches` this one, from the base name up
+    pub(crate) fn get_any_instance_names(&self) -> Vec<String> {
+        if self.is_any_instance() {
+            return vec![];
+        }
+        (0..=self.tags.len())
+            .map(|n| {
+                BehaviorId {
+                    base: self.base.clone(),
+                    tags: self.tags[..n].to_vec(),
+                }
+                .any_instance()
+                .to_string()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for BehaviorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.base)?;
+        for t in &self.tags {
+            write!(f, " | {}", t)?;
+        }
+        Ok(())
+    }
+}
+
+// Any string is a valid name; `a|b` and `a | b` are the same
+impl From<&str> for BehaviorId {
+    fn from(name: &str) -> Self {
+        let (base, tags) = name.split_once('|').unwrap_or((name, ""));
+        BehaviorId::new(base).with_tag(tags)
+    }
+}
+
+impl FromStr for BehaviorId {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(BehaviorId::from(s))
+    }
+}
+
+impl Serialize for BehaviorId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BehaviorId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(BehaviorId::from(
+            String::deserialize(deserializer)?.as_str(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let id = BehaviorId::from("db-write | shard-3");
+        assert_eq!(id.get_base(), "db-write");
+        assert_eq!(id.get_tags(), ["shard-3"]);
+        assert_eq!(id, BehaviorId::new("db-write").with_tag("shard-3"));
+        assert_eq!(id, "db-write|shard-3".parse().unwrap());
+        assert_eq!(id.to_string(), "db-write | shard-3");
+        assert!(!BehaviorId::from("db-write").is_tagged());
+        assert_eq!(BehaviorId::new("db").with_tag("").to_string(), "db");
+        assert_eq!(
+            serde_json::to_string(&id).unwrap(),
+            "\"db-write | shard-3\""
+        );
+        assert_eq!(
+            serde_json::from_str::<BehaviorId>("\"db-write|shard-3\"").unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let shard = BehaviorId::from("db | eu | shard-3");
+        assert!(shard.matches(&shard));
+        assert!(!BehaviorId::from("db").matches(&shard));
+        assert!(BehaviorId::from("db | *").matches(&shard));
+        assert!(BehaviorId::from("db | *").matches(&BehaviorId::from("db")));
+        assert!(BehaviorId::from("db | eu | *").matches(&shard));
+        assert!(!BehaviorId::from("db | us | *").matches(&shard));
+        assert!(!BehaviorId::from("cache | *").matches(&shard));
+        assert_eq!(
+            shard.get_any_instance_names(),
+            ["db | *", "db | eu | *", "db | eu | shard-3 | *"]
+        );
+    }
+}
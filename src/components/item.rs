@@ -3,7 +3,7 @@ use crate::components::superagent::SuperAgent;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(tag = "kind")]
 #[derive(JsonSchema)]
 #[serde(deny_unknown_fields)]
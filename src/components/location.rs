@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// A line and column in a file, both starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A range of text, from `start` up to (but not including) `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Where a definition was loaded from: the file, which YAML document in it
+/// (starting at 0) and the text of the definition itself
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Location {
+    pub path: String,
+    pub document: usize,
+    pub span: Span,
+}
+
+impl Location {
+    pub fn new(path: &str, document: usize, span: Span) -> Location {
+        Location {
+            path: String::from(path),
+            document,
+            span,
+        }
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.span.start.line
+    }
+}
+
+// Displayed as `path:line`, e.g. `contracts/db.yaml:14`
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.path, self.get_line())
+    }
+}
+
+/// Every place a definition was loaded from, in the order they were seen.
+///
+/// Provenance never takes part in comparing, ordering or hashing whatever
+/// holds it, so the same definition loaded from two files is still a
+/// duplicate and merges into one, which then remembers both locations.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance(Vec<Location>);
+
+impl Provenance {
+    pub fn new(location: Location) -> Provenance {
+        Provenance(vec![location])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get_locations(&self) -> &[Location] {
+        &self.0
+    }
+
+    /// Where it was first loaded from
+    pub fn get_first(&self) -> Option<&Location> {
+        self.0.first()
+    }
+
+    pub fn add(&mut self, location: Location) {
+        if !self.0.contains(&location) {
+            self.0.push(location);
+        }
+    }
+
+    pub fn extend(&mut self, other: &Provenance) {
+        for location in &other.0 {
+            self.add(location.clone());
+        }
+    }
+}
+
+impl PartialEq for Provenance {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Provenance {}
+
+impl PartialOrd for Provenance {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Provenance {
+    fn cmp(&self, _other: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl Hash for Provenance {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(path: &str, line: usize) -> Location {
+        Location::new(
+            path,
+            0,
+            Span {
+                start: Position { line, column: 1 },
+                end: Position { line, column: 10 },
+            },
+        )
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            location("contracts/db.yaml", 14).to_string(),
+            "contracts/db.yaml:14"
+        );
+    }
+
+    #[test]
+    fn test_provenance() {
+        let mut p = Provenance::default();
+        assert!(p.is_empty());
+        assert_eq!(p.get_first(), None);
+        p.add(location("a.yaml", 1));
+        p.extend(&Provenance::new(location("b.yaml", 2)));
+        p.add(location("a.yaml", 1));
+        assert_eq!(
+            p.get_locations(),
+            &[location("a.yaml", 1), location("b.yaml", 2)]
+        );
+        assert_eq!(p.get_first(), Some(&location("a.yaml", 1)));
+
+        // never makes a difference when comparing
+        assert_eq!(p, Provenance::default());
+        assert_eq!(p.cmp(&Provenance::default()), Ordering::Equal);
+    }
+}
@@ -1,9 +1,14 @@
 mod agent;
 mod behavior;
+mod behavior_id;
 mod item;
+mod location;
 mod superagent;
 
 pub use self::agent::Agent;
-pub use self::behavior::Behavior;
+pub(crate) use self::agent::IntermediateAgent;
+pub use self::behavior::{Behavior, Criticality};
+pub use self::behavior_id::{BehaviorId, ANY_INSTANCE};
 pub use self::item::Item;
-pub use self::superagent::SuperAgent;
+pub use self::location::{Location, Position, Provenance, Span};
+pub use self::superagent::{SuperAgent, SuperAgentMember};
@@ -1,4 +1,6 @@
 use crate::components::behavior::Behavior;
+use crate::components::location::{Location, Provenance};
+use crate::selector::Labels;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +11,10 @@ pub struct SuperAgentInstance {
     name: String,
     #[serde(default)]
     comment: String,
+    // Added to the labels of the SuperAgent, overriding them
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Labels::is_empty")]
+    labels: Labels,
     provides_tag: String,
     conditions_tag: String,
 
@@ -17,6 +23,9 @@ pub struct SuperAgentInstance {
 
     #[serde(default)]
     wants: Vec<Behavior>,
+
+    #[serde(skip)]
+    provenance: Provenance,
 }
 
 impl SuperAgentInstance {
@@ -24,6 +33,10 @@ impl SuperAgentInstance {
         &self.name
     }
 
+    pub fn get_labels(&self) -> &Labels {
+        &self.labels
+    }
+
     pub fn get_provides(&self) -> &Vec<Behavior> {
         &self.provides
     }
@@ -39,6 +52,22 @@ impl SuperAgentInstance {
     pub fn get_conditions_tags(&self) -> &String {
         &self.conditions_tag
     }
+
+    pub fn get_provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    pub(crate) fn add_location(&mut self, location: Location) {
+        self.provenance.add(location);
+    }
+
+    pub(crate) fn get_provides_mut(&mut self) -> &mut [Behavior] {
+        &mut self.provides
+    }
+
+    pub(crate) fn get_wants_mut(&mut self) -> &mut [Behavior] {
+        &mut self.wants
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
@@ -49,11 +78,21 @@ pub struct SuperAgent {
     #[serde(default)]
     comment: String,
 
+    // Given to the agent the SuperAgent becomes, or to each of its instances
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Labels::is_empty")]
+    labels: Labels,
+
+    // Names of the agents it is made of. A name can also be that of another
+    // SuperAgent, which is then expanded first and nested inside this one.
     #[serde(default)]
     agents: Vec<String>,
 
     #[serde(default)]
     instances: Vec<SuperAgentInstance>,
+
+    #[serde(skip)]
+    provenance: Provenance,
 }
 
 impl SuperAgent {
@@ -61,8 +100,10 @@ impl SuperAgent {
         SuperAgent {
             name: name,
             comment: String::from(""),
+            labels: Labels::new(),
             agents: vec![],
             instances: vec![],
+            provenance: Provenance::default(),
         }
     }
 
@@ -83,24 +124,53 @@ impl SuperAgent {
         provides: Vec<Behavior>,
         wants: Vec<Behavior>,
     ) -> SuperAgent {
-        if self.instances.iter().any(|i| i.name == self.name) {
+        if self.instances.iter().any(|i| i.name == name) {
             return self;
         }
         self.instances.push(SuperAgentInstance {
             name: name.to_string(),
             comment: comment.to_string(),
+            labels: Labels::new(),
             provides_tag: provides_tag.to_string(),
             conditions_tag: conditions_tag.to_string(),
             provides: provides,
             wants: wants,
+            provenance: Provenance::default(),
         });
         self
     }
 
+    pub fn with_label(mut self, key: &str, value: &str) -> SuperAgent {
+        self.labels.insert(String::from(key), String::from(value));
+        self
+    }
+
+    /// Sets a label on the instance called `instance_name`, if there is one
+    pub fn with_instance_label(
+        mut self,
+        instance_name: &str,
+        key: &str,
+        value: &str,
+    ) -> SuperAgent {
+        if let Some(i) = self.instances.iter_mut().find(|i| i.name == instance_name) {
+            i.labels.insert(String::from(key), String::from(value));
+        }
+        self
+    }
+
+    pub fn with_location(mut self, location: Location) -> SuperAgent {
+        self.provenance.add(location);
+        self
+    }
+
     pub fn get_name(&self) -> &String {
         &self.name
     }
 
+    pub fn get_labels(&self) -> &Labels {
+        &self.labels
+    }
+
     pub fn get_agent_names(&self) -> Vec<String> {
         self.agents.clone()
     }
@@ -112,6 +182,31 @@ impl SuperAgent {
     pub fn get_instances(&self) -> Vec<SuperAgentInstance> {
         self.instances.clone()
     }
+
+    pub fn get_provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    pub(crate) fn get_instances_mut(&mut self) -> &mut [SuperAgentInstance] {
+        &mut self.instances
+    }
+
+    // Returns false if it has no instance with this name
+    pub(crate) fn remove_instance(&mut self, instance_name: &str) -> bool {
+        let before = self.instances.len();
+        self.instances.retain(|i| i.name != instance_name);
+        self.instances.len() != before
+    }
+}
+
+/// A member of a SuperAgent, as folded into the agent it becomes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuperAgentMember {
+    pub name: String,
+    /// Set if the member is a SuperAgent itself
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<SuperAgentMember>,
 }
 
 #[cfg(test)]
@@ -136,10 +231,12 @@ mod tests {
           "name": "j",
           "agents": ["a1", "a2"],
           "comment": "this is a comment",
+          "labels": {"team": "payments"},
           "instances": [
             {
               "name": "i1",
               "comment": "this is a comment",
+              "labels": {"env": "prod"},
               "providesTag": "jp",
               "conditionsTag": "jc",
               "provides": [
@@ -156,6 +253,8 @@ mod tests {
         assert_eq!(s.agents, ["a1", "a2"]);
         assert_eq!(s.instances[0].name, "i1");
         assert_eq!(s.instances[0].comment, "this is a comment");
+        assert_eq!(s.get_labels()["team"], "payments");
+        assert_eq!(s.instances[0].get_labels()["env"], "prod");
         assert_eq!(s.instances[0].provides[0].get_name(), "p1");
         assert_eq!(s.instances[0].provides[1].get_conditions(), ["c1", "c2"]);
     }
@@ -187,15 +286,20 @@ mod tests {
             vec!(String::from("a1"), String::from("a2"))
         );
         assert_eq!(sa.get_instance_names(), vec!(String::from("i1")));
+        // instance names are unique
+        let sa = sa.with_instance("i1", "", "other", "other", vec![], vec![]);
+        assert_eq!(sa.get_instance_names(), vec!(String::from("i1")));
         assert_eq!(
             sa.get_instances(),
             vec![SuperAgentInstance {
                 name: "i1".to_string(),
                 comment: "this is a comment".to_string(),
+                labels: Labels::new(),
                 provides_tag: "jp".to_string(),
                 conditions_tag: "jc".to_string(),
                 provides: vec![Behavior::build("p1")],
                 wants: vec![Behavior::build("w1")],
+                provenance: Provenance::default(),
             }]
         );
     }
@@ -205,10 +309,12 @@ mod tests {
         let sai = SuperAgentInstance {
             name: "i1".to_string(),
             comment: "this is a comment".to_string(),
+            labels: Labels::new(),
             provides_tag: "jp".to_string(),
             conditions_tag: "jc".to_string(),
             provides: vec![Behavior::build("p1")],
             wants: vec![Behavior::build("w1")],
+            provenance: Provenance::default(),
         };
         assert_eq!(sai.get_name(), "i1");
         assert_eq!(sai.get_provides_tags(), "jp");
@@ -0,0 +1,290 @@
+//! Minimal deployment sets.
+//!
+//! Bringing up a new environment starts from a few root agents that have to
+//! ship. `minimal_deployment` works out which other working agents have to
+//! come along so that every want of the roots is satisfied, along with the
+//! wants of everything they pull in, and picks the smallest such set. It
+//! builds on the support sets of each want's resolution (see
+//! `ResolutionGraph::get_support_sets`), so providers that are filtered out
+//! by version or placement are never picked.
+
+use crate::report::AgentReport;
+use crate::resolution_graph::combine_sets;
+use crate::Tracker;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// A want that nothing in the network can satisfy, so no deployment will
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct UnsatisfiableWant {
+    pub agent_name: String,
+    pub behavior_name: String,
+}
+
+impl std::fmt::Display for UnsatisfiableWant {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} wants {}, which nothing can satisfy",
+            self.agent_name, self.behavior_name
+        )
+    }
+}
+
+/// The smallest sets of agents to deploy along with some roots
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployment {
+    /// The root agents, sorted
+    pub roots: Vec<String>,
+    /// Root names that are not working agents, sorted
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<String>,
+    /// Every set of other agents of the smallest size, each sorted and in
+    /// name order. The first one is the pick, the rest are alternatives.
+    pub sets: Vec<Vec<String>>,
+    /// Wants of the roots, or of the picked agents, that are left unmet
+    /// whatever is deployed
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unsatisfiable: Vec<UnsatisfiableWant>,
+}
+
+impl Deployment {
+    /// The picked set of agents to deploy along with the roots
+    pub fn get_set(&self) -> &[String] {
+        self.sets.first().map_or(&[], Vec::as_slice)
+    }
+
+    /// Other sets that are just as small as the picked one
+    pub fn get_alternatives(&self) -> &[Vec<String>] {
+        self.sets.get(1..).unwrap_or(&[])
+    }
+}
+
+/// Works out the smallest sets of working agents that have to be deployed
+/// along with `roots` so that every want of the roots, and of the agents
+/// that are deployed for them, is satisfied. Wants that nothing can satisfy
+/// are skipped and listed in `unsatisfiable`.
+pub fn minimal_deployment(tracker: &Tracker, roots: &[&str]) -> Deployment {
+    let mut ret = Deployment::default();
+    let mut start = BTreeSet::new();
+    for root in roots {
+        if tracker.has_agent(String::from(*root)) {
+            start.insert(*root);
+        } else {
+            ret.unknown.push(String::from(*root));
+        }
+    }
+    ret.unknown.sort();
+    ret.unknown.dedup();
+    ret.roots = start.iter().map(|r| String::from(*r)).collect();
+
+    let mut search = Search {
+        tracker,
+        needs: HashMap::new(),
+        roots: &start,
+        best: None,
+        found: BTreeSet::new(),
+        seen: HashSet::new(),
+    };
+    let deployed: BTreeSet<String> = start.iter().map(|r| String::from(*r)).collect();
+    search.expand(deployed.clone(), deployed.into_iter().collect());
+    ret.sets = search.found.iter().cloned().collect();
+
+    let mut shipped = ret.roots.clone();
+    shipped.extend(ret.get_set().iter().cloned());
+    for agent_name in shipped {
+        ret.unsatisfiable
+            .extend(search.get_needs(&agent_name).unsatisfiable.iter().cloned());
+    }
+    ret.unsatisfiable.sort();
+    ret
+}
+
+// What an agent needs deployed along with it
+struct Needs {
+    // the sets of agents that satisfy all of its satisfiable wants
+    sets: Vec<BTreeSet<String>>,
+    unsatisfiable: Vec<UnsatisfiableWant>,
+}
+
+impl Needs {
+    fn new(agent: &AgentReport) -> Needs {
+        let mut sets = vec![BTreeSet::new()];
+        let mut unsatisfiable = vec![];
+        for want in &agent.wants {
+            if !want.is_satisfied() {
+                unsatisfiable.push(UnsatisfiableWant {
+                    agent_name: agent.agent_name.clone(),
+                    behavior_name: want.behavior_name.clone(),
+                });
+                continue;
+            }
+            let want_sets: Vec<BTreeSet<String>> = want
+                .resolution
+                .get_support_sets()
+                .into_iter()
+                .map(|set| set.into_iter().collect())
+                .collect();
+            sets = combine_sets(&sets, &want_sets);
+        }
+        Needs {
+            sets,
+            unsatisfiable,
+        }
+    }
+}
+
+// A depth first search over which support set to use for each deployed
+// agent, dropping branches that are already bigger than the best so far.
+// Only agents that get deployed are resolved, each one once.
+struct Search<'a> {
+    tracker: &'a Tracker,
+    needs: HashMap<String, Needs>,
+    roots: &'a BTreeSet<&'a str>,
+    best: Option<usize>,
+    // the smallest sets found so far, without the roots
+    found: BTreeSet<Vec<String>>,
+    seen: HashSet<(BTreeSet<String>, Vec<String>)>,
+}
+
+impl Search<'_> {
+    fn get_needs(&mut self, agent_name: &str) -> &Needs {
+        self.needs
+            .entry(String::from(agent_name))
+            .or_insert_with(|| Needs::new(&self.tracker.resolve_agent(agent_name)))
+    }
+
+    // `pending` are deployed agents whose wants are not looked at yet
+    fn expand(&mut self, deployed: BTreeSet<String>, mut pending: Vec<String>) {
+        let size = deployed.len() - self.roots.len();
+        if self.best.is_some_and(|best| size > best) {
+            return;
+        }
+        if !self.seen.insert((deployed.clone(), pending.clone())) {
+            return;
+        }
+        let Some(agent_name) = pending.pop() else {
+            if self.best.is_none_or(|best| size < best) {
+                self.best = Some(size);
+                self.found.clear();
+            }
+            self.found.insert(
+                deployed
+                    .into_iter()
+                    .filter(|a| !self.roots.contains(a.as_str()))
+                    .collect(),
+            );
+            return;
+        };
+        for set in self.get_needs(&agent_name).sets.clone() {
+            let mut deployed = deployed.clone();
+            let mut pending = pending.clone();
+            for a in set {
+                if deployed.insert(a.clone()) {
+                    pending.push(a);
+                }
+            }
+            pending.sort();
+            pending.reverse();
+            self.expand(deployed, pending);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Agent, Behavior, SuperAgent};
+
+    fn network() -> Tracker {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("pg").with_provides(vec![Behavior::build("storage")]));
+        t.add_agent(Agent::build("mysql").with_provides(vec![Behavior::build("storage")]));
+        t.add_agent(
+            Agent::build("api")
+                .with_provides(vec![
+                    Behavior::build("http").with_conditions(vec![String::from("storage")])
+                ])
+                .with_wants(vec![Behavior::build("logs")]),
+        );
+        t.add_agent(Agent::build("fat-api").with_provides(vec![Behavior::build("http")]));
+        t.add_agent(Agent::build("loki").with_provides(vec![Behavior::build("logs")]));
+        t
+    }
+
+    #[test]
+    fn test_minimal_deployment() {
+        let mut t = network();
+        t.add_agent(
+            Agent::build("web")
+                .with_wants(vec![Behavior::build("http"), Behavior::build("missing")]),
+        );
+        let d = minimal_deployment(&t, &["web", "nope"]);
+        assert_eq!(d.roots, ["web"]);
+        assert_eq!(d.unknown, ["nope"]);
+        assert_eq!(d.get_set(), ["fat-api"]);
+        assert!(d.get_alternatives().is_empty());
+        assert_eq!(
+            d.unsatisfiable
+                .iter()
+                .map(|u| u.to_string())
+                .collect::<Vec<String>>(),
+            ["web wants missing, which nothing can satisfy"]
+        );
+
+        // api needs storage and its own wants too
+        t.remove_agent("fat-api");
+        let d = minimal_deployment(&t, &["web"]);
+        assert_eq!(
+            d.sets,
+            vec![vec!["api", "loki", "mysql"], vec!["api", "loki", "pg"]]
+        );
+
+        // a root that already provides something is used for free
+        let d = minimal_deployment(&t, &["web", "pg"]);
+        assert_eq!(d.sets, vec![vec!["api", "loki"]]);
+    }
+
+    #[test]
+    fn test_minimal_deployment_unrelated() {
+        let mut t = network();
+        t.add_agent(Agent::build("web").with_wants(vec![Behavior::build("http")]));
+        let expected = minimal_deployment(&t, &["web"]);
+
+        // a hub whose wants can be met in 4^10 ways, which nothing the roots
+        // need depends on
+        let wants: Vec<Behavior> = (0..10)
+            .map(|i| Behavior::build(&format!("r{}", i)))
+            .collect();
+        t.add_agent(Agent::build("hub").with_wants(wants));
+        for i in 0..10 {
+            for j in 0..4 {
+                t.add_agent(
+                    Agent::build(&format!("r{}-{}", i, j))
+                        .with_provides(vec![Behavior::build(&format!("r{}", i))]),
+                );
+            }
+        }
+        assert_eq!(minimal_deployment(&t, &["web"]), expected);
+
+        t.add_agent(Agent::build("one").with_wants(vec![Behavior::build("r0")]));
+        assert_eq!(minimal_deployment(&t, &["one"]).sets.len(), 4);
+    }
+
+    #[test]
+    fn test_minimal_deployment_instances() {
+        let mut t = network();
+        t.add_superagent(
+            SuperAgent::new(String::from("sa"))
+                .with_agent("pg")
+                .with_instance("eu", "", "eu", "", vec![], vec![])
+                .with_instance("us", "", "us", "", vec![], vec![]),
+        );
+        t.add_agent(Agent::build("shop").with_wants(vec![Behavior::build("storage | *")]));
+        // any instance, or the plain behavior
+        let d = minimal_deployment(&t, &["shop"]);
+        assert_eq!(d.sets, vec![vec!["eu"], vec!["mysql"], vec!["us"]]);
+    }
+}
@@ -3,60 +3,82 @@
 //! Generates Mermaid sequence diagram DSL showing promise resolution flows
 //! between components and behaviors.
 
-use crate::resolve::{Offer, Resolution};
+use crate::resolution_graph::{ResolutionGraph, ResolutionId};
+use crate::resolve::Resolution;
+use std::collections::HashSet;
 
 /// Input data for generating a sequence diagram.
 pub struct DiagramInput<'a> {
     pub component: &'a str,
     pub behavior: &'a str,
-    pub satisfied: &'a [Offer],
-    pub unsatisfied: &'a [Offer],
+    pub graph: &'a ResolutionGraph,
+    pub resolution: ResolutionId,
 }
 
 /// Recursively generate diagram lines for a resolution.
 ///
+/// Each resolution with offers is only drawn out the first time it is reached;
+/// later uses get a single "(see above)" message so shared conditions don't
+/// multiply the size of the diagram.
+///
 /// Returns a vector of diagram lines (without the leading indentation for the sequenceDiagram block).
-fn generate_lines(input: DiagramInput) -> Vec<String> {
+fn generate_lines(input: DiagramInput, expanded: &mut HashSet<ResolutionId>) -> Vec<String> {
     let DiagramInput {
         component,
         behavior,
-        satisfied,
-        unsatisfied,
+        graph,
+        resolution,
     } = input;
+    let node = graph.get_node(resolution);
+    let satisfied = node.get_satisfying_offers();
+    let unsatisfied = node.get_unsatisfying_offers();
 
     let mut ret = Vec::new();
 
-    // Handle satisfied offers (green rectangle)
-    if !satisfied.is_empty() {
-        ret.push("rect rgb(0,255,0)".to_string());
-        for offer in satisfied {
-            let agent_name = offer.get_agent_name();
-            ret.push(format!(
-                "    {} ->> {}: {}",
-                component, agent_name, behavior
-            ));
-
-            // Recursively process nested conditions
-            for condition in offer.get_resolved_conditions() {
-                let child_lines = generate_lines(DiagramInput {
-                    component: agent_name,
-                    behavior: condition.get_behavior_name(),
-                    satisfied: condition.get_satisfying_offers(),
-                    unsatisfied: condition.get_unsatisfying_offers(),
-                });
-                // Indent child lines
-                for line in child_lines {
-                    ret.push(format!("    {}", line));
-                }
-            }
-        }
+    // Handle loop back onto the active path (red rectangle with X and the loop)
+    if node.is_cyclic() {
+        ret.push("rect rgb(255,0,0)".to_string());
+        ret.push(format!(
+            "    {} -X {}: {} (cycle: {})",
+            component,
+            component,
+            behavior,
+            node.get_cycle().join(" -> ")
+        ));
         ret.push("end".to_string());
+        return ret;
     }
 
-    // Handle unsatisfied offers (red rectangle)
-    if !unsatisfied.is_empty() {
+    // Handle case with no offers (error state - red rectangle with X)
+    if satisfied.is_empty() && unsatisfied.is_empty() {
         ret.push("rect rgb(255,0,0)".to_string());
-        for offer in unsatisfied {
+        ret.push(format!("    {} -X {}: {}", component, component, behavior));
+        ret.push("end".to_string());
+        return ret;
+    }
+
+    // Handle a resolution that has already been drawn
+    if !expanded.insert(resolution) {
+        ret.push(if node.is_satisfied() {
+            "rect rgb(0,255,0)".to_string()
+        } else {
+            "rect rgb(255,0,0)".to_string()
+        });
+        ret.push(format!(
+            "    {} -->> {}: {} (see above)",
+            component, component, behavior
+        ));
+        ret.push("end".to_string());
+        return ret;
+    }
+
+    // Handle satisfied offers (green rectangle) then unsatisfied offers (red rectangle)
+    for (offers, color) in [(satisfied, "rgb(0,255,0)"), (unsatisfied, "rgb(255,0,0)")] {
+        if offers.is_empty() {
+            continue;
+        }
+        ret.push(format!("rect {}", color));
+        for offer in offers {
             let agent_name = offer.get_agent_name();
             ret.push(format!(
                 "    {} ->> {}: {}",
@@ -64,26 +86,41 @@ fn generate_lines(input: DiagramInput) -> Vec<String> {
             ));
 
             // Recursively process nested conditions
-            for condition in offer.get_resolved_conditions() {
-                let child_lines = generate_lines(DiagramInput {
-                    component: agent_name,
-                    behavior: condition.get_behavior_name(),
-                    satisfied: condition.get_satisfying_offers(),
-                    unsatisfied: condition.get_unsatisfying_offers(),
-                });
+            let condition_lines = |condition: ResolutionId, expanded: &mut HashSet<_>| {
+                generate_lines(
+                    DiagramInput {
+                        component: agent_name,
+                        behavior: graph.get_node(condition).get_behavior_name(),
+                        graph,
+                        resolution: condition,
+                    },
+                    expanded,
+                )
+            };
+            for condition in offer.get_conditions() {
                 // Indent child lines
-                for line in child_lines {
+                for line in condition_lines(*condition, expanded) {
                     ret.push(format!("    {}", line));
                 }
             }
-        }
-        ret.push("end".to_string());
-    }
 
-    // Handle case with no offers (error state - red rectangle with X)
-    if satisfied.is_empty() && unsatisfied.is_empty() {
-        ret.push("rect rgb(255,0,0)".to_string());
-        ret.push(format!("    {} -X {}: {}", component, component, behavior));
+            // Each anyOf group is one branch of an alt block
+            for (i, group) in offer.get_any_of().iter().enumerate() {
+                ret.push(format!(
+                    "    {} any of {}",
+                    if i == 0 { "alt" } else { "else" },
+                    i + 1
+                ));
+                for condition in group {
+                    for line in condition_lines(*condition, expanded) {
+                        ret.push(format!("        {}", line));
+                    }
+                }
+            }
+            if !offer.get_any_of().is_empty() {
+                ret.push("    end".to_string());
+            }
+        }
         ret.push("end".to_string());
     }
 
@@ -100,12 +137,28 @@ fn generate_lines(input: DiagramInput) -> Vec<String> {
 /// # Returns
 /// A string containing the complete Mermaid sequence diagram DSL.
 pub fn diagram(component: &str, behavior: &str, resolution: &Resolution) -> String {
-    let lines = generate_lines(DiagramInput {
-        component,
-        behavior,
-        satisfied: resolution.get_satisfying_offers(),
-        unsatisfied: resolution.get_unsatisfying_offers(),
-    });
+    diagram_graph(component, behavior, &ResolutionGraph::from(resolution))
+}
+
+/// Generate a Mermaid sequence diagram DSL string from a resolution graph.
+///
+/// # Arguments
+/// * `component` - The component (agent) requesting the behavior
+/// * `behavior` - The behavior being resolved
+/// * `graph` - The resolution graph from `Tracker::resolve_graph`
+///
+/// # Returns
+/// A string containing the complete Mermaid sequence diagram DSL.
+pub fn diagram_graph(component: &str, behavior: &str, graph: &ResolutionGraph) -> String {
+    let lines = generate_lines(
+        DiagramInput {
+            component,
+            behavior,
+            graph,
+            resolution: graph.get_root(),
+        },
+        &mut HashSet::new(),
+    );
 
     // Build the final diagram with proper indentation
     let mut result = vec!["sequenceDiagram".to_string()];
@@ -204,4 +257,64 @@ mod tests {
         assert!(result.contains("c1 ->> a1: b1"));
         assert!(result.contains("a1 ->> a2: b2")); // nested condition
     }
+
+    #[test]
+    fn test_cyclic_resolution() {
+        let mut tracker = Tracker::new();
+        tracker.add_agent(Agent::build("a1").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec!["b2".to_string()]),
+        ]));
+        tracker.add_agent(Agent::build("a2").with_provides(vec![
+            Behavior::build("b2").with_conditions(vec!["b1".to_string()]),
+        ]));
+
+        let resolution = tracker.resolve("b1");
+        let result = diagram("c1", "b1", &resolution);
+
+        assert!(result.contains("c1 ->> a1: b1"));
+        assert!(result.contains("a1 ->> a2: b2"));
+        assert!(result.contains("a2 -X a2: b1 (cycle: b1 -> b2 -> b1)"));
+        assert!(!result.contains("rgb(0,255,0)"));
+    }
+
+    #[test]
+    fn test_shared_condition_drawn_once() {
+        let mut tracker = Tracker::new();
+        tracker.add_agent(Agent::build("a1").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec!["b2".to_string()]),
+        ]));
+        tracker.add_agent(Agent::build("a2").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec!["b2".to_string()]),
+        ]));
+        tracker.add_agent(Agent::build("a3").with_provides(vec![Behavior::build("b2")]));
+
+        let result = diagram_graph("c1", "b1", &tracker.resolve_graph("b1"));
+
+        assert!(result.contains("c1 ->> a1: b1"));
+        assert!(result.contains("c1 ->> a2: b1"));
+        assert_eq!(result.matches("->> a3: b2").count(), 1);
+        assert!(result.contains("a2 -->> a2: b2 (see above)"));
+    }
+
+    #[test]
+    fn test_any_of() {
+        let mut tracker = Tracker::new();
+        tracker.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")
+            .with_conditions(vec!["b2".to_string()])
+            .with_any_of(vec![vec!["cache".to_string()], vec!["fallback".to_string()]])]));
+        tracker.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b2")]));
+        tracker.add_agent(Agent::build("a3").with_provides(vec![Behavior::build("fallback")]));
+
+        let result = diagram_graph("c1", "b1", &tracker.resolve_graph("b1"));
+
+        assert!(result.contains("c1 ->> a1: b1"));
+        assert!(result.contains("a1 ->> a2: b2"));
+        assert!(result.contains("alt any of 1"));
+        assert!(result.contains("a1 -X a1: cache"));
+        assert!(result.contains("else any of 2"));
+        assert!(result.contains("a1 ->> a3: fallback"));
+        // satisfied through the second group
+        assert!(!result.contains("c1 -X"));
+        assert!(result.starts_with("sequenceDiagram\n    rect rgb(0,255,0)"));
+    }
 }
@@ -0,0 +1,342 @@
+//! Blast-radius analysis.
+//!
+//! `impact` answers "what breaks if these agents go away?" by removing them
+//! from a copy of the `Tracker` and comparing every want of the agents that
+//! are left before and after. A want that was satisfied and no longer is
+//! counts as broken, whether the removed agents provided it themselves or
+//! something it depends on through conditions.
+
+use crate::components::Criticality;
+use crate::report::{SatisfactionReport, WantStatus};
+use crate::Tracker;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+
+/// A want that was satisfied before the removal and isn't after it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BrokenWant {
+    pub agent_name: String,
+    pub behavior_name: String,
+    pub criticality: Criticality,
+    /// How it resolves after the removal
+    pub status: WantStatus,
+    /// True if a removed agent provided it, false if it only broke because
+    /// of a condition further down
+    pub direct: bool,
+    /// See `ResolutionGraph::get_root_causes`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub root_causes: Vec<Vec<String>>,
+}
+
+impl BrokenWant {
+    /// Same as `ResolutionGraph::to_blocked_by_string`
+    pub fn to_blocked_by_string(&self) -> String {
+        if self.root_causes.is_empty() {
+            return String::from("blocked by: dependency cycle");
+        }
+        let sets: Vec<String> = self.root_causes.iter().map(|set| set.join(" + ")).collect();
+        format!("blocked by: {}", sets.join(" or "))
+    }
+}
+
+impl std::fmt::Display for BrokenWant {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} loses {}", self.agent_name, self.behavior_name)?;
+        if !self.direct {
+            write!(f, " (through its conditions)")?;
+        }
+        Ok(())
+    }
+}
+
+/// What removing some agents breaks
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Impact {
+    /// The agents, SuperAgents and instances that were removed, including
+    /// the members of removed SuperAgents, sorted
+    pub removed: Vec<String>,
+    /// Names that are neither an agent, a SuperAgent nor an instance, sorted
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<String>,
+    /// By agent, then behavior name
+    pub broken: Vec<BrokenWant>,
+}
+
+impl Impact {
+    /// True if any of the agents that are left loses something it can't
+    /// work without
+    pub fn has_broken_required(&self) -> bool {
+        self.broken.iter().any(|b| b.criticality.is_required())
+    }
+
+    /// The agents that lose at least one want, sorted
+    pub fn get_affected_agents(&self) -> Vec<&str> {
+        let mut ret: Vec<&str> = self.broken.iter().map(|b| b.agent_name.as_str()).collect();
+        ret.dedup();
+        ret
+    }
+
+    /// One line summary, e.g. `removing a1, a2 breaks 3 wants of 2 agents`
+    pub fn to_summary_string(&self) -> String {
+        let agents = self.get_affected_agents().len();
+        format!(
+            "removing {} breaks {} want{} of {} agent{}",
+            self.removed.join(", "),
+            self.broken.len(),
+            if self.broken.len() == 1 { "" } else { "s" },
+            agents,
+            if agents == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Removes every agent, SuperAgent and SuperAgent instance named in
+/// `agent_names` from a copy of `tracker` and reports which wants of the
+/// remaining agents go from satisfied to unsatisfied. A SuperAgent goes away
+/// along with everything it contains, and so does one whose instances are
+/// all removed. Wants of the removed agents themselves, and conditional
+/// wants that no longer apply, are not counted.
+pub fn impact(tracker: &Tracker, agent_names: &[&str]) -> Impact {
+    impact_with_report(tracker, &tracker.resolve_all(), agent_names)
+}
+
+/// Same as `impact`, but with `before` as the report of `tracker` (from
+/// `Tracker::resolve_all`), so it isn't resolved again
+pub fn impact_with_report(
+    tracker: &Tracker,
+    before: &SatisfactionReport,
+    agent_names: &[&str],
+) -> Impact {
+    let mut after = tracker.clone();
+    let mut removed = BTreeSet::new();
+    let mut unknown = BTreeSet::new();
+    for name in agent_names {
+        let owners: Vec<String> = after
+            .available_superagents
+            .iter()
+            .filter(|sa| sa.item.get_instance_names().iter().any(|i| i == name))
+            .map(|sa| sa.item.get_name().clone())
+            .collect();
+        let mut found = remove_with_members(tracker, &mut after, name, &mut removed);
+        if after.remove_instance(name) {
+            found = true;
+            removed.insert(String::from(*name));
+            // rather than letting it turn back into an agent of its own
+            for owner in owners {
+                let emptied = after.available_superagents.iter().any(|sa| {
+                    *sa.item.get_name() == owner && sa.item.get_instance_names().is_empty()
+                });
+                if emptied {
+                    remove_with_members(tracker, &mut after, &owner, &mut removed);
+                }
+            }
+        }
+        if !found && !removed.contains(*name) {
+            unknown.insert(String::from(*name));
+        }
+    }
+    Impact {
+        removed: removed.into_iter().collect(),
+        unknown: unknown.into_iter().collect(),
+        broken: broken_wants(before, &after.resolve_all()),
+    }
+}
+
+// Removes the agent or SuperAgent and, for a SuperAgent, everything it
+// contains. Returns false if there is neither.
+fn remove_with_members(
+    tracker: &Tracker,
+    after: &mut Tracker,
+    name: &str,
+    removed: &mut BTreeSet<String>,
+) -> bool {
+    let mut names = BTreeSet::new();
+    with_members(tracker, name, &mut names);
+    let mut found = false;
+    for n in names {
+        // both, in case an agent and a SuperAgent share the name
+        let removed_agent = after.remove_agent(&n);
+        let removed_superagent = after.remove_superagent(&n);
+        if removed_agent || removed_superagent {
+            found = true;
+            removed.insert(n);
+        }
+    }
+    found
+}
+
+// Adds the name and, if it is a SuperAgent, everything it contains
+fn with_members(tracker: &Tracker, name: &str, names: &mut BTreeSet<String>) {
+    if !names.insert(String::from(name)) {
+        return;
+    }
+    for sa in tracker
+        .available_superagents
+        .iter()
+        .filter(|sa| sa.item.get_name() == name)
+    {
+        for member in sa.item.get_agent_names() {
+            with_members(tracker, &member, names);
+        }
+    }
+}
+
+fn broken_wants(before: &SatisfactionReport, after: &SatisfactionReport) -> Vec<BrokenWant> {
+    let remaining: HashSet<&str> = after.agents.iter().map(|a| a.agent_name.as_str()).collect();
+    let gone: HashSet<&str> = before
+        .agents
+        .iter()
+        .map(|a| a.agent_name.as_str())
+        .filter(|a| !remaining.contains(a))
+        .collect();
+    let mut ret = vec![];
+    for agent in &after.agents {
+        let Some(was) = before.get_agent(&agent.agent_name) else {
+            continue;
+        };
+        for want in agent.wants.iter().filter(|w| !w.is_satisfied()) {
+            let Some(old) = was
+                .wants
+                .iter()
+                .find(|w| w.behavior_name == want.behavior_name && w.is_satisfied())
+            else {
+                continue;
+            };
+            ret.push(BrokenWant {
+                agent_name: agent.agent_name.clone(),
+                behavior_name: want.behavior_name.clone(),
+                criticality: want.criticality,
+                status: want.status,
+                direct: old
+                    .resolution
+                    .get_root_node()
+                    .get_satisfying_offers()
+                    .iter()
+                    .any(|o| gone.contains(o.get_agent_name())),
+                root_causes: want.root_causes.clone(),
+            });
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Agent, Behavior, SuperAgent};
+
+    #[test]
+    fn test_impact() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("p1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("p2").with_provides(vec![
+            Behavior::build("b2").with_conditions(vec![String::from("b1")]),
+        ]));
+        t.add_agent(Agent::build("p3").with_provides(vec![Behavior::build("b3")]));
+        t.add_agent(Agent::build("w1").with_wants(vec![
+            Behavior::build("b1"),
+            Behavior::build("b2").with_criticality(Criticality::Optional),
+        ]));
+        t.add_agent(Agent::build("w2").with_wants(vec![
+            Behavior::build("b2"),
+            Behavior::build("b3"),
+            Behavior::build("b4"),
+        ]));
+
+        let i = impact(&t, &["p1", "nope", "p1"]);
+        assert_eq!(
+            impact_with_report(&t, &t.resolve_all(), &["p1", "nope", "p1"]),
+            i
+        );
+        assert_eq!(i.removed, ["p1"]);
+        assert_eq!(i.unknown, ["nope"]);
+        assert_eq!(
+            i.broken
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<String>>(),
+            [
+                "w1 loses b1",
+                "w1 loses b2 (through its conditions)",
+                "w2 loses b2 (through its conditions)",
+            ]
+        );
+        assert_eq!(i.broken[0].status, WantStatus::NoProvider);
+        assert_eq!(i.broken[1].status, WantStatus::Unsatisfied);
+        assert_eq!(i.broken[1].to_blocked_by_string(), "blocked by: b1");
+        assert_eq!(i.get_affected_agents(), ["w1", "w2"]);
+        assert!(i.has_broken_required());
+        assert_eq!(
+            i.to_summary_string(),
+            "removing p1 breaks 3 wants of 2 agents"
+        );
+
+        // another provider takes over
+        t.add_agent(Agent::build("p4").with_provides(vec![Behavior::build("b1")]));
+        assert!(impact(&t, &["p1"]).broken.is_empty());
+
+        // wants of the removed agent don't count
+        let i = impact(&t, &["w2", "p3"]);
+        assert_eq!(i.removed, ["p3", "w2"]);
+        assert!(i.broken.is_empty());
+    }
+
+    #[test]
+    fn test_impact_superagent() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec![String::from("b2")]),
+        ]));
+        t.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b2")]));
+        t.add_agent(Agent::build("w1").with_wants(vec![Behavior::build("b1")]));
+        t.add_superagent(
+            SuperAgent::new(String::from("sa1"))
+                .with_agent("a1")
+                .with_agent("a2"),
+        );
+
+        let i = impact(&t, &["sa1"]);
+        assert_eq!(i.removed, ["a1", "a2", "sa1"]);
+        assert_eq!(i.broken.len(), 1);
+        assert!(i.broken[0].direct);
+
+        // a member going away breaks the SuperAgent from the inside
+        let i = impact(&t, &["a2"]);
+        assert_eq!(i.broken.len(), 1);
+        assert!(!i.broken[0].direct);
+        assert_eq!(i.broken[0].to_blocked_by_string(), "blocked by: b2");
+    }
+
+    #[test]
+    fn test_impact_instance() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("w1").with_wants(vec![Behavior::build("b1 | eu")]));
+        t.add_agent(Agent::build("w2").with_wants(vec![Behavior::build("b1 | *")]));
+        t.add_superagent(
+            SuperAgent::new(String::from("sa1"))
+                .with_agent("a1")
+                .with_instance("i1", "", "eu", "", vec![], vec![])
+                .with_instance("i2", "", "us", "", vec![], vec![]),
+        );
+
+        // i2 still provides some instance of b1
+        let i = impact(&t, &["i1"]);
+        assert!(i.unknown.is_empty());
+        assert_eq!(i.removed, ["i1"]);
+        assert_eq!(
+            i.broken
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<String>>(),
+            ["w1 loses b1 | eu"]
+        );
+
+        // the last instance takes the SuperAgent and its members with it
+        let i = impact(&t, &["i1", "i2"]);
+        assert_eq!(i.removed, ["a1", "i1", "i2", "sa1"]);
+        assert_eq!(i.get_affected_agents(), ["w1", "w2"]);
+    }
+}
@@ -1,22 +1,145 @@
+pub mod allocation;
 pub mod components;
+pub mod deployment;
 pub mod diagram;
+pub mod impact;
+pub mod lint;
+pub mod loader;
 pub mod network_diagram;
+pub mod placement;
+pub mod redundancy;
 
+use allocation::{Allocation, Consumer, ProviderLoad};
 use components::Agent;
+use components::Behavior;
+use components::BehaviorId;
+use components::Criticality;
 use components::Item;
+use components::Location;
 use components::SuperAgent;
+use components::SuperAgentMember;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::MutexGuard;
 
+mod interner;
+pub mod report;
+pub mod resolution_graph;
 pub mod resolve;
-use resolve::Offer;
+mod scc;
+pub mod selector;
+mod similarity;
+pub mod startup;
+pub mod validate;
+pub mod version;
+use interner::{Interner, Symbol};
+use placement::{LabelFilter, Placement, Rejection};
+use report::{AgentReport, SatisfactionReport, SkippedWant, WantReport};
+use resolution_graph::{OfferNode, ResolutionArena, ResolutionCache};
+use resolution_graph::{ResolutionGraph, ResolutionId, ResolutionNode};
 use resolve::Resolution;
+use selector::{Labels, Selector};
+use semver::{Version, VersionReq};
+use startup::StartupOrder;
+use version::VersionMismatch;
 
 #[derive(Debug, Clone)]
 pub struct Tracker {
-    available_agents: Vec<Agent>,
-    available_superagents: Vec<SuperAgent>,
+    available_agents: Vec<Sourced<Agent>>,
+    available_superagents: Vec<Sourced<SuperAgent>>,
     working_agents: HashMap<String, Vec<Agent>>,
+    // names of every working agent and every behavior they provide or depend on
+    names: Interner,
+    // behavior -> everything that provides it, in agent name order
+    providers: HashMap<Symbol, Vec<IndexedProvide>>,
+    // behavior -> id of the dependency cycle it is part of (only for
+    // behaviors that can reach themselves through other behaviors)
+    behavior_cycles: HashMap<Symbol, usize>,
+    resolutions: ResolutionCache,
+}
+
+// An agent or superagent as it was added, along with the source (e.g. the
+// file) it was loaded from, if any
+#[derive(Debug, Clone, PartialEq)]
+struct Sourced<T> {
+    source: Option<String>,
+    item: T,
+}
+
+// A single provide of a working agent, as stored in `Tracker::providers`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexedProvide {
+    agent: Symbol,
+    conditions: Vec<Symbol>,
+    any_of: Vec<Vec<Symbol>>,
+    // None if not given or not valid semver
+    version: Option<Version>,
+    // The version each condition has to be provided at, if any
+    requirements: Vec<(Symbol, VersionReq)>,
+    // The labels each condition's provider has to have, if any. Worked out
+    // from the condition placements and the labels of this agent.
+    placements: Vec<(Symbol, LabelFilter)>,
+    // Where the provide was first defined, shown next to its offers
+    location: Option<Location>,
+}
+
+impl IndexedProvide {
+    fn get_constraints(&self, condition: Symbol) -> Constraints {
+        Constraints {
+            version: self
+                .requirements
+                .iter()
+                .find(|(c, _)| *c == condition)
+                .map(|(_, r)| r.clone()),
+            placement: self
+                .placements
+                .iter()
+                .find(|(c, _)| *c == condition)
+                .map(|(_, f)| f.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// What a behavior is wanted with besides its name. Providers that don't meet
+// them are left out of the resolution.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Constraints {
+    version: Option<VersionReq>,
+    placement: LabelFilter,
+}
+
+impl Constraints {
+    fn is_empty(&self) -> bool {
+        self.version.is_none() && self.placement.is_empty()
+    }
+
+    // An empty node for the behavior that records what it was wanted with
+    fn to_node(&self, behavior_name: &str) -> ResolutionNode {
+        ResolutionNode::new(behavior_name)
+            .with_requirement(self.version.as_ref().map(|r| r.to_string()))
+            .with_placement(if self.placement.is_empty() {
+                None
+            } else {
+                Some(self.placement.to_string())
+            })
+    }
+}
+
+// A behavior looked up under some constraints. Its resolution only depends on
+// these, so each one is resolved once.
+type Lookup = (Symbol, Constraints);
+
+// The name a behavior is indexed under. Tagged names are written in more
+// than one way (`db|eu`, `db | eu`), so they are always looked up in their
+// canonical form.
+fn behavior_key(behavior_name: &str) -> String {
+    BehaviorId::from(behavior_name).to_string()
 }
 
 // Need:
@@ -29,6 +152,10 @@ impl Tracker {
             available_agents: vec![],
             available_superagents: vec![],
             working_agents: HashMap::new(),
+            names: Interner::default(),
+            providers: HashMap::new(),
+            behavior_cycles: HashMap::new(),
+            resolutions: ResolutionCache::default(),
         }
     }
 
@@ -37,23 +164,15 @@ impl Tracker {
     }
 
     pub fn add_agent(&mut self, a: Agent) {
-        for existing in &self.available_agents {
-            if existing == &a {
-                return;
-            }
+        if Self::push_sourced(&mut self.available_agents, None, a) {
+            self.rebuild();
         }
-        let _ = &self.available_agents.push(a.clone());
-        self.rebuild();
     }
 
     pub fn add_superagent(&mut self, sa: SuperAgent) {
-        for existing in &self.available_superagents {
-            if existing == &sa {
-                return;
-            }
+        if Self::push_sourced(&mut self.available_superagents, None, sa) {
+            self.rebuild();
         }
-        let _ = &self.available_superagents.push(sa.clone());
-        self.rebuild();
     }
 
     pub fn add_item(&mut self, i: Item) {
@@ -63,53 +182,200 @@ impl Tracker {
         }
     }
 
+    /// Adds all of the items and rebuilds once at the end, rather than once
+    /// per item like `add_item` does
+    pub fn add_items(&mut self, items: Vec<Item>) {
+        if self.push_items(None, items) {
+            self.rebuild();
+        }
+    }
+
+    /// Adds all of the items as coming from `source` (e.g. a file path), so
+    /// that they can later be replaced or removed together
+    pub fn add_source_items(&mut self, source: &str, items: Vec<Item>) {
+        if self.push_items(Some(source), items) {
+            self.rebuild();
+        }
+    }
+
+    /// Removes every agent with this name, no matter where it was loaded
+    /// from. Returns false if there wasn't one.
+    pub fn remove_agent(&mut self, agent_name: &str) -> bool {
+        let before = self.available_agents.len();
+        self.available_agents
+            .retain(|a| a.item.get_name() != agent_name);
+        if self.available_agents.len() == before {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+
+    /// Removes every superagent with this name, no matter where it was
+    /// loaded from. Returns false if there wasn't one.
+    pub fn remove_superagent(&mut self, superagent_name: &str) -> bool {
+        let before = self.available_superagents.len();
+        self.available_superagents
+            .retain(|sa| sa.item.get_name() != superagent_name);
+        if self.available_superagents.len() == before {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+
+    /// Removes every SuperAgent instance with this name, no matter which
+    /// SuperAgent it belongs to. A SuperAgent left without instances becomes
+    /// a single agent again. Returns false if there wasn't one.
+    pub fn remove_instance(&mut self, instance_name: &str) -> bool {
+        let mut removed = false;
+        for sa in self.available_superagents.iter_mut() {
+            removed |= sa.item.remove_instance(instance_name);
+        }
+        if !removed {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+
+    /// Removes every agent or superagent (matching the kind of `i`) with the
+    /// same name as `i`, then adds `i` in their place
+    pub fn replace_item(&mut self, i: Item) {
+        match &i {
+            Item::Agent(a) => self
+                .available_agents
+                .retain(|existing| existing.item.get_name() != a.get_name()),
+            Item::SuperAgent(sa) => self
+                .available_superagents
+                .retain(|existing| existing.item.get_name() != sa.get_name()),
+        }
+        self.push_items(None, vec![i]);
+        self.rebuild();
+    }
+
+    /// Removes everything that was added from `source`. Returns false if
+    /// nothing was.
+    pub fn remove_source(&mut self, source: &str) -> bool {
+        if !self.drop_source(source) {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+
+    /// Swaps everything that was added from `source` for `items`
+    pub fn replace_source(&mut self, source: &str, items: Vec<Item>) {
+        self.replace_sources(vec![(String::from(source), items)]);
+    }
+
+    /// Same as `replace_source` for several sources, rebuilding once at the
+    /// end. A source with no items is removed.
+    pub fn replace_sources(&mut self, sources: Vec<(String, Vec<Item>)>) {
+        for (source, items) in sources {
+            self.drop_source(&source);
+            self.push_items(Some(&source), items);
+        }
+        self.rebuild();
+    }
+
+    /// Every source that items were added from, sorted
+    pub fn get_source_names(&self) -> Vec<&String> {
+        let mut ret: Vec<&String> = self
+            .available_agents
+            .iter()
+            .filter_map(|a| a.source.as_ref())
+            .chain(
+                self.available_superagents
+                    .iter()
+                    .filter_map(|sa| sa.source.as_ref()),
+            )
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    /// The items that were added from `source`, superagents after agents
+    pub fn get_source_items(&self, source: &str) -> Vec<Item> {
+        let from_source = |s: &Option<String>| s.as_deref() == Some(source);
+        self.available_agents
+            .iter()
+            .filter(|a| from_source(&a.source))
+            .map(|a| Item::Agent(a.item.clone()))
+            .chain(
+                self.available_superagents
+                    .iter()
+                    .filter(|sa| from_source(&sa.source))
+                    .map(|sa| Item::SuperAgent(sa.item.clone())),
+            )
+            .collect()
+    }
+
+    // Returns false if every item was already there
+    fn push_items(&mut self, source: Option<&str>, items: Vec<Item>) -> bool {
+        let mut changed = false;
+        for i in items {
+            changed |= match i {
+                Item::Agent(a) => Self::push_sourced(&mut self.available_agents, source, a),
+                Item::SuperAgent(sa) => {
+                    Self::push_sourced(&mut self.available_superagents, source, sa)
+                }
+            };
+        }
+        changed
+    }
+
+    // Exact duplicates from the same source are skipped; returns false if so
+    fn push_sourced<T: PartialEq>(
+        list: &mut Vec<Sourced<T>>,
+        source: Option<&str>,
+        item: T,
+    ) -> bool {
+        let sourced = Sourced {
+            source: source.map(String::from),
+            item,
+        };
+        if list.contains(&sourced) {
+            return false;
+        }
+        list.push(sourced);
+        true
+    }
+
+    // Returns false if nothing came from the source
+    fn drop_source(&mut self, source: &str) -> bool {
+        let before = self.available_agents.len() + self.available_superagents.len();
+        self.available_agents
+            .retain(|a| a.source.as_deref() != Some(source));
+        self.available_superagents
+            .retain(|sa| sa.source.as_deref() != Some(source));
+        self.available_agents.len() + self.available_superagents.len() != before
+    }
+
     pub fn rebuild(&mut self) {
+        let nested = self.get_nested_superagents();
+        let superagent_names = self.get_superagent_names();
         let mut new_working_agents: HashMap<String, Vec<Agent>> = HashMap::new();
         let mut all_contained_agent_names = HashSet::new();
-        for sa in &self.available_superagents {
-            let contained_agents_names = sa.get_agent_names();
-            for contained_agent_name in contained_agents_names.iter() {
-                all_contained_agent_names.insert(contained_agent_name.clone());
-            }
-            // build out a stub agent that is a combination of all of the contained agents
-            let mut stub_agent = Agent::new(sa.get_name().clone());
-            self.available_agents
-                .iter()
-                .filter(|a| contained_agents_names.contains(a.get_name()))
-                .for_each(|a| {
-                    stub_agent.merge(a);
-                });
-            // reduce its behaviors to those that are not internally handled
-            stub_agent.reduce();
-
-            // if there are instances of this sa, use those; otherwise use itself
-            let instances = sa.get_instances();
-            if instances.len() == 0 {
-                let e = new_working_agents
-                    .entry(stub_agent.get_name().clone())
-                    .or_insert(vec![stub_agent.clone()]);
-                e[0].merge(&stub_agent);
+        for sa in self.available_superagents.iter().map(|sa| &sa.item) {
+            for contained_agent_name in sa.get_agent_names() {
+                if !superagent_names.contains(&contained_agent_name) {
+                    all_contained_agent_names.insert(contained_agent_name);
+                }
+            }
+            // SuperAgents inside other SuperAgents only show up as part of them
+            if nested.values().any(|inner| inner.contains(sa.get_name())) {
                 continue;
             }
-            for i in instances.iter() {
-                let mut instance_agent = stub_agent.make_instance(
-                    i.get_name(),
-                    i.get_provides_tags(),
-                    i.get_conditions_tags(),
-                );
-                for p in i.get_provides().iter() {
-                    instance_agent.add_provide(p.clone());
-                }
-                for w in i.get_wants().iter() {
-                    instance_agent.add_want(w.clone());
-                }
+            for agent in self.expand_superagent(sa, &nested) {
                 let e = new_working_agents
-                    .entry(instance_agent.get_name().clone())
-                    .or_insert(vec![instance_agent.clone()]);
-                e[0].merge(&instance_agent);
+                    .entry(agent.get_name().clone())
+                    .or_insert(vec![agent.clone()]);
+                e[0].merge(&agent);
             }
         }
-        for a in &self.available_agents {
+        for a in self.available_agents.iter().map(|a| &a.item) {
             if all_contained_agent_names.contains(a.get_name()) {
                 continue;
             }
@@ -119,12 +385,297 @@ impl Tracker {
             e[0].merge(&a);
         }
         self.working_agents = new_working_agents;
+        self.build_provider_index();
+        self.behavior_cycles = self.find_behavior_cycles();
+        self.resolutions = ResolutionCache::default();
+    }
+
+    // The agents a SuperAgent becomes: one per instance, or a single one
+    // named after it. SuperAgents it contains are expanded first and merged
+    // in like any other member, so internal behaviors are reduced and
+    // instances made at every level of nesting.
+    fn expand_superagent(
+        &self,
+        sa: &SuperAgent,
+        nested: &HashMap<String, Vec<String>>,
+    ) -> Vec<Agent> {
+        let contained_agents_names = sa.get_agent_names();
+        // build out a stub agent that is a combination of all of the contained agents
+        let mut stub_agent = Agent::new(sa.get_name().clone()).with_labels(sa.get_labels().clone());
+        stub_agent.add_provenance(sa.get_provenance());
+        self.available_agents
+            .iter()
+            .map(|a| &a.item)
+            .filter(|a| contained_agents_names.contains(a.get_name()))
+            .filter(|a| !self.is_superagent(a.get_name()))
+            .for_each(|a| {
+                stub_agent.merge(a);
+            });
+        let inner_names = nested.get(sa.get_name()).map_or(&[][..], Vec::as_slice);
+        self.available_superagents
+            .iter()
+            .map(|inner| &inner.item)
+            .filter(|inner| inner_names.contains(inner.get_name()))
+            .flat_map(|inner| self.expand_superagent(inner, nested))
+            .for_each(|a| {
+                stub_agent.merge(&a);
+            });
+        // reduce its behaviors to those that are not internally handled
+        stub_agent.reduce();
+
+        // if there are instances of this sa, use those; otherwise use itself
+        let instances = sa.get_instances();
+        if instances.len() == 0 {
+            return vec![stub_agent];
+        }
+        let mut ret = vec![];
+        for i in instances.iter() {
+            let mut instance_agent = stub_agent.make_instance(
+                i.get_name(),
+                i.get_provides_tags(),
+                i.get_conditions_tags(),
+            );
+            // the instance's own labels win over the SuperAgent's
+            let mut labels = instance_agent.get_labels().clone();
+            labels.extend(i.get_labels().clone());
+            instance_agent = instance_agent.with_labels(labels);
+            instance_agent.add_provenance(i.get_provenance());
+            instance_agent.add_provenance(stub_agent.get_provenance());
+            for p in i.get_provides().iter() {
+                instance_agent.add_provide(p.clone());
+            }
+            for w in i.get_wants().iter() {
+                instance_agent.add_want(w.clone());
+            }
+            ret.push(instance_agent);
+        }
+        ret
+    }
+
+    fn get_superagent_names(&self) -> HashSet<String> {
+        self.available_superagents
+            .iter()
+            .map(|sa| sa.item.get_name().clone())
+            .collect()
+    }
+
+    fn is_superagent(&self, name: &str) -> bool {
+        self.available_superagents
+            .iter()
+            .any(|sa| sa.item.get_name() == name)
+    }
+
+    // SuperAgent name -> the SuperAgents it lists as members
+    fn get_superagent_containment(&self) -> HashMap<String, Vec<String>> {
+        let superagent_names = self.get_superagent_names();
+        let mut ret: HashMap<String, Vec<String>> = HashMap::new();
+        for sa in self.available_superagents.iter().map(|sa| &sa.item) {
+            let inner = ret.entry(sa.get_name().clone()).or_default();
+            for member in sa.get_agent_names() {
+                if superagent_names.contains(&member) && !inner.contains(&member) {
+                    inner.push(member);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Groups of SuperAgents that contain each other, directly or through
+    /// other SuperAgents, sorted. A SuperAgent that contains itself is a
+    /// group of one. They can't be nested, so none of them is expanded
+    /// inside the others.
+    pub fn get_superagent_cycles(&self) -> Vec<Vec<String>> {
+        let containment = self.get_superagent_containment();
+        let mut ret: Vec<Vec<String>> = scc::strongly_connected_components(&containment)
+            .into_iter()
+            .filter(|c| c.len() > 1 || containment[&c[0]].contains(&c[0]))
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    // Same as `get_superagent_containment`, without the SuperAgents that
+    // contain each other
+    fn get_nested_superagents(&self) -> HashMap<String, Vec<String>> {
+        let cycles = self.get_superagent_cycles();
+        let cycle_of = |name: &String| cycles.iter().position(|c| c.contains(name));
+        let mut ret = self.get_superagent_containment();
+        for (outer, inner) in ret.iter_mut() {
+            inner.retain(|i| cycle_of(i).is_none() || cycle_of(i) != cycle_of(outer));
+        }
+        ret
+    }
+
+    /// What the working agent is made of, if it comes from a SuperAgent (or
+    /// one of its instances): the agents and SuperAgents it contains, in the
+    /// order they are listed, each with its own members. Members that are
+    /// not defined are left out.
+    pub fn get_superagent_members(&self, agent_name: &str) -> Vec<SuperAgentMember> {
+        let nested = self.get_nested_superagents();
+        let is_nested = |name: &String| nested.values().any(|inner| inner.contains(name));
+        let Some(sa) = self
+            .available_superagents
+            .iter()
+            .map(|sa| &sa.item)
+            .filter(|sa| !is_nested(sa.get_name()))
+            .find(|sa| {
+                sa.get_name() == agent_name
+                    || sa.get_instance_names().iter().any(|i| i == agent_name)
+            })
+        else {
+            return vec![];
+        };
+        self.members_of(sa.get_name(), &nested)
+    }
+
+    fn members_of(
+        &self,
+        superagent_name: &str,
+        nested: &HashMap<String, Vec<String>>,
+    ) -> Vec<SuperAgentMember> {
+        let inner = nested.get(superagent_name).map_or(&[][..], Vec::as_slice);
+        let mut ret: Vec<SuperAgentMember> = vec![];
+        for sa in self
+            .available_superagents
+            .iter()
+            .map(|sa| &sa.item)
+            .filter(|sa| sa.get_name() == superagent_name)
+        {
+            for member in sa.get_agent_names() {
+                if ret.iter().any(|m| m.name == member) {
+                    continue;
+                }
+                if inner.contains(&member) {
+                    ret.push(SuperAgentMember {
+                        members: self.members_of(&member, nested),
+                        name: member,
+                    });
+                } else if !self.is_superagent(&member)
+                    && self
+                        .available_agents
+                        .iter()
+                        .any(|a| *a.item.get_name() == member)
+                {
+                    ret.push(SuperAgentMember {
+                        name: member,
+                        members: vec![],
+                    });
+                }
+            }
+        }
+        ret
+    }
+
+    // Interns every working name and records, for each behavior, which
+    // agents provide it and under what conditions. Providers are kept in
+    // agent name order so resolutions come out in a stable order.
+    fn build_provider_index(&mut self) {
+        let mut names = Interner::default();
+        let mut providers: HashMap<Symbol, Vec<IndexedProvide>> = HashMap::new();
+        let mut agent_names: Vec<&String> = self.working_agents.keys().collect();
+        agent_names.sort();
+        for agent_name in agent_names {
+            let agent = names.intern(agent_name);
+            let labels = self
+                .get_agent_labels(agent_name)
+                .cloned()
+                .unwrap_or_default();
+            for variant_agent in &self.working_agents[agent_name] {
+                for b in variant_agent.iter_provides() {
+                    let provide = IndexedProvide {
+                        agent,
+                        conditions: b
+                            .get_conditions()
+                            .iter()
+                            .map(|c| names.intern(&behavior_key(c)))
+                            .collect(),
+                        any_of: b
+                            .get_any_of()
+                            .iter()
+                            .map(|g| g.iter().map(|c| names.intern(&behavior_key(c))).collect())
+                            .collect(),
+                        version: b.get_version().and_then(|v| version::parse_version(v).ok()),
+                        requirements: b
+                            .get_condition_versions()
+                            .iter()
+                            .filter_map(|(c, r)| {
+                                Some((
+                                    names.intern(&behavior_key(c)),
+                                    version::parse_requirement(r).ok()?,
+                                ))
+                            })
+                            .collect(),
+                        placements: b
+                            .get_condition_placements()
+                            .iter()
+                            .filter(|(_, p)| !p.is_empty())
+                            .map(|(c, p)| (names.intern(&behavior_key(c)), p.to_filter(&labels)))
+                            .collect(),
+                        location: b
+                            .get_provenance()
+                            .get_first()
+                            .or(variant_agent.get_provenance().get_first())
+                            .cloned(),
+                    };
+                    // also listed under every `| *` name that matches it
+                    let keys = std::iter::once(behavior_key(b.get_name()))
+                        .chain(b.get_id().get_any_instance_names());
+                    for key in keys {
+                        let entries = providers.entry(names.intern(&key)).or_default();
+                        if !entries.iter().any(|e| {
+                            e.agent == provide.agent
+                                && e.conditions == provide.conditions
+                                && e.any_of == provide.any_of
+                                && e.version == provide.version
+                                && e.requirements == provide.requirements
+                                && e.placements == provide.placements
+                        }) {
+                            entries.push(provide.clone());
+                        }
+                    }
+                }
+            }
+        }
+        self.names = names;
+        self.providers = providers;
+    }
+
+    // Groups behaviors that depend on each other (or on themselves) through
+    // conditions
+    fn find_behavior_cycles(&self) -> HashMap<Symbol, usize> {
+        let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
+        for (behavior, provides) in &self.providers {
+            let conditions = dependencies
+                .entry(String::from(self.names.resolve(*behavior)))
+                .or_default();
+            for p in provides {
+                conditions.extend(
+                    p.conditions
+                        .iter()
+                        .chain(p.any_of.iter().flatten())
+                        .map(|c| String::from(self.names.resolve(*c))),
+                );
+            }
+        }
+        let mut ret = HashMap::new();
+        for (i, component) in scc::strongly_connected_components(&dependencies)
+            .into_iter()
+            .filter(|c| c.len() > 1 || dependencies.get(&c[0]).is_some_and(|d| d.contains(&c[0])))
+            .enumerate()
+        {
+            for behavior_name in component {
+                if let Some(behavior) = self.names.get(&behavior_name) {
+                    ret.insert(behavior, i);
+                }
+            }
+        }
+        ret
     }
 
     pub fn get_agent_names(&self) -> Vec<&String> {
         let mut ret = vec![];
         for a in &self.available_agents {
-            ret.push(a.get_name());
+            ret.push(a.item.get_name());
         }
         ret.sort();
         ret
@@ -170,17 +721,325 @@ impl Tracker {
         ret
     }
 
+    /// The labels of a working agent, after SuperAgents are resolved
+    pub fn get_agent_labels(&self, agent_name: &str) -> Option<&Labels> {
+        self.working_agents
+            .get(agent_name)?
+            .first()
+            .map(|a| a.get_labels())
+    }
+
+    /// The working agents whose labels match `selector`, sorted by name
+    pub fn select_agent_names(&self, selector: &Selector) -> Vec<&String> {
+        self.get_working_agent_names()
+            .into_iter()
+            .filter(|a| {
+                self.get_agent_labels(a)
+                    .is_some_and(|labels| selector.matches(labels))
+            })
+            .collect()
+    }
+
+    /// The wants of the agent that apply. A want with conditions only applies
+    /// when they are provided somewhere in the network (by this agent or any
+    /// other), and one with own conditions only when this agent provides them
+    /// itself; see `get_skipped_wants` for the rest.
     pub fn get_agent_wants(&self, agent_name: String) -> HashSet<String> {
         let mut ret = HashSet::new();
         let Some(&ref variants) = self.working_agents.get(&agent_name) else {
             return ret;
         };
         for varient in variants {
-            ret.extend(varient.get_wants());
+            ret.extend(
+                varient
+                    .iter_wants()
+                    .filter(|w| {
+                        self.get_inactive_conditions(w).is_empty()
+                            && self.get_inactive_own_conditions(variants, w).is_empty()
+                    })
+                    .map(|w| w.get_name().clone()),
+            );
+        }
+        ret
+    }
+
+    /// The conditional wants of the agent that don't apply, sorted by name
+    pub fn get_skipped_wants(&self, agent_name: &str) -> Vec<SkippedWant> {
+        let Some(variants) = self.working_agents.get(agent_name) else {
+            return vec![];
+        };
+        let active = self.get_agent_wants(String::from(agent_name));
+        let mut inactive: HashMap<&String, (BTreeSet<Vec<String>>, BTreeSet<String>)> =
+            HashMap::new();
+        for w in variants.iter().flat_map(|a| a.iter_wants()) {
+            if !active.contains(w.get_name()) {
+                let (conditions, own) = inactive.entry(w.get_name()).or_default();
+                conditions.extend(self.get_inactive_conditions(w));
+                own.extend(self.get_inactive_own_conditions(variants, w));
+            }
+        }
+        let mut ret: Vec<SkippedWant> = inactive
+            .into_iter()
+            .map(|(behavior_name, (conditions, own))| SkippedWant {
+                agent_name: String::from(agent_name),
+                behavior_name: behavior_name.clone(),
+                inactive_conditions: conditions.into_iter().collect(),
+                inactive_own_conditions: own.into_iter().collect(),
+            })
+            .collect();
+        ret.sort_by(|a, b| a.behavior_name.cmp(&b.behavior_name));
+        ret
+    }
+
+    // Conditions of a want that keep it from applying: empty if any of its
+    // alternatives has every condition provided, otherwise the unprovided
+    // ones of each alternative
+    fn get_inactive_conditions(&self, want: &Behavior) -> Vec<Vec<String>> {
+        let alternatives = want.get_alternatives();
+        if alternatives
+            .iter()
+            .any(|a| a.iter().all(|c| self.is_provided(c)))
+        {
+            return vec![];
         }
+        let mut ret: Vec<Vec<String>> = alternatives
+            .into_iter()
+            .map(|a| {
+                let mut missing: Vec<String> =
+                    a.into_iter().filter(|c| !self.is_provided(c)).collect();
+                missing.sort();
+                missing.dedup();
+                missing
+            })
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    // Own conditions of a want that the agent (any of its variants) doesn't
+    // provide itself, sorted
+    fn get_inactive_own_conditions(&self, variants: &[Agent], want: &Behavior) -> Vec<String> {
+        let provided: Vec<BehaviorId> = variants
+            .iter()
+            .flat_map(|a| a.iter_provides())
+            .map(|p| BehaviorId::from(p.get_name().as_str()))
+            .collect();
+        let mut ret: Vec<String> = want
+            .get_own_conditions()
+            .iter()
+            .filter(|c| {
+                let id = BehaviorId::from(c.as_str());
+                !provided.iter().any(|p| id.matches(p))
+            })
+            .cloned()
+            .collect();
+        ret.sort();
+        ret.dedup();
         ret
     }
 
+    // Some working agent provides it, whether or not its conditions are met
+    fn is_provided(&self, behavior_name: &str) -> bool {
+        self.names
+            .get(&behavior_key(behavior_name))
+            .and_then(|b| self.providers.get(&b))
+            .is_some_and(|p| !p.is_empty())
+    }
+
+    /// The most critical level any variant of the agent wants `behavior_name`
+    /// at, if it is wanted
+    pub fn get_want_criticality(
+        &self,
+        agent_name: &str,
+        behavior_name: &str,
+    ) -> Option<Criticality> {
+        self.working_agents
+            .get(agent_name)?
+            .iter()
+            .filter_map(|a| a.get_want_criticality(behavior_name))
+            .min()
+    }
+
+    /// The version requirement the agent wants `behavior_name` at, if any
+    pub fn get_want_version(&self, agent_name: &str, behavior_name: &str) -> Option<&str> {
+        self.working_agents
+            .get(agent_name)?
+            .iter()
+            .find_map(|a| a.get_want_version(behavior_name))
+    }
+
+    /// Where the agent wants the provider of `behavior_name` to be, relative
+    /// to itself. Empty if anywhere will do.
+    pub fn get_want_placement(&self, agent_name: &str, behavior_name: &str) -> Placement {
+        self.working_agents
+            .get(agent_name)
+            .and_then(|variants| {
+                variants
+                    .iter()
+                    .find_map(|a| a.get_want_placement(behavior_name))
+            })
+            .unwrap_or_default()
+    }
+
+    /// The largest demand any variant of the agent wants `behavior_name`
+    /// with, if it is wanted
+    pub fn get_want_demand(&self, agent_name: &str, behavior_name: &str) -> Option<u64> {
+        self.working_agents
+            .get(agent_name)?
+            .iter()
+            .filter_map(|a| a.get_want_demand(behavior_name))
+            .max()
+    }
+
+    /// The largest capacity any variant of the agent provides `behavior_name`
+    /// with. None if it is not provided or is unlimited.
+    pub fn get_provide_capacity(&self, agent_name: &str, behavior_name: &str) -> Option<u64> {
+        self.working_agents
+            .get(agent_name)?
+            .iter()
+            .filter_map(|a| a.get_provide_capacity(behavior_name))
+            .max()
+    }
+
+    /// Hands every satisfied want to one of the agents that satisfy it and
+    /// adds up the demand on each provide that has a capacity. Wants are
+    /// handed out in agent and then behavior name order. A want goes to a
+    /// provider without a capacity if there is one, otherwise to the one
+    /// with the most capacity left.
+    pub fn allocate(&self) -> Allocation {
+        let mut loads: BTreeMap<(String, String), ProviderLoad> = BTreeMap::new();
+        for agent_name in self.get_working_agent_names() {
+            for p in self.working_agents[agent_name]
+                .iter()
+                .flat_map(|a| a.iter_provides())
+            {
+                let Some(capacity) = self.get_provide_capacity(agent_name, p.get_name()) else {
+                    continue;
+                };
+                loads
+                    .entry((p.get_name().clone(), agent_name.clone()))
+                    .or_insert_with(|| ProviderLoad {
+                        agent_name: agent_name.clone(),
+                        behavior_name: p.get_name().clone(),
+                        capacity,
+                        consumers: vec![],
+                    });
+            }
+        }
+        let mut lookups = self.lookups();
+        for agent_name in self.get_working_agent_names() {
+            let mut wants: Vec<String> = self
+                .get_agent_wants(agent_name.clone())
+                .into_iter()
+                .collect();
+            wants.sort();
+            for w in wants {
+                let graph = lookups.resolve_want(agent_name, &w);
+                let offers = graph.get_root_node().get_satisfying_offers();
+                if offers
+                    .iter()
+                    .any(|o| !loads.contains_key(&(w.clone(), String::from(o.get_agent_name()))))
+                {
+                    // an unlimited provider can take it, or nothing can
+                    continue;
+                }
+                let Some(provider) = offers
+                    .iter()
+                    .map(|o| &loads[&(w.clone(), String::from(o.get_agent_name()))])
+                    .max_by_key(|l| (l.get_remaining(), Reverse(&l.agent_name)))
+                    .map(|l| l.agent_name.clone())
+                else {
+                    continue;
+                };
+                loads
+                    .get_mut(&(w.clone(), provider))
+                    .unwrap()
+                    .consumers
+                    .push(Consumer {
+                        agent_name: agent_name.clone(),
+                        demand: self.get_want_demand(agent_name, &w).unwrap_or(1),
+                    });
+            }
+        }
+        Allocation {
+            loads: loads.into_values().collect(),
+        }
+    }
+
+    /// Orders the working agents into waves that can start in parallel. An
+    /// agent waits until each of its conditions is provided by one agent
+    /// that is already up, and one group of each anyOf is. Only providers at
+    /// a matching version and placement count, and only ones that are
+    /// satisfied or held up by nothing but a dependency cycle; a provider
+    /// that needs something missing never comes up, so it isn't waited for.
+    /// Conditions the agent provides itself, or that nothing can provide,
+    /// don't hold it up, and neither do wants. Agents that wait for each
+    /// other are reported as cycles and start together.
+    pub fn get_startup_order(&self) -> StartupOrder {
+        let mut needs: HashMap<String, Vec<startup::Need>> = HashMap::new();
+        for agent_name in self.get_working_agent_names() {
+            needs.insert(agent_name.clone(), vec![]);
+        }
+        let mut lookups = self.lookups();
+        for provides in self.providers.values() {
+            for p in provides {
+                let agent_name = self.names.resolve(p.agent);
+                // The agents it could wait for: none if it provides the
+                // condition itself, None if nothing can
+                let mut providers = |c: &Symbol| {
+                    let graph = lookups.resolve(self.names.resolve(*c), p.get_constraints(*c));
+                    let mut ret: Vec<String> = graph
+                        .get_live_agent_names()
+                        .into_iter()
+                        .map(String::from)
+                        .collect();
+                    if ret.is_empty() {
+                        return None;
+                    }
+                    if ret.iter().any(|a| a == agent_name) {
+                        return Some(vec![]);
+                    }
+                    ret.sort();
+                    ret.dedup();
+                    Some(ret)
+                };
+                let mut any_of = vec![];
+                for group in &p.any_of {
+                    // a group with something that can't be provided is no way
+                    // in, and one with nothing to wait for is always open
+                    let Some(group) = group.iter().map(&mut providers).collect::<Option<Vec<_>>>()
+                    else {
+                        continue;
+                    };
+                    let group: Vec<Vec<String>> =
+                        group.into_iter().filter(|ps| !ps.is_empty()).collect();
+                    if group.is_empty() {
+                        any_of.clear();
+                        break;
+                    }
+                    any_of.push(group);
+                }
+                let need = startup::Need {
+                    conditions: p
+                        .conditions
+                        .iter()
+                        .filter_map(&mut providers)
+                        .filter(|ps| !ps.is_empty())
+                        .collect(),
+                    any_of,
+                };
+                if need != startup::Need::default() {
+                    needs
+                        .entry(String::from(agent_name))
+                        .or_default()
+                        .push(need);
+                }
+            }
+        }
+        startup::startup_order(&needs)
+    }
+
     pub fn get_working_behaviors(&self) -> HashSet<String> {
         let mut ret = HashSet::new();
         for (_, variants) in &self.working_agents {
@@ -194,71 +1053,550 @@ impl Tracker {
     // As a rule of thumb:
     // - satisfied conditions will result in an Offer
     // - unsatisfied conditions will result in an Resolution
+    // - conditions that loop back onto the active path result in a cyclic Resolution
     pub fn resolve(&self, behavior_name: &str) -> Resolution {
-        let mut r = Resolution::new(behavior_name);
-        let mut agent_names: Vec<String> = vec![];
-        for (a, _) in &self.working_agents {
-            if agent_names.contains(a) {
-                continue;
-            }
-            agent_names.push(a.clone());
-        }
-        agent_names.sort();
-        for agent_name in agent_names {
-            let variants = match self.working_agents.get(&agent_name) {
-                Some(variants) => variants,
-                None => continue,
-            };
-            // for (agent_name, variants) in &self.working_agents {
-            for variant_agent in variants {
-                if let Some(behaviors) = variant_agent.get_provides(behavior_name) {
-                    for b in behaviors {
-                        // if unconditional, add this as a satisfied Offer
-                        if b.is_unconditional() {
-                            r = r.add_satisfying_offer(Offer::new(&agent_name));
-                            continue;
-                        }
-                        // resolve conditions
-                        let resolved_conditions = b
-                            .get_conditions()
-                            .iter()
-                            .map(|c| self.resolve(c))
-                            .collect::<Vec<Resolution>>();
-                        // if all conditions are satisfied, add this as a satisfied Offer
-                        if resolved_conditions.iter().all(|x| x.is_satisfied()) {
-                            r = r.add_satisfying_offer(Offer::new_conditional(
-                                &agent_name,
-                                resolved_conditions,
-                            ));
-                        // otherwise, add this as an unsatisfied Offer
-                        } else {
-                            r = r.add_unsatisfying_offer(Offer::new_conditional(
-                                &agent_name,
-                                resolved_conditions,
-                            ));
-                        }
-                    }
-                }
-            }
-        }
-        r
+        self.resolve_graph(behavior_name).to_resolution()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use components::Behavior;
+    /// Minimal sets of missing behaviors that keep `behavior_name` from being
+    /// satisfied. See `ResolutionGraph::get_root_causes`.
+    pub fn get_root_causes(&self, behavior_name: &str) -> Vec<Vec<String>> {
+        self.resolve_graph(behavior_name).get_root_causes()
+    }
 
-    #[test]
-    fn simple_adds() {
-        let mut t = Tracker {
-            available_agents: vec![],
-            available_superagents: vec![],
-            working_agents: HashMap::new(),
-        };
-        let mut a = Agent::new(String::from("abcd"));
-        a.add_provide(Behavior::new_with_conditions(String::from("ba"), vec![]));
+    /// Provided behavior names that `behavior_name` was probably meant to be,
+    /// best first. Used to explain wants that nothing provides.
+    pub fn get_suggestions(&self, behavior_name: &str) -> Vec<String> {
+        similarity::suggestions(
+            behavior_name,
+            self.providers
+                .iter()
+                .filter(|(_, provides)| !provides.is_empty())
+                .map(|(b, _)| self.names.resolve(*b))
+                .filter(|b| !BehaviorId::from(*b).is_any_instance()),
+        )
+    }
+
+    /// The provided behaviors that are instances of `behavior_name` (i.e.
+    /// it with one or more tags), sorted
+    pub fn get_behavior_instances(&self, behavior_name: &str) -> Vec<String> {
+        let any_instance = BehaviorId::from(behavior_name).any_instance();
+        let mut ret: Vec<String> = self
+            .providers
+            .iter()
+            .filter(|(_, provides)| !provides.is_empty())
+            .map(|(b, _)| BehaviorId::from(self.names.resolve(*b)))
+            .filter(|b| !b.is_any_instance() && any_instance.matches(b))
+            .filter(|b| b.get_tags().len() > any_instance.get_tags().len() - 1)
+            .map(|b| b.to_string())
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    /// Resolves every want of every working agent. Agents are in name order
+    /// and each agent's wants are sorted.
+    pub fn resolve_all(&self) -> SatisfactionReport {
+        self.resolve_selected(&Selector::default())
+    }
+
+    /// Same as `resolve_all`, but only for the agents whose labels match
+    /// `selector`
+    pub fn resolve_selected(&self, selector: &Selector) -> SatisfactionReport {
+        let mut report = SatisfactionReport::new();
+        let mut lookups = self.lookups();
+        for agent_name in self.select_agent_names(selector) {
+            report.add_agent(self.report_agent(agent_name, &mut lookups));
+        }
+        report
+    }
+
+    // Same as `resolve_all`, but only for one working agent
+    pub(crate) fn resolve_agent(&self, agent_name: &str) -> AgentReport {
+        self.report_agent(agent_name, &mut self.lookups())
+    }
+
+    fn report_agent(&self, agent_name: &str, lookups: &mut Lookups) -> AgentReport {
+        let mut wants: Vec<String> = self
+            .get_agent_wants(String::from(agent_name))
+            .into_iter()
+            .collect();
+        wants.sort();
+        AgentReport {
+            skipped: self.get_skipped_wants(agent_name),
+            agent_name: String::from(agent_name),
+            wants: wants
+                .iter()
+                .map(|w| {
+                    WantReport::new(lookups.resolve_want(agent_name, w)).with_criticality(
+                        self.get_want_criticality(agent_name, w).unwrap_or_default(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Same as `resolve`, but shared sub-resolutions are stored once. Results
+    /// are cached until the next `rebuild`.
+    pub fn resolve_graph(&self, behavior_name: &str) -> ResolutionGraph {
+        self.resolve_graph_with_requirement(behavior_name, None)
+    }
+
+    /// Same as `resolve_graph`, but only providers whose version meets
+    /// `requirement` (e.g. `^2.1`) are used. The others are recorded as
+    /// mismatches. An invalid requirement is ignored.
+    pub fn resolve_graph_with_requirement(
+        &self,
+        behavior_name: &str,
+        requirement: Option<&str>,
+    ) -> ResolutionGraph {
+        self.resolve_constrained(
+            behavior_name,
+            Constraints {
+                version: requirement.and_then(|r| version::parse_requirement(r).ok()),
+                ..Constraints::default()
+            },
+        )
+    }
+
+    /// Resolves a want of the agent the way the agent wants it: only
+    /// providers at the version it wants and in the place it wants them
+    /// (e.g. `sameAs: [zone]`) are used. The others are recorded as
+    /// mismatches and rejections.
+    pub fn resolve_want(&self, agent_name: &str, behavior_name: &str) -> Resolution {
+        self.resolve_want_graph(agent_name, behavior_name)
+            .to_resolution()
+    }
+
+    /// Same as `resolve_want`, but shared sub-resolutions are stored once
+    pub fn resolve_want_graph(&self, agent_name: &str, behavior_name: &str) -> ResolutionGraph {
+        self.lookups().resolve_want(agent_name, behavior_name)
+    }
+
+    fn get_want_constraints(&self, agent_name: &str, behavior_name: &str) -> Constraints {
+        let labels = self
+            .get_agent_labels(agent_name)
+            .cloned()
+            .unwrap_or_default();
+        Constraints {
+            version: self
+                .get_want_version(agent_name, behavior_name)
+                .and_then(|r| version::parse_requirement(r).ok()),
+            placement: self
+                .get_want_placement(agent_name, behavior_name)
+                .to_filter(&labels),
+        }
+    }
+
+    fn resolve_constrained(
+        &self,
+        behavior_name: &str,
+        constraints: Constraints,
+    ) -> ResolutionGraph {
+        self.lookups().resolve(behavior_name, constraints)
+    }
+
+    // Holds the cache until the lookups are done
+    fn lookups(&self) -> Lookups<'_> {
+        Lookups {
+            tracker: self,
+            arena: self.resolutions.lock(),
+            looked_up: HashMap::new(),
+        }
+    }
+
+    // Results without constraints are kept in the arena until the next
+    // `rebuild`. Results with constraints, and anything that depends on one,
+    // are temporary: they are kept in `looked_up` until the end of the lookup.
+    fn get_looked_up(
+        &self,
+        lookup: &Lookup,
+        looked_up: &HashMap<Lookup, ResolutionId>,
+        arena: &ResolutionArena,
+    ) -> Option<ResolutionId> {
+        if lookup.1.is_empty() {
+            if let Some(id) = arena.get_resolved(lookup.0) {
+                return Some(id);
+            }
+        }
+        looked_up.get(lookup).copied()
+    }
+
+    fn resolve_lookup(
+        &self,
+        lookup: &Lookup,
+        looked_up: &mut HashMap<Lookup, ResolutionId>,
+        arena: &mut ResolutionArena,
+    ) -> ResolutionId {
+        if let Some(id) = self.get_looked_up(lookup, looked_up, arena) {
+            return id;
+        }
+        if self.behavior_cycles.contains_key(&lookup.0) {
+            return self.resolve_cycle(lookup, looked_up, arena);
+        }
+        let mut ids: HashMap<Lookup, ResolutionId> = HashMap::new();
+        for c in self.get_condition_lookups(lookup) {
+            let id = self.resolve_lookup(&c, looked_up, arena);
+            ids.insert(c, id);
+        }
+        let temporary = !lookup.1.is_empty() || ids.values().any(|id| arena.is_temporary(*id));
+        let node = self.to_lookup_node(lookup, |c| ids[c], arena);
+        if temporary {
+            let id = arena.push_temporary(node);
+            looked_up.insert(lookup.clone(), id);
+            id
+        } else {
+            let id = arena.push(node);
+            arena.set_resolved(lookup.0, id);
+            id
+        }
+    }
+
+    // Resolves a lookup of a behavior in a dependency cycle together with
+    // every lookup of the cycle it reaches. Each of them gets a single node,
+    // and conditions between them point at those nodes instead of being
+    // resolved again for each path.
+    //
+    // Lookups that reach each other are entered through one of them (the
+    // given lookup, in its own group), which is treated like a behavior that
+    // is already being resolved: conditions on it are recorded as loops. The
+    // others are added in the order they can be satisfied in without it, then
+    // the rest depth-first, and the entry last. A condition on a lookup that
+    // comes later is recorded as a loop too, so the nodes never loop.
+    //
+    // The result depends on the lookup it was entered through, so only that
+    // one is kept for reuse.
+    fn resolve_cycle(
+        &self,
+        lookup: &Lookup,
+        looked_up: &mut HashMap<Lookup, ResolutionId>,
+        arena: &mut ResolutionArena,
+    ) -> ResolutionId {
+        let cycle_id = self.behavior_cycles.get(&lookup.0);
+        // the lookups of the cycle and the conditions of each one between
+        // them, in the order they were reached in
+        let mut lookups: Vec<Lookup> = vec![lookup.clone()];
+        let mut edges: Vec<Vec<usize>> = vec![];
+        // what every lookup outside of them, and each one added so far, is
+        // resolved to
+        let mut ids: HashMap<Lookup, ResolutionId> = HashMap::new();
+        while edges.len() < lookups.len() {
+            let mut to = vec![];
+            for c in self.get_condition_lookups(&lookups[edges.len()]) {
+                if let Some(i) = lookups.iter().position(|l| *l == c) {
+                    to.push(i);
+                } else if self.behavior_cycles.get(&c.0) == cycle_id {
+                    to.push(lookups.len());
+                    lookups.push(c);
+                } else if let Entry::Vacant(e) = ids.entry(c) {
+                    let id = self.resolve_lookup(e.key(), looked_up, arena);
+                    e.insert(id);
+                }
+            }
+            edges.push(to);
+        }
+        let temporary = lookups.iter().any(|l| !l.1.is_empty())
+            || ids.values().any(|id| arena.is_temporary(*id));
+        let graph: HashMap<String, Vec<String>> = edges
+            .iter()
+            .enumerate()
+            .map(|(i, to)| (i.to_string(), to.iter().map(usize::to_string).collect()))
+            .collect();
+        // dependencies first
+        for component in scc::strongly_connected_components(&graph) {
+            let mut component: Vec<usize> = component.iter().map(|i| i.parse().unwrap()).collect();
+            component.sort();
+            let entry = component[0];
+            let mut order: Vec<usize> = vec![];
+            loop {
+                let before = order.len();
+                for i in &component {
+                    if *i == entry || order.contains(i) {
+                        continue;
+                    }
+                    let is_satisfied = |c: &Lookup| match ids.get(c) {
+                        Some(id) => arena.is_satisfied(*id),
+                        None => order.iter().any(|j| lookups[*j] == *c),
+                    };
+                    if self.can_satisfy(&lookups[*i], is_satisfied) {
+                        order.push(*i);
+                    }
+                }
+                if order.len() == before {
+                    break;
+                }
+            }
+            for i in depth_first_order(entry, &component, &edges) {
+                if !order.contains(&i) {
+                    order.push(i);
+                }
+            }
+            for i in order {
+                let mut loops: HashMap<&Lookup, ResolutionId> = HashMap::new();
+                for j in &edges[i] {
+                    if ids.contains_key(&lookups[*j]) || loops.contains_key(&lookups[*j]) {
+                        continue;
+                    }
+                    let node = ResolutionNode::new_cyclic(
+                        self.names.resolve(lookups[*j].0),
+                        self.get_loop(*j, i, &edges, &lookups),
+                    );
+                    let id = if temporary {
+                        arena.push_temporary(node)
+                    } else {
+                        arena.push(node)
+                    };
+                    loops.insert(&lookups[*j], id);
+                }
+                let node = self.to_lookup_node(
+                    &lookups[i],
+                    |c| loops.get(c).or_else(|| ids.get(c)).copied().unwrap(),
+                    arena,
+                );
+                let id = if temporary {
+                    arena.push_temporary(node)
+                } else {
+                    arena.push(node)
+                };
+                ids.insert(lookups[i].clone(), id);
+            }
+        }
+        let id = ids[lookup];
+        if temporary {
+            looked_up.insert(lookup.clone(), id);
+        } else {
+            arena.set_resolved(lookup.0, id);
+        }
+        id
+    }
+
+    // The behavior names along the shortest way from lookup `from` back to
+    // itself through lookup `to`, e.g. `a -> b -> a`
+    fn get_loop(
+        &self,
+        from: usize,
+        to: usize,
+        edges: &[Vec<usize>],
+        lookups: &[Lookup],
+    ) -> Vec<String> {
+        let mut previous: HashMap<usize, usize> = HashMap::new();
+        let mut todo = VecDeque::from([from]);
+        while let Some(i) = todo.pop_front() {
+            if i == to {
+                break;
+            }
+            for j in &edges[i] {
+                if *j != from && !previous.contains_key(j) {
+                    previous.insert(*j, i);
+                    todo.push_back(*j);
+                }
+            }
+        }
+        let mut ret = vec![from];
+        let mut i = to;
+        while i != from {
+            ret.insert(1, i);
+            i = previous[&i];
+        }
+        ret.push(from);
+        ret.iter()
+            .map(|i| String::from(self.names.resolve(lookups[*i].0)))
+            .collect()
+    }
+
+    // The providers of the behavior that meet the constraints, and the
+    // mismatches and rejections of the ones that don't
+    fn get_lookup_providers(
+        &self,
+        lookup: &Lookup,
+    ) -> (Vec<&IndexedProvide>, Vec<VersionMismatch>, Vec<Rejection>) {
+        let (behavior, constraints) = lookup;
+        let mut providers = vec![];
+        let mut mismatches: Vec<VersionMismatch> = vec![];
+        let mut rejections: Vec<Rejection> = vec![];
+        for p in self.providers.get(behavior).into_iter().flatten() {
+            let agent_name = self.names.resolve(p.agent);
+            if let Some(mismatch) = constraints
+                .version
+                .as_ref()
+                .and_then(|req| VersionMismatch::check(agent_name, p.version.as_ref(), req))
+            {
+                if !mismatches.contains(&mismatch) {
+                    mismatches.push(mismatch);
+                }
+                continue;
+            }
+            if let Some(rejection) = constraints.placement.check(
+                agent_name,
+                self.get_agent_labels(agent_name).unwrap_or(&Labels::new()),
+            ) {
+                if !rejections.contains(&rejection) {
+                    rejections.push(rejection);
+                }
+                continue;
+            }
+            providers.push(p);
+        }
+        (providers, mismatches, rejections)
+    }
+
+    // What each condition of the providers used for the lookup is looked up
+    // as, once each
+    fn get_condition_lookups(&self, lookup: &Lookup) -> Vec<Lookup> {
+        let mut ret: Vec<Lookup> = vec![];
+        for p in self.get_lookup_providers(lookup).0 {
+            for c in p.conditions.iter().chain(p.any_of.iter().flatten()) {
+                let c = (*c, p.get_constraints(*c));
+                if !ret.contains(&c) {
+                    ret.push(c);
+                }
+            }
+        }
+        ret
+    }
+
+    // Whether one of the providers used for the lookup has every condition
+    // (and one anyOf group) satisfied
+    fn can_satisfy(&self, lookup: &Lookup, is_satisfied: impl Fn(&Lookup) -> bool) -> bool {
+        self.get_lookup_providers(lookup).0.iter().any(|p| {
+            let met = |c: &Symbol| is_satisfied(&(*c, p.get_constraints(*c)));
+            p.conditions.iter().all(met)
+                && (p.any_of.is_empty() || p.any_of.iter().any(|g| g.iter().all(met)))
+        })
+    }
+
+    // The node for the lookup, given the ids its conditions resolve to
+    fn to_lookup_node(
+        &self,
+        lookup: &Lookup,
+        id: impl Fn(&Lookup) -> ResolutionId,
+        arena: &ResolutionArena,
+    ) -> ResolutionNode {
+        let (behavior, constraints) = lookup;
+        let behavior_name = self.names.resolve(*behavior);
+        let mut r = constraints.to_node(behavior_name);
+        if self.providers.get(behavior).is_none_or(Vec::is_empty) {
+            r = r.with_suggestions(self.get_suggestions(behavior_name));
+        }
+        let (providers, mismatches, rejections) = self.get_lookup_providers(lookup);
+        for p in providers {
+            let agent_name = self.names.resolve(p.agent);
+            // if unconditional, add this as a satisfied Offer
+            if p.conditions.is_empty() && p.any_of.is_empty() {
+                r.add_satisfying_offer(
+                    OfferNode::new(agent_name, vec![]).with_location(p.location.clone()),
+                );
+                continue;
+            }
+            // resolved conditions
+            let resolved = |c: &Symbol| id(&(*c, p.get_constraints(*c)));
+            let offer = OfferNode::new(agent_name, p.conditions.iter().map(resolved).collect())
+                .with_any_of(
+                    p.any_of
+                        .iter()
+                        .map(|g| g.iter().map(resolved).collect())
+                        .collect(),
+                )
+                .with_location(p.location.clone());
+            // if all conditions (and one anyOf group) are satisfied, add this as a satisfied Offer
+            if offer.is_met(|x| arena.is_satisfied(x)) {
+                r.add_satisfying_offer(offer);
+            // otherwise, add this as an unsatisfied Offer
+            } else {
+                r.add_unsatisfying_offer(offer);
+            }
+        }
+        for m in mismatches {
+            r.add_mismatch(m);
+        }
+        for rejection in rejections {
+            r.add_rejection(rejection);
+        }
+        r
+    }
+}
+
+// Lookups made together, e.g. one for every want in `resolve_all`. Results
+// with constraints are shared between them, and dropped along with them.
+struct Lookups<'a> {
+    tracker: &'a Tracker,
+    arena: MutexGuard<'a, ResolutionArena>,
+    looked_up: HashMap<Lookup, ResolutionId>,
+}
+
+impl Lookups<'_> {
+    fn resolve(&mut self, behavior_name: &str, constraints: Constraints) -> ResolutionGraph {
+        let Some(behavior) = self.tracker.names.get(&behavior_key(behavior_name)) else {
+            // nothing provides or depends on it, so there is nothing to look up
+            let mut arena = ResolutionArena::default();
+            let root = arena.push(
+                constraints
+                    .to_node(behavior_name)
+                    .with_suggestions(self.tracker.get_suggestions(behavior_name)),
+            );
+            return arena.extract(root);
+        };
+        let root = self.tracker.resolve_lookup(
+            &(behavior, constraints),
+            &mut self.looked_up,
+            &mut self.arena,
+        );
+        self.arena.extract(root)
+    }
+
+    fn resolve_want(&mut self, agent_name: &str, behavior_name: &str) -> ResolutionGraph {
+        let constraints = self.tracker.get_want_constraints(agent_name, behavior_name);
+        self.resolve(behavior_name, constraints)
+    }
+}
+
+impl Drop for Lookups<'_> {
+    fn drop(&mut self) {
+        self.arena.drop_temporary();
+    }
+}
+
+// The lookups of a group that reach each other, depth-first from `entry`,
+// each one after the ones it leads to
+fn depth_first_order(entry: usize, component: &[usize], edges: &[Vec<usize>]) -> Vec<usize> {
+    let mut ret = vec![];
+    let mut seen: HashSet<usize> = HashSet::from([entry]);
+    // (lookup, position of the next edge to look at)
+    let mut work = vec![(entry, 0)];
+    while let Some((i, next)) = work.pop() {
+        match edges[i].get(next) {
+            Some(j) => {
+                work.push((i, next + 1));
+                if component.contains(j) && seen.insert(*j) {
+                    work.push((*j, 0));
+                }
+            }
+            None => ret.push(i),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::{Behavior, Provenance};
+    use report::WantStatus;
+    use resolve::Offer;
+
+    #[test]
+    fn simple_adds() {
+        let mut t = Tracker {
+            available_agents: vec![],
+            available_superagents: vec![],
+            working_agents: HashMap::new(),
+            names: Interner::default(),
+            providers: HashMap::new(),
+            behavior_cycles: HashMap::new(),
+            resolutions: ResolutionCache::default(),
+        };
+        let mut a = Agent::new(String::from("abcd"));
+        a.add_provide(Behavior::new_with_conditions(String::from("ba"), vec![]));
         t.add_agent(a);
         t.add_agent(Agent::new(String::from("ijkl")));
         let mut b = Agent::new(String::from("efgh"));
@@ -302,163 +1640,1131 @@ mod tests {
     }
 
     #[test]
-    fn agent_bools() {
+    fn agent_bools() {
+        let mut t = Tracker::new();
+
+        assert!(t.is_empty());
+
+        t.add_agent(Agent::new(String::from("abcd")));
+        assert!(t.has_agent(String::from("abcd")));
+        assert!(!t.has_agent(String::from("efgh")));
+        assert!(t.is_agent_wants_empty(String::from("abcd")));
+
+        t.add_agent(Agent::new(String::from("efgh")));
+        assert!(t.has_agent(String::from("efgh")));
+        let mut efgh = Agent::new(String::from("efgh"));
+        efgh.add_want(Behavior::new(String::from("efgh_want1")));
+        t.add_agent(efgh);
+        assert!(!t.is_agent_wants_empty(String::from("efgh")));
+
+        assert!(t.has_behavior(String::from("efgh_want1")));
+        assert!(!t.has_behavior(String::from("missing_want")));
+    }
+
+    #[test]
+    fn nested_gets() {
+        let mut t = Tracker::new();
+
+        let mut abcd = Agent::new(String::from("abcd"));
+        abcd.add_want(Behavior::new(String::from("abcd_w1")));
+        t.add_agent(abcd);
+        let mut abcd = Agent::new(String::from("abcd"));
+        abcd.add_want(Behavior::new(String::from("abcd_w2")));
+        t.add_agent(abcd);
+
+        let mut efgh = Agent::new(String::from("efgh"));
+        efgh.add_want(Behavior::new(String::from("efgh_w3")));
+        t.add_agent(efgh);
+
+        assert_eq!(
+            t.get_agent_wants(String::from("abcd")),
+            HashSet::from([String::from("abcd_w1"), String::from("abcd_w2"),])
+        );
+        assert_eq!(
+            t.get_agent_wants(String::from("efgh")),
+            HashSet::from([String::from("efgh_w3"),])
+        );
+    }
+
+    #[test]
+    fn test_simple_resolve() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        assert_eq!(
+            t.resolve("b1"),
+            Resolution::new("b1").add_satisfying_offer(Offer::new("a1"))
+        );
+
+        t.add_agent(Agent::build("a2").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec![String::from("b2")]),
+        ]));
+        assert_eq!(
+            t.resolve("b1"),
+            Resolution::new("b1")
+                .add_satisfying_offer(Offer::new("a1"))
+                .add_unsatisfying_offer(Offer::new_conditional("a2", vec!(Resolution::new("b2"))))
+        );
+
+        t.add_agent(Agent::build("a3").with_provides(vec![Behavior::build("b2")]));
+        assert_eq!(
+            t.resolve("b1"),
+            Resolution::new("b1")
+                .add_satisfying_offer(Offer::new("a1"))
+                .add_satisfying_offer(Offer::new_conditional(
+                    "a2",
+                    vec!(Resolution::new("b2").add_satisfying_offer(Offer::new("a3")))
+                ))
+        );
+    }
+
+    #[test]
+    fn test_resolve_multiple_satisfying() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("a3").with_provides(vec![Behavior::build("b1")]));
+        let resolve_b1 = Resolution::new("b1")
+            .add_satisfying_offer(Offer::new("a1"))
+            .add_satisfying_offer(Offer::new("a2"))
+            .add_satisfying_offer(Offer::new("a3"));
+        assert_eq!(t.resolve("b1"), resolve_b1);
+    }
+
+    #[test]
+    fn test_resolve_unsatisfied() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec![String::from("b2a"), String::from("b2b")]),
+        ]));
+        t.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b2a")]));
+
+        assert_eq!(
+            t.resolve("b1"),
+            Resolution::new("b1").add_unsatisfying_offer(Offer::new_conditional(
+                "a1",
+                vec!(
+                    Resolution::new("b2a").add_satisfying_offer(Offer::new("a2")),
+                    Resolution::new("b2b"),
+                )
+            ))
+        )
+    }
+
+    #[test]
+    fn test_resolve_deep() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec![String::from("b2")]),
+        ]));
+        t.add_agent(Agent::build("a2").with_provides(vec![
+            Behavior::build("b2").with_conditions(vec![String::from("b3")]),
+        ]));
+        t.add_agent(Agent::build("a3").with_provides(vec![
+            Behavior::build("b3").with_conditions(vec![String::from("b4")]),
+        ]));
+        t.add_agent(Agent::build("a4").with_provides(vec![Behavior::build("b4")]));
+        let satisfied_part = Resolution::new("b1").add_satisfying_offer(Offer::new_conditional(
+            "a1",
+            vec![
+                Resolution::new("b2").add_satisfying_offer(Offer::new_conditional(
+                    "a2",
+                    vec![
+                        Resolution::new("b3").add_satisfying_offer(Offer::new_conditional(
+                            "a3",
+                            vec![Resolution::new("b4").add_satisfying_offer(Offer::new("a4"))],
+                        )),
+                    ],
+                )),
+            ],
+        ));
+        assert_eq!(t.resolve("b1"), satisfied_part);
+        t.add_agent(Agent::build("a0").with_provides(vec![
+            Behavior::build("b0").with_conditions(vec![String::from("b1"), String::from("b1b")]),
+        ]));
+        t.add_agent(Agent::build("a1b").with_provides(vec![
+            Behavior::build("b1b").with_conditions(vec![String::from("b2b")]),
+        ]));
+        assert_eq!(
+            t.resolve("b0"),
+            Resolution::new("b0").add_unsatisfying_offer(Offer::new_conditional(
+                "a0",
+                vec!(
+                    satisfied_part,
+                    Resolution::new("b1b").add_unsatisfying_offer(Offer::new_conditional(
+                        "a1b",
+                        vec!(Resolution::new("b2b"))
+                    ))
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_resolve_direct_cycle() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a").with_provides(vec![
+            Behavior::build("x").with_conditions(vec![String::from("y")]),
+        ]));
+        t.add_agent(Agent::build("b").with_provides(vec![
+            Behavior::build("y").with_conditions(vec![String::from("x")]),
+        ]));
+        let cycle = vec![String::from("x"), String::from("y"), String::from("x")];
+        assert_eq!(
+            t.resolve("x"),
+            Resolution::new("x").add_unsatisfying_offer(Offer::new_conditional(
+                "a",
+                vec![
+                    Resolution::new("y").add_unsatisfying_offer(Offer::new_conditional(
+                        "b",
+                        vec![Resolution::new_cyclic("x", cycle)],
+                    ))
+                ],
+            ))
+        );
+
+        // an alternative provider breaks the loop
+        t.add_agent(Agent::build("c").with_provides(vec![Behavior::build("y")]));
+        assert!(t.resolve("x").is_satisfied());
+        assert!(t.resolve("y").is_satisfied());
+    }
+
+    #[test]
+    fn test_resolve_indirect_cycle() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a").with_provides(vec![
+            Behavior::build("x").with_conditions(vec![String::from("y")]),
+        ]));
+        t.add_agent(Agent::build("b").with_provides(vec![
+            Behavior::build("y").with_conditions(vec![String::from("z")]),
+        ]));
+        t.add_agent(Agent::build("c").with_provides(vec![
+            Behavior::build("z").with_conditions(vec![String::from("w"), String::from("y")]),
+        ]));
+        t.add_agent(Agent::build("d").with_provides(vec![Behavior::build("w")]));
+        let r = t.resolve("x");
+        assert!(!r.is_satisfied());
+        assert_eq!(
+            r.to_strings_compressed(false),
+            vec![
+                "x |-> a &-> y |-> b &-> z |-> c &-> w |-> d".to_string(),
+                "                                &-> y |-> cycle: y -> z -> y".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_self_cycle() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a").with_provides(vec![
+            Behavior::build("x").with_conditions(vec![String::from("x")]),
+        ]));
+        assert_eq!(
+            t.resolve("x"),
+            Resolution::new("x").add_unsatisfying_offer(Offer::new_conditional(
+                "a",
+                vec![Resolution::new_cyclic(
+                    "x",
+                    vec![String::from("x"), String::from("x")]
+                )],
+            ))
+        );
+    }
+
+    #[test]
+    fn test_resolve_dense_cycle() {
+        // every behavior needs every other one, so there are 7! paths around
+        // the cycle from each of them
+        let mut t = Tracker::new();
+        let n = 8;
+        let names: Vec<String> = (0..n).map(|i| format!("b{}", i)).collect();
+        for name in &names {
+            t.add_agent(
+                Agent::build(&format!("a-{}", name)).with_provides(vec![Behavior::build(name)
+                    .with_conditions(names.iter().filter(|c| *c != name).cloned().collect())]),
+            );
+        }
+        let g = t.resolve_graph("b0");
+        assert!(!g.is_satisfied());
+        // a node per behavior, plus a loop per condition at most
+        assert!(g.get_nodes().len() <= n * n);
+        assert!(t.resolutions.lock().get_node_count() <= n * n);
+
+        t.add_agent(Agent::build("ext").with_provides(vec![Behavior::build("b7")]));
+        let g = t.resolve_graph("b0");
+        assert!(g.get_nodes().len() <= n * n);
+        assert!(!g.is_satisfied());
+        for i in 0..n {
+            t.add_agent(
+                Agent::build(&format!("ext{}", i))
+                    .with_provides(vec![Behavior::build(&format!("b{}", i))]),
+            );
+        }
+        for name in &names {
+            let g = t.resolve_graph(name);
+            assert!(g.is_satisfied());
+            assert!(g.get_nodes().len() <= n * n);
+        }
+        assert!(t.resolutions.lock().get_node_count() <= n * n * n);
+    }
+
+    #[test]
+    fn test_resolve_graph_shares_conditions() {
+        // every level has two providers that both depend on the next level, so
+        // the tree doubles in size per level while the graph stays linear
+        let mut t = Tracker::new();
+        let depth = 24;
+        for i in 0..depth {
+            t.add_agent(Agent::build(&format!("a{}", i)).with_provides(vec![
+                Behavior::build(&format!("b{}", i)).with_conditions(vec![format!("b{}", i + 1)]),
+            ]));
+            t.add_agent(Agent::build(&format!("c{}", i)).with_provides(vec![
+                Behavior::build(&format!("b{}", i)).with_conditions(vec![format!("b{}", i + 1)]),
+            ]));
+        }
+        let g = t.resolve_graph("b0");
+        assert_eq!(g.get_nodes().len(), depth + 1);
+        assert!(!g.is_satisfied());
+        assert!(g.to_colorized_strings().len() < 10 * depth);
+        assert!(g.to_colorized_compressed_strings().len() < 10 * depth);
+
+        t.add_agent(
+            Agent::build("end").with_provides(vec![Behavior::build(&format!("b{}", depth))]),
+        );
+        assert!(t.resolve_graph("b0").is_satisfied());
+    }
+
+    #[test]
+    fn test_resolve_cache_cleared_on_rebuild() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec![String::from("b2")]),
+        ]));
+        assert!(!t.resolve("b1").is_satisfied());
+        let b2 = t.names.get("b2").unwrap();
+        assert!(t.resolutions.lock().get_resolved(b2).is_some());
+        t.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b2")]));
+        let b2 = t.names.get("b2").unwrap();
+        assert!(t.resolutions.lock().get_resolved(b2).is_none());
+        assert!(t.resolve("b1").is_satisfied());
+    }
+
+    #[test]
+    fn test_resolve_cache_order_independent() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a").with_provides(vec![
+            Behavior::build("x").with_conditions(vec![String::from("y")]),
+        ]));
+        t.add_agent(Agent::build("b").with_provides(vec![
+            Behavior::build("y").with_conditions(vec![String::from("z")]),
+        ]));
+        t.add_agent(Agent::build("c").with_provides(vec![
+            Behavior::build("z").with_conditions(vec![String::from("x")]),
+        ]));
+        t.add_agent(Agent::build("d").with_provides(vec![Behavior::build("x")]));
+        let fresh = t.clone();
+        let expected_x = fresh.resolve("x");
+
+        // resolving members of the cycle first must not change later answers
+        let _ = t.resolve("y");
+        let _ = t.resolve("z");
+        assert_eq!(t.resolve("x"), expected_x);
+        assert_eq!(t.resolve("y"), fresh.clone().resolve("y"));
+        assert_eq!(
+            t.resolve("x").to_strings_compressed(false),
+            vec![
+                "x |-> d".to_string(),
+                "  |-> a &-> y |-> b &-> z |-> c &-> x |-> cycle: x -> y -> z -> x".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_cache_keeps_no_constrained_results() {
+        let mut t = Tracker::new();
+        t.add_agent(
+            Agent::build("pg")
+                .with_label("zone", "eu")
+                .with_provides(vec![Behavior::build("db").with_version("2.1.0")]),
+        );
+        t.add_agent(
+            Agent::build("api").with_provides(vec![Behavior::build("orders")
+                .with_conditions(vec![String::from("db")])
+                .with_condition_version("db", "^2")]),
+        );
+        t.add_agent(
+            Agent::build("web")
+                .with_label("zone", "eu")
+                .with_wants(vec![
+                    Behavior::build("db").with_same_as("zone"),
+                    Behavior::build("orders"),
+                ]),
+        );
+        let report = t.resolve_all();
+        let cached = t.resolutions.lock().get_node_count();
+        // results under constraints are dropped after each lookup, so
+        // resolving again doesn't grow the cache
+        for _ in 0..3 {
+            assert_eq!(t.resolve_all(), report);
+            assert_eq!(t.resolve_want("web", "db").get_satisfying_offers().len(), 1);
+        }
+        assert_eq!(t.resolutions.lock().get_node_count(), cached);
+    }
+
+    #[test]
+    fn test_resolve_constrained_results_shared_between_wants() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("disk").with_provides(vec![Behavior::build("disk")]));
+        t.add_agent(
+            Agent::build("pg")
+                .with_label("zone", "eu")
+                .with_provides(vec![
+                    Behavior::build("db").with_conditions(vec![String::from("disk")])
+                ]),
+        );
+        for i in 0..10 {
+            t.add_agent(
+                Agent::build(&format!("web{}", i))
+                    .with_label("zone", "eu")
+                    .with_wants(vec![Behavior::build("db").with_same_as("zone")]),
+            );
+        }
+        let mut lookups = t.lookups();
+        assert!(lookups.resolve_want("web0", "db").is_satisfied());
+        let resolved = lookups.arena.get_node_count();
+        // every web wants db in the same zone, so it is only looked up once
+        for i in 1..10 {
+            assert!(lookups
+                .resolve_want(&format!("web{}", i), "db")
+                .is_satisfied());
+        }
+        assert_eq!(lookups.arena.get_node_count(), resolved);
+        drop(lookups);
+        assert!(t.resolutions.lock().get_node_count() < resolved);
+    }
+
+    #[test]
+    fn test_get_root_causes() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("web").with_provides(vec![
+            Behavior::build("site").with_conditions(vec![String::from("db"), String::from("cdn")]),
+        ]));
+        t.add_agent(Agent::build("pg").with_provides(vec![
+            Behavior::build("db").with_conditions(vec![String::from("disk")]),
+        ]));
+        t.add_agent(Agent::build("mysql").with_provides(vec![
+            Behavior::build("db").with_conditions(vec![String::from("disk"), String::from("net")]),
+        ]));
+        t.add_agent(Agent::build("sqlite").with_provides(vec![
+            Behavior::build("db").with_conditions(vec![String::from("file")]),
+        ]));
+        assert_eq!(
+            t.get_root_causes("site"),
+            vec![vec!["cdn", "disk"], vec!["cdn", "file"]]
+        );
+        assert_eq!(
+            t.get_root_causes("site"),
+            t.resolve("site").get_root_causes()
+        );
+
+        t.add_agent(Agent::build("s3").with_provides(vec![Behavior::build("cdn")]));
+        t.add_agent(Agent::build("ebs").with_provides(vec![Behavior::build("disk")]));
+        assert!(t.get_root_causes("site").is_empty());
+        assert!(t.resolve("site").is_satisfied());
+    }
+
+    #[test]
+    fn test_provider_index() {
+        let mut t = Tracker::new();
+        t.add_items(vec![
+            Item::Agent(Agent::build("zeta").with_provides(vec![Behavior::build("b1")])),
+            Item::Agent(Agent::build("alpha").with_provides(vec![
+                Behavior::build("b1").with_conditions(vec![String::from("c1")]),
+                Behavior::build("b1").with_conditions(vec![String::from("c1")]),
+                Behavior::build("b1"),
+            ])),
+            Item::Agent(Agent::build("alpha").with_provides(vec![Behavior::build("b1")])),
+        ]);
+        assert_eq!(t.get_agent_names(), vec!["alpha", "alpha", "zeta"]);
+
+        let b1 = t.names.get("b1").unwrap();
+        let c1 = t.names.get("c1").unwrap();
+        let alpha = t.names.get("alpha").unwrap();
+        let zeta = t.names.get("zeta").unwrap();
+        assert_eq!(
+            t.providers[&b1],
+            vec![
+                IndexedProvide {
+                    agent: alpha,
+                    conditions: vec![c1],
+                    any_of: vec![],
+                    version: None,
+                    requirements: vec![],
+                    placements: vec![],
+                    location: None,
+                },
+                IndexedProvide {
+                    agent: alpha,
+                    conditions: vec![],
+                    any_of: vec![],
+                    version: None,
+                    requirements: vec![],
+                    placements: vec![],
+                    location: None,
+                },
+                IndexedProvide {
+                    agent: zeta,
+                    conditions: vec![],
+                    any_of: vec![],
+                    version: None,
+                    requirements: vec![],
+                    placements: vec![],
+                    location: None,
+                },
+            ]
+        );
+        assert!(!t.providers.contains_key(&c1));
+        assert_eq!(
+            t.resolve("b1").to_strings_compressed(false),
+            vec![
+                "b1 |-> alpha".to_string(),
+                "   |-> zeta".to_string(),
+                "   |-> alpha &-> c1 |-> ?".to_string(),
+            ]
+        );
+        assert_eq!(t.resolve("unknown"), Resolution::new("unknown"));
+    }
+
+    #[test]
+    fn test_resolve_large_network() {
+        // a0 provides b0; every other ai provides bi as long as two lower
+        // numbered behaviors are there, and some of them also need something
+        // nobody provides
+        let count = 10_000;
+        let mut items = vec![];
+        let mut expected = vec![true; count];
+        for i in 0..count {
+            let mut conditions = vec![];
+            if i > 0 {
+                conditions.push(format!("b{}", (i - 1) / 2));
+                conditions.push(format!("b{}", (i - 1) / 3));
+                expected[i] = expected[(i - 1) / 2] && expected[(i - 1) / 3];
+            }
+            if i % 1000 == 999 {
+                conditions.push(String::from("missing"));
+                expected[i] = false;
+            }
+            items.push(Item::Agent(
+                Agent::build(&format!("a{}", i))
+                    .with_provides(vec![
+                        Behavior::build(&format!("b{}", i)).with_conditions(conditions)
+                    ])
+                    .with_wants(vec![Behavior::build(&format!("b{}", (i * 7919) % count))]),
+            ));
+        }
+        let mut t = Tracker::new();
+        t.add_items(items);
+
+        let mut unsatisfied = 0;
+        for agent_name in t.get_working_agent_names() {
+            for want in t.get_agent_wants(agent_name.clone()) {
+                let i: usize = want[1..].parse().unwrap();
+                let g = t.resolve_graph(&want);
+                assert_eq!(g.is_satisfied(), expected[i], "{}", want);
+                if !g.is_satisfied() {
+                    unsatisfied += 1;
+                }
+            }
+        }
+        assert_eq!(unsatisfied, expected.iter().filter(|s| !**s).count());
+    }
+
+    #[test]
+    fn test_remove_agent() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b2")]));
+        t.add_agent(Agent::build("a2").with_wants(vec![Behavior::build("b1")]));
+        assert!(t.resolve("b1").is_satisfied());
+
+        assert!(t.remove_agent("a1"));
+        assert!(!t.remove_agent("a1"));
+        assert_eq!(t.get_agent_names(), vec!["a2"]);
+        assert_eq!(t.get_working_agent_names(), vec!["a2"]);
+        assert!(!t.resolve("b1").is_satisfied());
+        assert!(!t.has_behavior(String::from("b2")));
+    }
+
+    #[test]
+    fn test_remove_superagent() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b2")]));
+        t.add_superagent(
+            SuperAgent::new(String::from("sa1"))
+                .with_agent("a1")
+                .with_agent("a2"),
+        );
+        assert_eq!(t.get_working_agent_names(), vec!["sa1"]);
+
+        assert!(t.remove_superagent("sa1"));
+        assert!(!t.remove_superagent("sa1"));
+        assert_eq!(t.get_working_agent_names(), vec!["a1", "a2"]);
+        assert_eq!(
+            t.resolve("b1").to_strings_compressed(false),
+            vec!["b1 |-> a1".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_remove_instance() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_superagent(
+            SuperAgent::new(String::from("sa1"))
+                .with_agent("a1")
+                .with_instance("i1", "", "t1", "", vec![], vec![])
+                .with_instance("i2", "", "t2", "", vec![], vec![]),
+        );
+        assert!(t.remove_instance("i1"));
+        assert!(!t.remove_instance("i1"));
+        assert_eq!(t.get_working_agent_names(), vec!["i2"]);
+        assert!(!t.resolve("b1 | t1").is_satisfied());
+
+        // without instances, the SuperAgent is an agent of its own
+        assert!(t.remove_instance("i2"));
+        assert_eq!(t.get_working_agent_names(), vec!["sa1"]);
+    }
+
+    #[test]
+    fn test_replace_item() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b2")]));
+        t.replace_item(Item::Agent(
+            Agent::build("a1").with_provides(vec![Behavior::build("b3")]),
+        ));
+        assert_eq!(t.get_agent_names(), vec!["a1"]);
+        assert_eq!(
+            t.get_agent_provides("a1"),
+            Some(HashSet::from([String::from("b3")]))
+        );
+
+        t.add_superagent(SuperAgent::new(String::from("sa1")).with_agent("a1"));
+        t.replace_item(Item::SuperAgent(
+            SuperAgent::new(String::from("sa1")).with_agent("a2"),
+        ));
+        assert_eq!(t.get_working_agent_names(), vec!["a1", "sa1"]);
+    }
+
+    #[test]
+    fn test_sources() {
+        let a1 = || Agent::build("a1").with_provides(vec![Behavior::build("b1")]);
+        let a2 = || {
+            Agent::build("a2").with_provides(vec![
+                Behavior::build("b2").with_conditions(vec![String::from("b1")])
+            ])
+        };
+        let mut t = Tracker::new();
+        t.add_source_items("x.yaml", vec![Item::Agent(a1()), Item::Agent(a2())]);
+        // the same agent from somewhere else
+        t.add_source_items("y.yaml", vec![Item::Agent(a1())]);
+        t.add_agent(Agent::build("a3"));
+        assert_eq!(t.get_source_names(), vec!["x.yaml", "y.yaml"]);
+        assert_eq!(
+            t.get_source_items("x.yaml"),
+            vec![Item::Agent(a1()), Item::Agent(a2())]
+        );
+        assert!(t.resolve("b2").is_satisfied());
+
+        assert!(t.remove_source("y.yaml"));
+        assert!(!t.remove_source("y.yaml"));
+        assert_eq!(t.get_source_names(), vec!["x.yaml"]);
+        assert!(t.resolve("b2").is_satisfied());
+
+        t.replace_source("x.yaml", vec![Item::Agent(a2())]);
+        assert_eq!(t.get_agent_names(), vec!["a2", "a3"]);
+        assert!(!t.resolve("b2").is_satisfied());
+
+        t.replace_sources(vec![
+            (String::from("x.yaml"), vec![]),
+            (
+                String::from("z.yaml"),
+                vec![Item::Agent(a1()), Item::Agent(a2())],
+            ),
+        ]);
+        assert_eq!(t.get_source_names(), vec!["z.yaml"]);
+        assert!(t.get_source_items("x.yaml").is_empty());
+        assert!(t.resolve("b2").is_satisfied());
+    }
+
+    #[test]
+    fn test_conditional_wants() {
+        let mut t = Tracker::new();
+        t.add_agent(
+            Agent::build("app")
+                .with_provides(vec![Behavior::build("web")])
+                .with_wants(vec![
+                    // only when app itself provides web
+                    Behavior::build("tls").with_own_conditions(vec![String::from("web")]),
+                    // only when app itself provides search, which it doesn't
+                    Behavior::build("index").with_own_conditions(vec![String::from("search")]),
+                    // only when tracing exists in the network
+                    Behavior::build("collector").with_conditions(vec![String::from("tracing")]),
+                    Behavior::build("logs").with_any_of(vec![
+                        vec![String::from("tracing")],
+                        vec![String::from("audit")],
+                    ]),
+                    Behavior::build("db"),
+                ]),
+        );
+        t.add_agent(Agent::build("solr").with_provides(vec![Behavior::build("search")]));
+        assert_eq!(
+            t.get_agent_wants(String::from("app")),
+            HashSet::from([String::from("tls"), String::from("db")])
+        );
+        let skipped = t.get_skipped_wants("app");
+        assert_eq!(
+            skipped
+                .iter()
+                .map(|w| (w.behavior_name.as_str(), w.get_reason()))
+                .collect::<Vec<(&str, String)>>(),
+            vec![
+                (
+                    "collector",
+                    String::from("only wanted when tracing is provided")
+                ),
+                (
+                    "index",
+                    String::from("only wanted when app provides search")
+                ),
+                (
+                    "logs",
+                    String::from("only wanted when audit or tracing is provided")
+                ),
+            ]
+        );
+        let report = t.resolve_all();
+        assert_eq!(report.totals.wants, 2);
+        assert_eq!(report.get_agent("app").unwrap().skipped, skipped);
+
+        // another agent providing web doesn't count for own conditions
+        t.add_agent(Agent::build("jaeger").with_provides(vec![Behavior::build("tracing")]));
+        assert_eq!(t.get_agent_wants(String::from("app")).len(), 4);
+        assert_eq!(t.get_skipped_wants("app").len(), 1);
+        assert!(t.get_skipped_wants("nope").is_empty());
+
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("app").with_wants(vec![
+            Behavior::build("tls").with_own_conditions(vec![String::from("web")]),
+            Behavior::build("pool")
+                .with_conditions(vec![String::from("db")])
+                .with_any_of(vec![
+                    vec![String::from("pg"), String::from("disk")],
+                    vec![String::from("mysql")],
+                ]),
+        ]));
+        t.add_agent(Agent::build("nginx").with_provides(vec![Behavior::build("web")]));
+        assert!(t.get_agent_wants(String::from("app")).is_empty());
+        assert_eq!(
+            t.get_skipped_wants("app")
+                .iter()
+                .map(|w| w.get_reason())
+                .collect::<Vec<String>>(),
+            [
+                "only wanted when (db and disk and pg) or (db and mysql) is provided",
+                "only wanted when app provides web",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_labels() {
         let mut t = Tracker::new();
-
-        assert!(t.is_empty());
-
-        t.add_agent(Agent::new(String::from("abcd")));
-        assert!(t.has_agent(String::from("abcd")));
-        assert!(!t.has_agent(String::from("efgh")));
-        assert!(t.is_agent_wants_empty(String::from("abcd")));
-
-        t.add_agent(Agent::new(String::from("efgh")));
-        assert!(t.has_agent(String::from("efgh")));
-        let mut efgh = Agent::new(String::from("efgh"));
-        efgh.add_want(Behavior::new(String::from("efgh_want1")));
-        t.add_agent(efgh);
-        assert!(!t.is_agent_wants_empty(String::from("efgh")));
-
-        assert!(t.has_behavior(String::from("efgh_want1")));
-        assert!(!t.has_behavior(String::from("missing_want")));
+        t.add_agent(
+            Agent::build("api")
+                .with_label("team", "payments")
+                .with_label("tier", "web"),
+        );
+        t.add_agent(Agent::build("api").with_label("team", "other"));
+        t.add_agent(Agent::build("db").with_label("team", "storage"));
+        t.add_agent(Agent::build("worker").with_label("tier", "batch"));
+        t.add_superagent(
+            SuperAgent::new(String::from("queue"))
+                .with_label("team", "payments")
+                .with_label("tier", "batch")
+                .with_agent("worker")
+                .with_instance("queue-eu", "", "eu", "eu", vec![], vec![])
+                .with_instance("queue-us", "", "us", "us", vec![], vec![])
+                .with_instance_label("queue-us", "tier", "web"),
+        );
+        // merged definitions keep the first value
+        assert_eq!(
+            t.get_agent_labels("api").unwrap().get("team"),
+            Some(&String::from("payments"))
+        );
+        // instances get the SuperAgent's labels, overridden by their own
+        assert_eq!(
+            t.get_agent_labels("queue-eu").unwrap().get("tier"),
+            Some(&String::from("batch"))
+        );
+        assert_eq!(
+            t.get_agent_labels("queue-us").unwrap().get("tier"),
+            Some(&String::from("web"))
+        );
+        assert_eq!(t.get_agent_labels("worker"), None);
+        let select = |s: &str| t.select_agent_names(&s.parse().unwrap());
+        assert_eq!(select("team=payments"), vec!["api", "queue-eu", "queue-us"]);
+        assert_eq!(select("team=payments,tier!=batch"), vec!["api", "queue-us"]);
+        assert_eq!(select("!team"), Vec::<&String>::new());
+        assert_eq!(select("").len(), 4);
     }
 
     #[test]
-    fn nested_gets() {
+    fn test_versions() {
         let mut t = Tracker::new();
+        t.add_agent(
+            Agent::build("orders-v1")
+                .with_provides(vec![Behavior::build("orders-api").with_version("1.4.0")]),
+        );
+        t.add_agent(
+            Agent::build("orders-v2").with_provides(vec![Behavior::build("orders-api")
+                .with_version("2.3.0")
+                .with_conditions(vec![String::from("db")])
+                .with_condition_version("db", ">=14")]),
+        );
+        t.add_agent(
+            Agent::build("pg").with_provides(vec![Behavior::build("db").with_version("13.2.0")]),
+        );
+        t.add_agent(
+            Agent::build("web")
+                .with_wants(vec![Behavior::build("orders-api").with_version("^2.1")]),
+        );
+        t.add_agent(Agent::build("batch").with_wants(vec![Behavior::build("orders-api")]));
 
-        let mut abcd = Agent::new(String::from("abcd"));
-        abcd.add_want(Behavior::new(String::from("abcd_w1")));
-        t.add_agent(abcd);
-        let mut abcd = Agent::new(String::from("abcd"));
-        abcd.add_want(Behavior::new(String::from("abcd_w2")));
-        t.add_agent(abcd);
+        // without a requirement every provider is used
+        assert!(t.resolve_graph("orders-api").is_satisfied());
+        let graph = t.resolve_graph_with_requirement("orders-api", Some("^2.1"));
+        assert_eq!(
+            graph.to_resolution().to_strings_compressed(false),
+            vec!["orders-api |-> orders-v2 &-> db |-> ? (needs >=14: pg has 13.2.0)".to_string(),]
+        );
+        assert_eq!(graph.get_root_node().get_mismatches().len(), 1);
+        assert_eq!(
+            t.resolve_graph_with_requirement("orders-api", Some("^3"))
+                .get_root_node()
+                .to_mismatch_string(),
+            Some(String::from(
+                "needs ^3: orders-v1 has 1.4.0, orders-v2 has 2.3.0"
+            ))
+        );
 
-        let mut efgh = Agent::new(String::from("efgh"));
-        efgh.add_want(Behavior::new(String::from("efgh_w3")));
-        t.add_agent(efgh);
+        let report = t.resolve_all();
+        let status = |agent_name: &str| report.get_agent(agent_name).unwrap().wants[0].status;
+        assert_eq!(status("batch"), WantStatus::Satisfied);
+        assert_eq!(status("web"), WantStatus::Unsatisfied);
+        assert_eq!(report.get_distinct_wants().len(), 2);
 
+        t.add_agent(
+            Agent::build("pg").with_provides(vec![Behavior::build("db").with_version("15.0.0")]),
+        );
         assert_eq!(
-            t.get_agent_wants(String::from("abcd")),
-            HashSet::from([String::from("abcd_w1"), String::from("abcd_w2"),])
+            t.resolve_all().get_agent("web").unwrap().wants[0].status,
+            WantStatus::Satisfied
+        );
+        t.add_agent(
+            Agent::build("mobile")
+                .with_wants(vec![Behavior::build("orders-api").with_version("^3")]),
         );
+        let report = t.resolve_all();
         assert_eq!(
-            t.get_agent_wants(String::from("efgh")),
-            HashSet::from([String::from("efgh_w3"),])
+            report.get_agent("mobile").unwrap().wants[0].status,
+            WantStatus::VersionMismatch
         );
+        assert_eq!(report.totals.version_mismatch, 1);
     }
 
     #[test]
-    fn test_simple_resolve() {
+    fn test_placements() {
         let mut t = Tracker::new();
-        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        for (name, zone) in [("pg-east", "us-east"), ("pg-west", "eu-west")] {
+            t.add_agent(
+                Agent::build(name)
+                    .with_label("zone", zone)
+                    .with_label("region", &zone[..2])
+                    .with_provides(vec![Behavior::build("db")]),
+            );
+        }
+        t.add_agent(
+            Agent::build("orders")
+                .with_label("zone", "eu-west")
+                .with_provides(vec![Behavior::build("orders-api")
+                    .with_conditions(vec![String::from("db")])
+                    .with_condition_placement(
+                        "db",
+                        Placement {
+                            same_as: vec![String::from("zone")],
+                            ..Placement::default()
+                        },
+                    )]),
+        );
+        t.add_agent(
+            Agent::build("web")
+                .with_label("zone", "us-east")
+                .with_wants(vec![
+                    Behavior::build("db").with_same_as("zone"),
+                    Behavior::build("orders-api").with_match_label("region", "us"),
+                ]),
+        );
+        t.add_agent(
+            Agent::build("batch").with_wants(vec![Behavior::build("db").with_same_as("zone")]),
+        );
+
+        // without a want, any provider anywhere will do
+        assert_eq!(t.resolve("db").get_satisfying_offers().len(), 2);
+        let db = t.resolve_want("web", "db");
+        assert_eq!(db.get_satisfying_offers().len(), 1);
+        assert_eq!(db.get_satisfying_offers()[0].get_agent_name(), "pg-east");
+        assert_eq!(db.get_placement(), Some("zone=us-east"));
         assert_eq!(
-            t.resolve("b1"),
-            Resolution::new("b1").add_satisfying_offer(Offer::new("a1"))
+            db.get_rejections(),
+            [Rejection {
+                agent_name: String::from("pg-west"),
+                reason: String::from("zone is eu-west, not us-east"),
+            }]
+        );
+        // conditions are placed relative to the providing agent
+        let orders = t.resolve("orders-api");
+        let condition = &orders.get_satisfying_offers()[0].get_resolved_conditions()[0];
+        assert_eq!(
+            condition.get_satisfying_offers()[0].get_agent_name(),
+            "pg-west"
         );
+        assert_eq!(condition.get_rejections()[0].agent_name, "pg-east");
 
-        t.add_agent(Agent::build("a2").with_provides(vec![
-            Behavior::build("b1").with_conditions(vec![String::from("b2")]),
-        ]));
+        let graph = t.resolve_want_graph("web", "orders-api");
         assert_eq!(
-            t.resolve("b1"),
-            Resolution::new("b1")
-                .add_satisfying_offer(Offer::new("a1"))
-                .add_unsatisfying_offer(Offer::new_conditional("a2", vec!(Resolution::new("b2"))))
+            graph.get_root_node().to_rejection_string(),
+            Some(String::from(
+                "needs region=us: orders (no region label, wanted us)"
+            ))
         );
+        let report = t.resolve_all();
+        let status =
+            |agent_name: &str, i: usize| report.get_agent(agent_name).unwrap().wants[i].status;
+        assert_eq!(status("web", 0), WantStatus::Satisfied);
+        assert_eq!(status("web", 1), WantStatus::PlacementMismatch);
+        // batch has no zone, so nothing is in the same one
+        assert_eq!(status("batch", 0), WantStatus::PlacementMismatch);
+        assert_eq!(report.totals.placement_mismatch, 2);
+        assert_eq!(report.get_distinct_wants().len(), 3);
+    }
 
-        t.add_agent(Agent::build("a3").with_provides(vec![Behavior::build("b2")]));
+    #[test]
+    fn test_allocate() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("pg").with_provides(vec![Behavior::build("db").with_capacity(3)]));
+        t.add_agent(
+            Agent::build("replica").with_provides(vec![Behavior::build("db").with_capacity(2)]),
+        );
+        t.add_agent(Agent::build("redis").with_provides(vec![Behavior::build("cache")]));
+        for (name, demand) in [("a", 2), ("b", 2), ("c", 1), ("d", 1)] {
+            t.add_agent(Agent::build(name).with_wants(vec![
+                Behavior::build("db").with_demand(demand),
+                Behavior::build("cache"),
+            ]));
+        }
+        assert_eq!(t.get_provide_capacity("pg", "db"), Some(3));
+        assert_eq!(t.get_provide_capacity("redis", "cache"), None);
+        assert_eq!(t.get_want_demand("a", "db"), Some(2));
+        assert_eq!(t.get_want_demand("a", "cache"), Some(1));
+
+        let allocation = t.allocate();
+        // unlimited providers are left out
+        assert_eq!(allocation.loads.len(), 2);
+        let consumers = |agent_name: &str| {
+            allocation
+                .get_load(agent_name, "db")
+                .unwrap()
+                .consumers
+                .iter()
+                .map(|c| c.agent_name.as_str())
+                .collect::<Vec<&str>>()
+        };
+        // a goes to pg (3 left), b to replica (2 left), c to pg (1 left) and
+        // d to pg again, as both are full and pg comes first
+        assert_eq!(consumers("pg"), vec!["a", "c", "d"]);
+        assert_eq!(consumers("replica"), vec!["b"]);
         assert_eq!(
-            t.resolve("b1"),
-            Resolution::new("b1")
-                .add_satisfying_offer(Offer::new("a1"))
-                .add_satisfying_offer(Offer::new_conditional(
-                    "a2",
-                    vec!(Resolution::new("b2").add_satisfying_offer(Offer::new("a3")))
-                ))
+            allocation
+                .get_oversubscribed()
+                .iter()
+                .map(|l| l.to_string())
+                .collect::<Vec<String>>(),
+            vec!["pg provides db: 4 of 3 used by 3 consumers (oversubscribed)"]
         );
+
+        // with an unlimited provider around, nothing is charged
+        t.add_agent(Agent::build("aurora").with_provides(vec![Behavior::build("db")]));
+        let allocation = t.allocate();
+        assert!(!allocation.is_oversubscribed());
+        assert_eq!(allocation.get_load("pg", "db").unwrap().get_demand(), 0);
     }
 
     #[test]
-    fn test_resolve_multiple_satisfying() {
+    fn test_any_of() {
         let mut t = Tracker::new();
-        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
-        t.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b1")]));
-        t.add_agent(Agent::build("a3").with_provides(vec![Behavior::build("b1")]));
-        let resolve_b1 = Resolution::new("b1")
-            .add_satisfying_offer(Offer::new("a1"))
-            .add_satisfying_offer(Offer::new("a2"))
-            .add_satisfying_offer(Offer::new("a3"));
-        assert_eq!(t.resolve("b1"), resolve_b1);
+        t.add_agent(
+            Agent::build("app").with_provides(vec![Behavior::build("web")
+                .with_conditions(vec![String::from("db")])
+                .with_any_of(vec![
+                    vec![String::from("cache")],
+                    vec![String::from("disk"), String::from("local")],
+                ])]),
+        );
+        t.add_agent(Agent::build("pg").with_provides(vec![Behavior::build("db")]));
+        t.add_agent(Agent::build("ssd").with_provides(vec![Behavior::build("disk")]));
+
+        // neither group is complete
+        let graph = t.resolve_graph("web");
+        assert!(!graph.is_satisfied());
+        assert_eq!(graph.get_root_causes(), vec![vec!["cache"], vec!["local"]]);
+        assert_eq!(
+            t.resolve("web").to_strings_compressed(false),
+            vec![
+                "web |-> app &-> db |-> pg",
+                "            1-> cache |-> ?",
+                "            2-> disk |-> ssd",
+                "            2-> local |-> ?",
+            ]
+        );
+        assert_eq!(graph.to_resolution(), t.resolve("web"));
+
+        // completing one group is enough
+        t.add_agent(Agent::build("fs").with_provides(vec![Behavior::build("local")]));
+        assert!(t.resolve("web").is_satisfied());
+        assert!(t.get_root_causes("web").is_empty());
+
+        // but the plain conditions are still required
+        t.remove_agent("pg");
+        assert_eq!(t.get_root_causes("web"), vec![vec!["db"]]);
     }
 
     #[test]
-    fn test_resolve_unsatisfied() {
+    fn test_suggestions() {
         let mut t = Tracker::new();
-        t.add_agent(Agent::build("a1").with_provides(vec![
-            Behavior::build("b1").with_conditions(vec![String::from("b2a"), String::from("b2b")]),
-        ]));
-        t.add_agent(Agent::build("a2").with_provides(vec![Behavior::build("b2a")]));
+        let mut db = Agent::new(String::from("db"));
+        db.add_provide(Behavior::new(String::from("postgres-connection")));
+        db.add_provide(Behavior::new(String::from("postgres-backup")));
+        t.add_agent(db);
+        let mut app = Agent::new(String::from("app"));
+        app.add_want(Behavior::new(String::from("postgres-conn")));
+        app.add_provide(Behavior::new_with_conditions(
+            String::from("web"),
+            vec![String::from("postgres-conn")],
+        ));
+        t.add_agent(app);
 
+        let expected = vec!["postgres-connection", "postgres-backup"];
+        assert_eq!(t.get_suggestions("postgres-conn"), expected);
+        assert_eq!(t.resolve("postgres-conn").get_suggestions(), expected);
+        assert_eq!(t.resolve("postgres-conection").get_suggestions(), expected);
+        // only behaviors that nothing provides get suggestions
+        assert!(t.resolve("postgres-backup").get_suggestions().is_empty());
+        let graph = t.resolve_graph("web");
+        let condition = graph.get_root_node().get_unsatisfying_offers()[0].get_conditions()[0];
         assert_eq!(
-            t.resolve("b1"),
-            Resolution::new("b1").add_unsatisfying_offer(Offer::new_conditional(
-                "a1",
-                vec!(
-                    Resolution::new("b2a").add_satisfying_offer(Offer::new("a2")),
-                    Resolution::new("b2b"),
-                )
+            graph.get_node(condition).to_suggestion_string(),
+            Some(String::from(
+                "did you mean: postgres-connection, postgres-backup"
             ))
-        )
+        );
+        assert_eq!(
+            graph.to_resolution().to_strings_compressed(false),
+            vec!["web |-> app &-> postgres-conn |-> ? (did you mean: postgres-connection, postgres-backup)"]
+        );
     }
 
     #[test]
-    fn test_resolve_deep() {
+    fn test_provenance() {
         let mut t = Tracker::new();
-        t.add_agent(Agent::build("a1").with_provides(vec![
-            Behavior::build("b1").with_conditions(vec![String::from("b2")]),
-        ]));
-        t.add_agent(Agent::build("a2").with_provides(vec![
-            Behavior::build("b2").with_conditions(vec![String::from("b3")]),
-        ]));
-        t.add_agent(Agent::build("a3").with_provides(vec![
-            Behavior::build("b3").with_conditions(vec![String::from("b4")]),
-        ]));
-        t.add_agent(Agent::build("a4").with_provides(vec![Behavior::build("b4")]));
-        let satisfied_part = Resolution::new("b1").add_satisfying_offer(Offer::new_conditional(
-            "a1",
-            vec![
-                Resolution::new("b2").add_satisfying_offer(Offer::new_conditional(
-                    "a2",
-                    vec![
-                        Resolution::new("b3").add_satisfying_offer(Offer::new_conditional(
-                            "a3",
-                            vec![Resolution::new("b4").add_satisfying_offer(Offer::new("a4"))],
-                        )),
-                    ],
-                )),
-            ],
-        ));
-        assert_eq!(t.resolve("b1"), satisfied_part);
-        t.add_agent(Agent::build("a0").with_provides(vec![
-            Behavior::build("b0").with_conditions(vec![String::from("b1"), String::from("b1b")]),
-        ]));
-        t.add_agent(Agent::build("a1b").with_provides(vec![
-            Behavior::build("b1b").with_conditions(vec![String::from("b2b")]),
-        ]));
+        let db = "kind: Agent
+name: db
+provides:
+  - name: storage
+    conditions: [disk]
+";
+        let disks = "kind: Agent
+name: disks
+provides:
+  - name: disk
+---
+kind: Agent
+name: db
+provides:
+  - name: storage
+    conditions: [disk]
+---
+kind: SuperAgent
+name: sa
+agents: [a1]
+instances:
+  - name: i1
+    providesTag: p
+    conditionsTag: c
+";
+        t.add_source_items("db.yaml", loader::load_items("db.yaml", db).unwrap());
+        t.add_source_items(
+            "disks.yaml",
+            loader::load_items("disks.yaml", disks).unwrap(),
+        );
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+
+        // both definitions of db are kept after merging
+        let working_db = &t.working_agents["db"][0];
+        let lines = |p: &Provenance| {
+            p.get_locations()
+                .iter()
+                .map(|l| l.to_string())
+                .collect::<Vec<String>>()
+        };
         assert_eq!(
-            t.resolve("b0"),
-            Resolution::new("b0").add_unsatisfying_offer(Offer::new_conditional(
-                "a0",
-                vec!(
-                    satisfied_part,
-                    Resolution::new("b1b").add_unsatisfying_offer(Offer::new_conditional(
-                        "a1b",
-                        vec!(Resolution::new("b2b"))
-                    ))
-                )
+            lines(working_db.get_provenance()),
+            vec!["db.yaml:1", "disks.yaml:6"]
+        );
+        let storage = working_db.get_all_provides().into_iter().next().unwrap();
+        assert_eq!(
+            lines(storage.get_provenance()),
+            vec!["db.yaml:4", "disks.yaml:9"]
+        );
+
+        // and instances point at their definition, then at their SuperAgent
+        let i1 = &t.working_agents["i1"][0];
+        assert_eq!(
+            lines(i1.get_provenance()),
+            vec!["disks.yaml:16", "disks.yaml:12"]
+        );
+
+        let r = t.resolve("storage");
+        assert_eq!(
+            r.get_satisfying_offers()[0]
+                .get_location()
+                .unwrap()
+                .to_string(),
+            "db.yaml:4"
+        );
+        assert_eq!(
+            r.to_strings_compressed(false),
+            vec!["storage |-> db (db.yaml:4) &-> disk |-> disks (disks.yaml:4)".to_string()]
+        );
+        // locations never change what counts as the same resolution
+        assert_eq!(
+            r,
+            Resolution::new("storage").add_satisfying_offer(Offer::new_conditional(
+                "db",
+                vec![Resolution::new("disk").add_satisfying_offer(Offer::new("disks"))]
             ))
         );
+        assert_eq!(ResolutionGraph::from(&r).to_resolution(), r);
     }
 
     #[test]
@@ -575,6 +2881,108 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_nested_superagents() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("app").with_provides(vec![
+            Behavior::build("api").with_conditions(vec![String::from("db")]),
+        ]));
+        t.add_agent(Agent::build("pg").with_provides(vec![Behavior::build("db")]));
+        t.add_agent(Agent::build("lb").with_provides(vec![
+            Behavior::build("web").with_conditions(vec![String::from("api | a")]),
+        ]));
+        t.add_superagent(
+            SuperAgent::new(String::from("pod"))
+                .with_agent("app")
+                .with_agent("pg")
+                .with_instance("pod-a", "", "a", "a", vec![], vec![])
+                .with_instance("pod-b", "", "b", "b", vec![], vec![]),
+        );
+        t.add_superagent(
+            SuperAgent::new(String::from("service"))
+                .with_agent("pod")
+                .with_agent("lb"),
+        );
+        t.add_superagent(
+            SuperAgent::new(String::from("cluster"))
+                .with_agent("service")
+                .with_instance("eu", "", "eu", "eu", vec![], vec![])
+                .with_instance("us", "", "us", "us", vec![], vec![]),
+        );
+        assert_eq!(t.get_working_agent_names(), vec!["eu", "us"]);
+        // db is handled inside each pod, and api | a inside the service
+        assert_eq!(
+            t.resolve("web | eu"),
+            Resolution::new("web | eu").add_satisfying_offer(Offer::new("eu"))
+        );
+        assert_eq!(
+            t.resolve("api | a | us"),
+            Resolution::new("api | a | us").add_satisfying_offer(Offer::new("us"))
+        );
+        assert_eq!(t.get_superagent_cycles(), Vec::<Vec<String>>::new());
+        let leaf = |name: &str| SuperAgentMember {
+            name: String::from(name),
+            members: vec![],
+        };
+        assert_eq!(
+            t.get_superagent_members("eu"),
+            vec![SuperAgentMember {
+                name: String::from("service"),
+                members: vec![
+                    SuperAgentMember {
+                        name: String::from("pod"),
+                        members: vec![leaf("app"), leaf("pg")],
+                    },
+                    leaf("lb"),
+                ],
+            }]
+        );
+        assert!(t.get_superagent_members("service").is_empty());
+
+        // SuperAgents that contain each other are not nested in one another
+        t.add_superagent(SuperAgent::new(String::from("pod")).with_agent("cluster"));
+        assert_eq!(
+            t.get_superagent_cycles(),
+            vec![vec!["cluster", "pod", "service"]]
+        );
+        assert_eq!(
+            t.get_working_agent_names(),
+            vec!["eu", "pod", "pod-a", "pod-b", "service", "us"]
+        );
+        assert_eq!(t.get_superagent_members("service"), vec![leaf("lb")]);
+    }
+
+    #[test]
+    fn test_superagent_redundant_providers() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("api").with_provides(vec![
+            Behavior::build("orders").with_conditions(vec![String::from("db")]),
+        ]));
+        t.add_agent(Agent::build("primary").with_provides(vec![
+            Behavior::build("db").with_conditions(vec![String::from("disk")]),
+        ]));
+        t.add_agent(Agent::build("replica").with_provides(vec![
+            Behavior::build("db").with_conditions(vec![String::from("s3")]),
+        ]));
+        t.add_superagent(
+            SuperAgent::new(String::from("sa1"))
+                .with_agent("api")
+                .with_agent("primary")
+                .with_agent("replica"),
+        );
+        // either database will do, not just the first one, so sa1 provides
+        // orders once for each
+        t.add_agent(Agent::build("bucket").with_provides(vec![Behavior::build("s3")]));
+        assert_eq!(
+            t.resolve("orders").to_strings_compressed(false),
+            vec![
+                "orders |-> sa1 &-> s3 |-> bucket",
+                "       |-> sa1 &-> disk |-> ?",
+            ]
+        );
+        assert!(t.resolve("orders").is_satisfied());
+    }
+
     #[test]
     fn test_resolve_torture() {
         let mut t = Tracker::new();
@@ -635,4 +3043,102 @@ mod tests {
             )),
         )
     }
+
+    #[test]
+    fn test_startup_order() {
+        let mut t = Tracker::new();
+        let conditions = |c: &[&str]| c.iter().map(|c| String::from(*c)).collect();
+        t.add_agent(Agent::build("db").with_provides(vec![Behavior::build("storage")]));
+        t.add_agent(Agent::build("cache").with_provides(vec![
+            Behavior::build("kv").with_conditions(conditions(&["storage"])),
+        ]));
+        t.add_agent(Agent::build("api").with_provides(vec![
+            Behavior::build("http").with_conditions(conditions(&["storage", "kv"])),
+        ]));
+        // never comes up, so nothing waits for it
+        t.add_agent(Agent::build("dead").with_provides(vec![
+            Behavior::build("http").with_conditions(conditions(&["nothing"])),
+        ]));
+        // wants don't hold anything up
+        t.add_agent(
+            Agent::build("web")
+                .with_provides(vec![
+                    Behavior::build("ui").with_conditions(conditions(&["http"]))
+                ])
+                .with_wants(vec![Behavior::build("kv")]),
+        );
+        t.add_agent(Agent::build("loner"));
+        // x and y need each other, a works with q or r, and b needs a
+        t.add_agent(Agent::build("x").with_provides(vec![
+            Behavior::build("bx").with_conditions(conditions(&["by"])),
+        ]));
+        t.add_agent(Agent::build("y").with_provides(vec![
+            Behavior::build("by").with_conditions(conditions(&["bx"])),
+        ]));
+        t.add_agent(Agent::build("a").with_provides(vec![
+            Behavior::build("p").with_any_of(vec![conditions(&["q"]), conditions(&["r"])]),
+        ]));
+        t.add_agent(Agent::build("b").with_provides(vec![
+            Behavior::build("q").with_conditions(conditions(&["p"])),
+        ]));
+        t.add_agent(Agent::build("rr").with_provides(vec![Behavior::build("r")]));
+
+        let order = t.get_startup_order();
+        assert_eq!(
+            order.to_strings(),
+            [
+                "wave 1: db, dead, loner, rr, x, y",
+                "wave 2: a, cache",
+                "wave 3: api, b",
+                "wave 4: web",
+                "cycle: x, y",
+            ]
+        );
+        assert_eq!(order.get_wave("api"), Some(3));
+        assert_eq!(order.get_wave("nope"), None);
+        assert!(order.has_cycles());
+
+        // only providers at the right version count
+        t.replace_item(Item::Agent(Agent::build("cache").with_provides(vec![
+            Behavior::build("kv")
+                .with_conditions(conditions(&["storage"]))
+                .with_condition_version("storage", ">=2"),
+        ])));
+        assert_eq!(t.get_startup_order().get_wave("cache"), Some(1));
+    }
+
+    #[test]
+    fn test_any_instance_resolve() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_superagent(
+            SuperAgent::new(String::from("sa1"))
+                .with_agent("a1")
+                .with_instance("i1", "", "i1p", "", vec![], vec![])
+                .with_instance("i2", "", "i2p", "", vec![], vec![]),
+        );
+        assert_eq!(t.get_behavior_instances("b1"), ["b1 | i1p", "b1 | i2p"]);
+        // any instance
+        assert_eq!(
+            t.resolve("b1 | *"),
+            Resolution::new("b1 | *")
+                .add_satisfying_offer(Offer::new("i1"))
+                .add_satisfying_offer(Offer::new("i2"))
+        );
+        assert_eq!(t.resolve("b1|*").get_satisfying_offers().len(), 2);
+        // this specific instance
+        assert_eq!(
+            t.resolve("b1 | i2p"),
+            Resolution::new("b1 | i2p").add_satisfying_offer(Offer::new("i2"))
+        );
+        // however the tags are spaced, in wants and in conditions
+        assert_eq!(t.resolve("b1|i2p"), t.resolve("b1 | i2p"));
+        t.add_agent(Agent::build("a2").with_provides(vec![
+            Behavior::build("b2").with_conditions(vec![String::from("b1|i1p")]),
+        ]));
+        assert!(t.resolve_graph("b2").is_satisfied());
+        assert!(!t
+            .get_suggestions("b1 | x")
+            .contains(&String::from("b1 | *")));
+    }
 }
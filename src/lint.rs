@@ -0,0 +1,588 @@
+//! Hygiene checks for contracts.
+//!
+//! Unlike `validate`, nothing reported here breaks a network; it is clutter
+//! that makes contracts harder to read and mistakes easier to miss. Every
+//! rule has an id (its `DiagnosticCode`) and can be turned off with a
+//! `LintConfig`. Rules that need to see how a contract was written, rather
+//! than what it means, run over the contract text with `lint_contract`.
+
+use crate::components::{Agent, Behavior, BehaviorId, IntermediateAgent, Item, Location};
+use crate::loader::{document_ranges, load_documents, parse_documents};
+use crate::similarity::{differ_only_by_case, is_probable_typo};
+use crate::validate::{Diagnostic, DiagnosticCode, Severity};
+use crate::{behavior_key, Tracker};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Every lint rule
+pub const RULES: [DiagnosticCode; 4] = [
+    DiagnosticCode::UnwantedProvide,
+    DiagnosticCode::SelfCondition,
+    DiagnosticCode::FactorableGlobalConditions,
+    DiagnosticCode::SimilarBehaviorNames,
+];
+
+/// Which rules to run. All of them unless turned off.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    disabled: HashSet<DiagnosticCode>,
+}
+
+impl LintConfig {
+    pub fn new() -> LintConfig {
+        LintConfig::default()
+    }
+
+    pub fn with_disabled(mut self, rule: DiagnosticCode) -> LintConfig {
+        self.disabled.insert(rule);
+        self
+    }
+
+    pub fn is_enabled(&self, rule: DiagnosticCode) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// The result of `fix_contract`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fix {
+    /// The whole contract with every safe fix applied, or None if nothing
+    /// was rewritten
+    pub fixed: Option<String>,
+    /// Why each agent that needs fixing, but would lose something if
+    /// rewritten, was left alone
+    pub skipped: Vec<String>,
+}
+
+impl Fix {
+    /// Nothing was fixed and nothing was left alone
+    pub fn is_unchanged(&self) -> bool {
+        self.fixed.is_none() && self.skipped.is_empty()
+    }
+}
+
+/// Runs the rules that look at everything in the Tracker together
+pub fn lint(tracker: &Tracker, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut ret = vec![];
+    if config.is_enabled(DiagnosticCode::SelfCondition) {
+        ret.extend(check_self_conditions(tracker));
+    }
+    if config.is_enabled(DiagnosticCode::UnwantedProvide) {
+        ret.extend(check_unwanted_provides(tracker));
+    }
+    if config.is_enabled(DiagnosticCode::SimilarBehaviorNames) {
+        ret.extend(check_similar_names(tracker));
+    }
+    ret
+}
+
+/// Runs the rules that look at how a single contract was written.
+/// Documents that do not parse are skipped.
+pub fn lint_contract(path: &str, contents: &str, config: &LintConfig) -> Vec<Diagnostic> {
+    if !config.is_enabled(DiagnosticCode::FactorableGlobalConditions) {
+        return vec![];
+    }
+    let items = load_documents(path, contents);
+    let mut ret = vec![];
+    for (i, value) in parse_documents::<serde_yaml::Value>(contents)
+        .into_iter()
+        .enumerate()
+    {
+        let Some(written) = value.ok().and_then(written_agent) else {
+            continue;
+        };
+        let factorable = factorable_conditions(&written);
+        if factorable.is_empty() {
+            continue;
+        }
+        let location = match items.get(i) {
+            Some(Ok(Item::Agent(a))) => a.get_provenance().get_first(),
+            _ => None,
+        };
+        ret.push(
+            lint_diagnostic(
+                DiagnosticCode::FactorableGlobalConditions,
+                format!(
+                    "every provide of agent {} has {}, which can be moved to globalConditions",
+                    written.name,
+                    factorable.join(", ")
+                ),
+            )
+            .with_location(location),
+        );
+    }
+    ret
+}
+
+/// Rewrites the agents in a contract that break factorable-global-conditions.
+/// Only rewrites that keep the meaning are made, so self-conditions are
+/// reported but never removed. Rewritten agents go through the same
+/// serialization as everywhere else, so their conditions are always factored
+/// into `globalConditions`. Other documents are kept as they were. Since YAML
+/// comments can't be kept, an agent with comments is never rewritten; it is
+/// listed in `skipped` and the rest are still fixed. So is an agent whose
+/// self-condition would be moved to `globalConditions`, where it is dropped.
+pub fn fix_contract(contents: &str, config: &LintConfig) -> Fix {
+    let raw_lines: Vec<&str> = contents.lines().collect();
+    let ranges = document_ranges(contents);
+    let mut replacements = vec![];
+    let mut ret = Fix::default();
+    for (i, value) in parse_documents::<serde_yaml::Value>(contents)
+        .into_iter()
+        .enumerate()
+    {
+        let (Some(written), Some(range)) = (value.ok().and_then(written_agent), ranges.get(i))
+        else {
+            continue;
+        };
+        if !config.is_enabled(DiagnosticCode::FactorableGlobalConditions) {
+            continue;
+        }
+        let factorable = factorable_conditions(&written);
+        if factorable.is_empty() {
+            continue;
+        }
+        // comments and blank lines around the agent are kept, but not inside it
+        let is_content = |i: &usize| {
+            let text = raw_lines[*i].trim();
+            !text.is_empty() && !text.starts_with('#')
+        };
+        let Some(start) = range.clone().find(is_content) else {
+            continue;
+        };
+        let end = range.clone().rev().find(is_content).unwrap_or(start) + 1;
+        if raw_lines[start..end]
+            .iter()
+            .any(|l| l.trim_start().starts_with('#') || l.contains(" #"))
+        {
+            ret.skipped.push(format!(
+                "agent {} has comments that would be lost",
+                written.name
+            ));
+            continue;
+        }
+        // a provide never has a global condition on itself
+        if let Some(p) = written
+            .provides
+            .iter()
+            .find(|p| factorable.contains(p.get_name()))
+        {
+            ret.skipped.push(format!(
+                "agent {} would lose the self-condition of {}",
+                written.name,
+                p.get_name()
+            ));
+            continue;
+        }
+        let Ok(agent) = Agent::try_from(written) else {
+            continue;
+        };
+        let name = String::from(agent.get_name());
+        match serde_yaml::to_string(&Item::Agent(agent)) {
+            Ok(text) => replacements.push((start..end, text)),
+            Err(e) => ret.skipped.push(format!("agent {}: {}", name, e)),
+        }
+    }
+    if replacements.is_empty() {
+        return ret;
+    }
+    let mut fixed = String::new();
+    let mut line = 0;
+    for (range, text) in replacements {
+        for l in &raw_lines[line..range.start] {
+            fixed.push_str(l);
+            fixed.push('\n');
+        }
+        fixed.push_str(&text);
+        line = range.end;
+    }
+    for l in &raw_lines[line..] {
+        fixed.push_str(l);
+        fixed.push('\n');
+    }
+    if !contents.ends_with('\n') {
+        fixed.pop();
+    }
+    ret.fixed = Some(fixed);
+    ret
+}
+
+fn lint_diagnostic(code: DiagnosticCode, message: String) -> Diagnostic {
+    Diagnostic::new(Severity::Warning, code, message)
+}
+
+// The agent in a document exactly as it was written, if it is one
+fn written_agent(value: serde_yaml::Value) -> Option<IntermediateAgent> {
+    let serde_yaml::Value::Mapping(mut map) = value else {
+        return None;
+    };
+    if map.remove("kind")? != "Agent" {
+        return None;
+    }
+    serde_yaml::from_value(serde_yaml::Value::Mapping(map)).ok()
+}
+
+// Conditions that every provide repeats, when there is more than one provide
+fn factorable_conditions(written: &IntermediateAgent) -> Vec<String> {
+    if written.provides.len() < 2 {
+        return vec![];
+    }
+    let Ok(agent) = Agent::try_from(written.clone()) else {
+        return vec![];
+    };
+    IntermediateAgent::from(agent)
+        .global_conditions
+        .into_iter()
+        .filter(|c| !written.global_conditions.contains(c))
+        .collect()
+}
+
+fn has_self_condition(b: &Behavior) -> bool {
+    b.get_all_conditions().contains(b.get_name())
+}
+
+fn check_self_conditions(tracker: &Tracker) -> Vec<Diagnostic> {
+    let mut ret = vec![];
+    let mut check = |owner: String, provides: Vec<&Behavior>| {
+        for p in provides.into_iter().filter(|p| has_self_condition(p)) {
+            ret.push(
+                lint_diagnostic(
+                    DiagnosticCode::SelfCondition,
+                    format!(
+                        "{} provides {} with itself as a condition",
+                        owner,
+                        p.get_name()
+                    ),
+                )
+                .with_location(p.get_provenance().get_first()),
+            );
+        }
+    };
+    for a in tracker.available_agents.iter().map(|a| &a.item) {
+        check(
+            format!("agent {}", a.get_name()),
+            a.iter_provides().collect(),
+        );
+    }
+    for sa in tracker.available_superagents.iter().map(|sa| &sa.item) {
+        for i in sa.get_instances() {
+            check(
+                format!("instance {} of SuperAgent {}", i.get_name(), sa.get_name()),
+                i.get_provides().iter().collect(),
+            );
+        }
+    }
+    ret
+}
+
+fn check_unwanted_provides(tracker: &Tracker) -> Vec<Diagnostic> {
+    // wanted directly or needed as a condition
+    let mut wanted: HashSet<String> = HashSet::new();
+    // (agent, behavior) -> where it is provided
+    let mut provided: BTreeMap<(&String, &String), Option<&Location>> = BTreeMap::new();
+    for (agent_name, agents) in &tracker.working_agents {
+        for a in agents {
+            wanted.extend(a.iter_wants().map(|w| behavior_key(w.get_name())));
+            for p in a.iter_provides() {
+                wanted.extend(
+                    p.get_all_conditions()
+                        .into_iter()
+                        .filter(|c| c != p.get_name())
+                        .map(|c| behavior_key(&c)),
+                );
+                let location = p.get_provenance().get_first();
+                provided
+                    .entry((agent_name, p.get_name()))
+                    .and_modify(|l| *l = l.or(location))
+                    .or_insert(location);
+            }
+        }
+    }
+    provided
+        .into_iter()
+        .filter(|((_, behavior_name), _)| {
+            // or through any `| *` name that matches it
+            !wanted.contains(*behavior_name)
+                && !BehaviorId::from(behavior_name.as_str())
+                    .get_any_instance_names()
+                    .iter()
+                    .any(|n| wanted.contains(n))
+        })
+        .map(|((agent_name, behavior_name), location)| {
+            lint_diagnostic(
+                DiagnosticCode::UnwantedProvide,
+                format!(
+                    "agent {} provides {}, which nothing wants",
+                    agent_name, behavior_name
+                ),
+            )
+            .with_location(location)
+        })
+        .collect()
+}
+
+// Where a behavior name is first used, as a provide or a want
+fn find_location<'a>(tracker: &'a Tracker, behavior_name: &str) -> Option<&'a Location> {
+    tracker
+        .available_agents
+        .iter()
+        .flat_map(|a| a.item.iter_provides().chain(a.item.iter_wants()))
+        .filter(|b| b.get_name() == behavior_name)
+        .find_map(|b| b.get_provenance().get_first())
+}
+
+fn check_similar_names(tracker: &Tracker) -> Vec<Diagnostic> {
+    let mut provided: BTreeSet<String> = BTreeSet::new();
+    let mut referenced: BTreeSet<String> = BTreeSet::new();
+    for a in tracker.working_agents.values().flatten() {
+        for p in a.iter_provides() {
+            provided.insert(p.get_name().clone());
+            referenced.extend(p.get_all_conditions());
+        }
+        referenced.extend(a.iter_wants().map(|w| w.get_name().clone()));
+    }
+    let mut ret = vec![];
+    let all: BTreeSet<&String> = provided.iter().chain(referenced.iter()).collect();
+    let mut reported: HashSet<&String> = HashSet::new();
+    for name in &all {
+        for other in all.range::<&String, _>(..*name) {
+            if differ_only_by_case(name, other) && reported.insert(name) {
+                ret.push(
+                    lint_diagnostic(
+                        DiagnosticCode::SimilarBehaviorNames,
+                        format!("behaviors {} and {} differ only by case", other, name),
+                    )
+                    .with_location(find_location(tracker, name)),
+                );
+            }
+        }
+    }
+    // something wanted that nothing provides, but that is one typo away
+    // from something that is
+    for name in referenced.difference(&provided) {
+        if reported.contains(name) {
+            continue;
+        }
+        if let Some(other) = provided.iter().find(|p| is_probable_typo(name, p)) {
+            ret.push(
+                lint_diagnostic(
+                    DiagnosticCode::SimilarBehaviorNames,
+                    format!(
+                        "nothing provides {}, which is one character away from {}",
+                        name, other
+                    ),
+                )
+                .with_location(find_location(tracker, name)),
+            );
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::load_items;
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|d| d.to_string()).collect()
+    }
+
+    fn tracker(path: &str, contents: &str) -> Tracker {
+        let mut t = Tracker::new();
+        t.add_source_items(path, load_items(path, contents).unwrap());
+        t
+    }
+
+    #[test]
+    fn test_lint() {
+        let contents = "kind: Agent
+name: db
+provides:
+  - name: storage
+    conditions: [storage, disk]
+  - name: Disk
+---
+kind: Agent
+name: web
+wants:
+  - name: storag
+  - name: disk
+";
+        let t = tracker("c.yaml", contents);
+        assert_eq!(
+            messages(&lint(&t, &LintConfig::new())),
+            vec![
+                "c.yaml:4: warning[self-condition]: agent db provides storage with itself as a condition",
+                "c.yaml:6: warning[unwanted-provide]: agent db provides Disk, which nothing wants",
+                "c.yaml:4: warning[unwanted-provide]: agent db provides storage, which nothing wants",
+                "c.yaml:12: warning[similar-behavior-names]: behaviors Disk and disk differ only by case",
+                "c.yaml:11: warning[similar-behavior-names]: nothing provides storag, which is one character away from storage",
+            ]
+        );
+        let config = LintConfig::new()
+            .with_disabled(DiagnosticCode::UnwantedProvide)
+            .with_disabled(DiagnosticCode::SimilarBehaviorNames);
+        assert!(!config.is_enabled(DiagnosticCode::UnwantedProvide));
+        assert_eq!(
+            lint(&t, &config)
+                .iter()
+                .map(|d| d.code)
+                .collect::<Vec<DiagnosticCode>>(),
+            vec![DiagnosticCode::SelfCondition]
+        );
+    }
+
+    #[test]
+    fn test_factorable() {
+        let contents = "kind: Agent
+name: a1
+provides:
+  - name: b1
+    conditions: [c1, c2]
+  - name: b2
+    conditions: [c1]
+---
+kind: Agent
+name: a2
+provides:
+  - name: b3
+globalConditions: [c1]
+";
+        assert_eq!(
+            messages(&lint_contract("c.yaml", contents, &LintConfig::new())),
+            vec!["c.yaml:1: warning[factorable-global-conditions]: every provide of agent a1 has c1, which can be moved to globalConditions"]
+        );
+        let config = LintConfig::new().with_disabled(DiagnosticCode::FactorableGlobalConditions);
+        assert!(lint_contract("c.yaml", contents, &config).is_empty());
+        assert!(fix_contract(contents, &config).is_unchanged());
+    }
+
+    #[test]
+    fn test_fix() {
+        let contents = "# agents
+kind: Agent
+name: a1
+provides:
+  - name: b1
+    comment: kept
+    conditions: [c1, b1, c2]
+  - name: b2
+    conditions: [c1]
+---
+# untouched
+kind: Agent
+name: a2
+wants: [{name: b1}]
+";
+        let Some(fixed) = fix_contract(contents, &LintConfig::new()).fixed else {
+            panic!("expected a fix");
+        };
+        assert_eq!(
+            fixed,
+            "# agents
+kind: Agent
+name: a1
+provides:
+- name: b1
+  comment: kept
+  conditions:
+  - b1
+  - c2
+- name: b2
+globalConditions:
+- c1
+---
+# untouched
+kind: Agent
+name: a2
+wants: [{name: b1}]
+"
+        );
+        assert!(fix_contract(&fixed, &LintConfig::new()).is_unchanged());
+        assert!(lint_contract("c.yaml", &fixed, &LintConfig::new()).is_empty());
+
+        // the self-condition changes what b1 means, so it is only reported
+        let before = tracker("c.yaml", contents);
+        let after = tracker("c.yaml", &fixed);
+        assert_eq!(
+            messages(&lint(&after, &LintConfig::new()))
+                .into_iter()
+                .filter(|m| m.contains("self-condition"))
+                .collect::<Vec<String>>(),
+            ["c.yaml:5: warning[self-condition]: agent a1 provides b1 with itself as a condition"]
+        );
+        assert!(after.get_root_causes("b1").is_empty());
+        assert_eq!(before.resolve("b1"), after.resolve("b1"));
+        assert!(fix_contract(
+            "kind: Agent
+name: a1
+provides:
+  - name: b1
+    conditions: [b1]
+",
+            &LintConfig::new()
+        )
+        .is_unchanged());
+
+        let commented = "kind: Agent
+name: a1
+provides:
+  - name: b1
+    conditions: [c1] # oops
+  - name: b2
+    conditions: [c1]
+---
+kind: Agent
+name: a2
+provides:
+  - name: b3
+    conditions: [b3, c1]
+  - name: b4
+    conditions: [b3, c1]
+---
+kind: Agent
+name: a3
+provides:
+  - name: b5
+    conditions: [c1]
+  - name: b6
+    conditions: [c1]
+";
+        // a3 is still fixed
+        let fix = fix_contract(commented, &LintConfig::new());
+        assert_eq!(
+            fix.skipped,
+            [
+                "agent a1 has comments that would be lost",
+                "agent a2 would lose the self-condition of b3",
+            ]
+        );
+        assert_eq!(
+            fix.fixed.unwrap(),
+            "kind: Agent
+name: a1
+provides:
+  - name: b1
+    conditions: [c1] # oops
+  - name: b2
+    conditions: [c1]
+---
+kind: Agent
+name: a2
+provides:
+  - name: b3
+    conditions: [b3, c1]
+  - name: b4
+    conditions: [b3, c1]
+---
+kind: Agent
+name: a3
+provides:
+- name: b5
+- name: b6
+globalConditions:
+- c1
+"
+        );
+    }
+}
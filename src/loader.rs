@@ -0,0 +1,465 @@
+//! Loading contracts from YAML text.
+//!
+//! `serde_yaml` does not report where values came from, so the loader scans
+//! the text itself to find each document, the top level `provides`, `wants`
+//! and `instances` lists and the entries in them. Every Agent, SuperAgent,
+//! instance and Behavior that is loaded gets a `Location` pointing at its own
+//! text. When the text uses a layout the scanner does not follow (flow
+//! style lists, for example), a definition falls back to the location of
+//! whatever contains it.
+
+use crate::components::{Agent, Behavior, Item, Location, Position, Span, SuperAgent};
+use serde::de::DeserializeOwned;
+use std::ops::Range;
+
+/// Parses every YAML document in `contents` into an `Item`, recording
+/// `path` and the position of every definition in its provenance.
+pub fn load_items(path: &str, contents: &str) -> Result<Vec<Item>, serde_yaml::Error> {
+    load_documents(path, contents).into_iter().collect()
+}
+
+/// Same as `load_items`, but keeps going after a document fails to parse.
+/// A YAML syntax error ends the list, since nothing after it can be read.
+pub fn load_documents(path: &str, contents: &str) -> Vec<Result<Item, serde_yaml::Error>> {
+    let documents = scan_documents(contents);
+    parse_documents(contents)
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            Ok(match documents.get(i) {
+                Some(lines) if !lines.is_empty() => {
+                    locate_item(item?, &Locator { path, document: i }, lines)
+                }
+                _ => item?,
+            })
+        })
+        .collect()
+}
+
+// Deserializes every document, stopping after a syntax error
+pub(crate) fn parse_documents<T: DeserializeOwned>(
+    contents: &str,
+) -> Vec<Result<T, serde_yaml::Error>> {
+    let mut ret = vec![];
+    let mut last_error = None;
+    for document in serde_yaml::Deserializer::from_str(contents) {
+        match T::deserialize(document) {
+            Ok(value) => ret.push(Ok(value)),
+            Err(e) => {
+                // after a syntax error serde_yaml hands back the same error forever
+                let message = e.to_string();
+                if last_error.as_ref() == Some(&message) {
+                    break;
+                }
+                last_error = Some(message);
+                ret.push(Err(e));
+            }
+        }
+    }
+    ret
+}
+
+// A non-blank, non-comment line with its indentation split off
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+struct Locator<'a> {
+    path: &'a str,
+    document: usize,
+}
+
+impl Locator<'_> {
+    fn location(&self, lines: &[Line]) -> Location {
+        let first = &lines[0];
+        let last = &lines[lines.len() - 1];
+        Location::new(
+            self.path,
+            self.document,
+            Span {
+                start: Position {
+                    line: first.number,
+                    column: first.indent + 1,
+                },
+                end: Position {
+                    line: last.number,
+                    column: last.indent + last.text.len() + 1,
+                },
+            },
+        )
+    }
+}
+
+// Splits the text into documents the same way serde_yaml does: `---` starts
+// a new document, `...` ends one, and text before the first `---` is only a
+// document if there is something in it. Returns the (0-based) range of lines
+// of each document, without the `---` and `...` lines themselves.
+pub(crate) fn document_ranges(contents: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    // first line of the current document, and whether `---` started it
+    let mut current: Option<(usize, bool)> = Some((0, false));
+    let mut has_content = false;
+    let mut line_count = 0;
+    for (i, raw) in contents.lines().enumerate() {
+        line_count = i + 1;
+        let trimmed = raw.trim_end();
+        if trimmed == "---" || trimmed.starts_with("--- ") || trimmed == "..." {
+            if let Some((start, explicit)) = current.take() {
+                if explicit || has_content {
+                    ranges.push(start..i);
+                }
+            }
+            if trimmed != "..." {
+                current = Some((i + 1, true));
+            }
+            has_content = false;
+            continue;
+        }
+        let text = trimmed.trim_start();
+        if !text.is_empty() && !text.starts_with('#') {
+            current.get_or_insert((i, false));
+            has_content = true;
+        }
+    }
+    if let Some((start, explicit)) = current {
+        if explicit || has_content {
+            ranges.push(start..line_count);
+        }
+    }
+    ranges
+}
+
+// The non-blank, non-comment lines of each document
+fn scan_documents(contents: &str) -> Vec<Vec<Line<'_>>> {
+    let raw_lines: Vec<&str> = contents.lines().collect();
+    document_ranges(contents)
+        .into_iter()
+        .map(|range| {
+            raw_lines[range.clone()]
+                .iter()
+                .zip(range)
+                .filter_map(|(raw, i)| {
+                    let trimmed = raw.trim_end();
+                    let text = trimmed.trim_start();
+                    if text.is_empty() || text.starts_with('#') {
+                        return None;
+                    }
+                    Some(Line {
+                        number: i + 1,
+                        indent: trimmed.len() - text.len(),
+                        text,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// The entries of the block sequence under `key` in a mapping whose keys are
+// at `indent`. None if the key is missing or the list is not a block list.
+fn sequence_entries<'a>(
+    lines: &[Line<'a>],
+    indent: usize,
+    key: &str,
+) -> Option<Vec<Vec<Line<'a>>>> {
+    let start = lines.iter().position(|l| {
+        l.indent == indent
+            && l.text
+                .strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix(':'))
+                .is_some_and(|rest| rest.is_empty())
+    })?;
+    let is_dash = |l: &Line| l.text == "-" || l.text.starts_with("- ");
+    let block: Vec<Line> = lines[start + 1..]
+        .iter()
+        .take_while(|l| l.indent > indent || (l.indent == indent && is_dash(l)))
+        .copied()
+        .collect();
+    let dash_indent = block.first().filter(|l| is_dash(l))?.indent;
+    let mut entries: Vec<Vec<Line>> = vec![];
+    for line in block {
+        if line.indent == dash_indent && is_dash(&line) {
+            entries.push(vec![line]);
+        } else if line.indent > dash_indent {
+            entries.last_mut()?.push(line);
+        } else {
+            return None;
+        }
+    }
+    Some(entries)
+}
+
+// The lines of a sequence entry as a mapping, with the `- ` of the first
+// line turned into indentation. Returns the indentation of its keys too.
+fn entry_mapping<'a>(entry: &[Line<'a>]) -> Option<(usize, Vec<Line<'a>>)> {
+    let first = entry[0];
+    let rest = &first.text[1..];
+    let text = rest.trim_start();
+    if text.is_empty() {
+        // the mapping starts on the next line
+        let indent = entry.get(1)?.indent;
+        return Some((indent, entry[1..].to_vec()));
+    }
+    let indent = first.indent + 1 + rest.len() - text.len();
+    let mut ret = vec![Line {
+        number: first.number,
+        indent,
+        text,
+    }];
+    ret.extend_from_slice(&entry[1..]);
+    Some((indent, ret))
+}
+
+fn locate_behaviors(
+    behaviors: &mut [Behavior],
+    locator: &Locator,
+    lines: &[Line],
+    indent: usize,
+    key: &str,
+    fallback: &Location,
+) {
+    match sequence_entries(lines, indent, key) {
+        Some(entries) if entries.len() == behaviors.len() => {
+            for (b, entry) in behaviors.iter_mut().zip(entries) {
+                b.add_location(locator.location(&entry));
+            }
+        }
+        _ => locate_all(behaviors, fallback),
+    }
+}
+
+fn locate_all(behaviors: &mut [Behavior], location: &Location) {
+    for b in behaviors.iter_mut() {
+        b.add_location(location.clone());
+    }
+}
+
+fn locate_agent(agent: Agent, locator: &Locator, lines: &[Line], indent: usize) -> Agent {
+    let location = locator.location(lines);
+    let mut agent = agent.with_location(location.clone());
+    locate_behaviors(
+        agent.get_provides_mut(),
+        locator,
+        lines,
+        indent,
+        "provides",
+        &location,
+    );
+    locate_behaviors(
+        agent.get_wants_mut(),
+        locator,
+        lines,
+        indent,
+        "wants",
+        &location,
+    );
+    agent
+}
+
+fn locate_superagent(
+    superagent: SuperAgent,
+    locator: &Locator,
+    lines: &[Line],
+    indent: usize,
+) -> SuperAgent {
+    let location = locator.location(lines);
+    let mut superagent = superagent.with_location(location.clone());
+    let instances = superagent.get_instances_mut();
+    let entries =
+        sequence_entries(lines, indent, "instances").filter(|e| e.len() == instances.len());
+    for (i, instance) in instances.iter_mut().enumerate() {
+        let mapping = entries
+            .as_ref()
+            .and_then(|e| Some((locator.location(&e[i]), entry_mapping(&e[i])?)));
+        let Some((instance_location, (instance_indent, instance_lines))) = mapping else {
+            instance.add_location(location.clone());
+            locate_all(instance.get_provides_mut(), &location);
+            locate_all(instance.get_wants_mut(), &location);
+            continue;
+        };
+        instance.add_location(instance_location.clone());
+        locate_behaviors(
+            instance.get_provides_mut(),
+            locator,
+            &instance_lines,
+            instance_indent,
+            "provides",
+            &instance_location,
+        );
+        locate_behaviors(
+            instance.get_wants_mut(),
+            locator,
+            &instance_lines,
+            instance_indent,
+            "wants",
+            &instance_location,
+        );
+    }
+    superagent
+}
+
+fn locate_item(item: Item, locator: &Locator, lines: &[Line]) -> Item {
+    let indent = lines[0].indent;
+    match item {
+        Item::Agent(agent) => Item::Agent(locate_agent(agent, locator, lines, indent)),
+        Item::SuperAgent(superagent) => {
+            Item::SuperAgent(locate_superagent(superagent, locator, lines, indent))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(item: &Item) -> Vec<usize> {
+        match item {
+            Item::Agent(a) => a.get_provenance(),
+            Item::SuperAgent(sa) => sa.get_provenance(),
+        }
+        .get_locations()
+        .iter()
+        .map(|l| l.get_line())
+        .collect()
+    }
+
+    fn behavior_lines(behaviors: &[Behavior]) -> Vec<usize> {
+        behaviors
+            .iter()
+            .map(|b| b.get_provenance().get_first().unwrap().get_line())
+            .collect()
+    }
+
+    #[test]
+    fn test_load_agents() {
+        let contents = "# leading comment
+---
+kind: Agent
+name: db
+provides:
+  - name: storage
+    conditions:
+      - disk
+
+  - name: backups
+wants:
+- name: disk
+---
+kind: Agent
+name: web
+wants: [{name: storage}]
+";
+        let items = load_items("contracts/db.yaml", contents).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(lines(&items[0]), vec![3]);
+        assert_eq!(lines(&items[1]), vec![14]);
+
+        let Item::Agent(db) = &items[0] else { panic!() };
+        let location = db.get_provenance().get_first().unwrap();
+        assert_eq!(location.to_string(), "contracts/db.yaml:3");
+        assert_eq!(location.document, 0);
+        assert_eq!(
+            location.span,
+            Span {
+                start: Position { line: 3, column: 1 },
+                end: Position {
+                    line: 12,
+                    column: 13
+                },
+            }
+        );
+        let mut provides: Vec<Behavior> = db.get_all_provides().into_iter().collect();
+        provides.sort();
+        assert_eq!(behavior_lines(&provides), vec![10, 6]);
+        let storage = provides[1].get_provenance().get_first().unwrap();
+        assert_eq!(
+            storage.span,
+            Span {
+                start: Position { line: 6, column: 3 },
+                end: Position {
+                    line: 8,
+                    column: 13
+                },
+            }
+        );
+
+        // flow style lists fall back to the agent's location
+        let Item::Agent(web) = &items[1] else {
+            panic!()
+        };
+        let mut web = web.clone();
+        assert_eq!(behavior_lines(web.get_wants_mut()), vec![14]);
+        assert_eq!(items[1].get_name(), "Agent/web");
+    }
+
+    #[test]
+    fn test_load_superagent() {
+        let contents = "kind: SuperAgent
+name: sa
+agents: [a1, a2]
+instances:
+  - name: i1
+    providesTag: p
+    conditionsTag: c
+    provides:
+      - name: extra
+    wants:
+      - name: w1
+      - name: w2
+  -
+    name: i2
+    providesTag: p
+    conditionsTag: c
+...
+";
+        let items = load_items("sa.yaml", contents).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(lines(&items[0]), vec![1]);
+        let Item::SuperAgent(mut sa) = items[0].clone() else {
+            panic!()
+        };
+        let instances = sa.get_instances_mut();
+        assert_eq!(
+            instances[0]
+                .get_provenance()
+                .get_first()
+                .unwrap()
+                .get_line(),
+            5
+        );
+        assert_eq!(
+            instances[1]
+                .get_provenance()
+                .get_first()
+                .unwrap()
+                .get_line(),
+            13
+        );
+        assert_eq!(behavior_lines(instances[0].get_provides()), vec![9]);
+        assert_eq!(behavior_lines(instances[0].get_wants()), vec![11, 12]);
+    }
+
+    #[test]
+    fn test_document_ranges() {
+        assert_eq!(document_ranges("a: 1\n"), vec![0..1]);
+        assert_eq!(
+            document_ranges("# c\n---\na: 1\n...\n---\nb: 2\n"),
+            vec![2..3, 5..6]
+        );
+        assert_eq!(document_ranges("a: 1\n---\n"), vec![0..1, 2..2]);
+    }
+
+    #[test]
+    fn test_load_error() {
+        assert!(load_items("bad.yaml", "kind: Agent\nname: a\nnope: 1\n").is_err());
+        assert!(load_items("empty.yaml", "---\n").is_err());
+        assert_eq!(load_documents("bad.yaml", "a: b: [").len(), 1);
+
+        let documents = load_documents("mixed.yaml", "kind: Nope\n---\nkind: Agent\nname: a\n");
+        assert!(documents[0].is_err());
+        assert_eq!(lines(documents[1].as_ref().unwrap()), vec![3],);
+    }
+}
@@ -1,12 +1,17 @@
 //! Network diagram generation for force-directed graph visualization.
 //!
 //! Generates graph data (nodes and links) showing promise relationships
-//! between components and behaviors.
-
-use crate::resolve::{Offer, Resolution};
+//! between components and behaviors. Components that come from (nested)
+//! SuperAgents also get nodes for what they are made of, linked with
+//! `contains` links and marked with their `parent`, so that the hierarchy can
+//! be drawn collapsed and expanded on demand.
+
+use crate::components::{Criticality, SuperAgentMember};
+use crate::report::SkippedWant;
+use crate::resolution_graph::{OfferNode, ResolutionGraph, ResolutionId};
 use crate::Tracker;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Type of node in the graph
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,12 +22,14 @@ pub enum NodeType {
 }
 
 /// Type of relationship between nodes
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum LinkType {
     Wants,
     Provides,
     Needs,
+    /// From a SuperAgent component to one of its members
+    Contains,
 }
 
 /// A node in the force-directed graph
@@ -33,6 +40,11 @@ pub struct GraphNode {
     #[serde(rename = "type")]
     pub node_type: NodeType,
     pub satisfied: bool,
+    /// Set on the members of a SuperAgent component to the id of the node
+    /// they are folded into. They take no part in any promise themselves.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
 }
 
 /// A link between nodes in the graph
@@ -43,6 +55,13 @@ pub struct GraphLink {
     #[serde(rename = "type")]
     pub link_type: LinkType,
     pub satisfied: bool,
+    /// Set on `needs` links whose condition loops back into its own resolution
+    #[serde(default)]
+    pub cyclic: bool,
+    /// Set on `wants` links to how badly the component needs the behavior
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub criticality: Option<Criticality>,
 }
 
 /// The complete graph data structure for rendering
@@ -50,6 +69,10 @@ pub struct GraphLink {
 pub struct GraphData {
     pub nodes: Vec<GraphNode>,
     pub links: Vec<GraphLink>,
+    /// Conditional wants that are left out of the graph, and why
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_wants: Vec<SkippedWant>,
 }
 
 impl GraphData {
@@ -57,6 +80,7 @@ impl GraphData {
         Self {
             nodes: Vec::new(),
             links: Vec::new(),
+            skipped_wants: Vec::new(),
         }
     }
 
@@ -70,6 +94,7 @@ struct GraphBuilder {
     nodes: Vec<GraphNode>,
     links: Vec<GraphLink>,
     node_map: HashMap<String, usize>,
+    link_map: HashMap<(String, String, LinkType), usize>,
 }
 
 impl GraphBuilder {
@@ -78,6 +103,7 @@ impl GraphBuilder {
             nodes: Vec::new(),
             links: Vec::new(),
             node_map: HashMap::new(),
+            link_map: HashMap::new(),
         }
     }
 
@@ -93,6 +119,7 @@ impl GraphBuilder {
             label: id.to_string(),
             node_type,
             satisfied: true, // default, will be updated if unsatisfied
+            parent: None,
         });
         self.node_map.insert(id.to_string(), idx);
         idx
@@ -100,31 +127,49 @@ impl GraphBuilder {
 
     /// Check if a link already exists
     fn link_exists(&self, source: &str, target: &str, link_type: LinkType) -> bool {
-        self.links.iter().any(|link| {
-            link.source == source && link.target == target && link.link_type == link_type
-        })
+        self.find_link(source, target, link_type).is_some()
     }
 
-    /// Find a link if it exists and return a mutable reference to its index
+    /// Find a link if it exists and return its index
     fn find_link(&self, source: &str, target: &str, link_type: LinkType) -> Option<usize> {
-        self.links.iter().position(|link| {
-            link.source == source && link.target == target && link.link_type == link_type
-        })
+        self.link_map
+            .get(&(source.to_string(), target.to_string(), link_type))
+            .copied()
     }
 
-    /// Process a resolution recursively to extract all nested relationships
-    fn process_resolution(&mut self, behavior_name: &str, resolution: &Resolution) {
+    /// Add a new link and index it
+    fn push_link(&mut self, link: GraphLink) {
+        self.link_map.insert(
+            (link.source.clone(), link.target.clone(), link.link_type),
+            self.links.len(),
+        );
+        self.links.push(link);
+    }
+
+    /// Process a resolution graph to extract all nested relationships. Each
+    /// node of the graph is only processed once.
+    fn process_resolution(
+        &mut self,
+        graph: &ResolutionGraph,
+        id: ResolutionId,
+        visited: &mut HashSet<ResolutionId>,
+    ) {
+        if !visited.insert(id) {
+            return;
+        }
+        let resolution = graph.get_node(id);
+        let behavior_name = resolution.get_behavior_name();
         let satisfied = resolution.get_satisfying_offers();
         let unsatisfied = resolution.get_unsatisfying_offers();
 
         // Process satisfied offers
         for offer in satisfied {
-            self.process_offer(behavior_name, offer, true);
+            self.process_offer(graph, behavior_name, offer, true, visited);
         }
 
         // Process unsatisfied offers
         for offer in unsatisfied {
-            self.process_offer(behavior_name, offer, false);
+            self.process_offer(graph, behavior_name, offer, false, visited);
         }
 
         // Update behavior node satisfaction status
@@ -138,7 +183,14 @@ impl GraphBuilder {
     }
 
     /// Process an offer (satisfied or unsatisfied)
-    fn process_offer(&mut self, behavior_name: &str, offer: &Offer, is_satisfied: bool) {
+    fn process_offer(
+        &mut self,
+        graph: &ResolutionGraph,
+        behavior_name: &str,
+        offer: &OfferNode,
+        is_satisfied: bool,
+        visited: &mut HashSet<ResolutionId>,
+    ) {
         let provider_name = offer.get_agent_name();
 
         // Ensure provider component node exists
@@ -146,16 +198,19 @@ impl GraphBuilder {
 
         // Create link from behavior to provider (provides relationship)
         if !self.link_exists(behavior_name, provider_name, LinkType::Provides) {
-            self.links.push(GraphLink {
+            self.push_link(GraphLink {
                 source: behavior_name.to_string(),
                 target: provider_name.to_string(),
                 link_type: LinkType::Provides,
                 satisfied: is_satisfied,
+                cyclic: false,
+                criticality: None,
             });
         }
 
-        // Process nested conditions
-        for condition in offer.get_resolved_conditions() {
+        // Process nested conditions, including those of anyOf groups
+        for (_, condition_id) in offer.get_edges() {
+            let condition = graph.get_node(condition_id);
             let condition_behavior_name = condition.get_behavior_name();
 
             // Ensure condition behavior node exists
@@ -164,13 +219,32 @@ impl GraphBuilder {
             // Determine if this condition is satisfied
             let condition_satisfied = !condition.get_satisfying_offers().is_empty();
 
+            // A loop back onto the resolution path only marks the link; the
+            // condition itself is (or was) processed where the loop started
+            if condition.is_cyclic() {
+                match self.find_link(provider_name, condition_behavior_name, LinkType::Needs) {
+                    Some(idx) => self.links[idx].cyclic = true,
+                    None => self.push_link(GraphLink {
+                        source: provider_name.to_string(),
+                        target: condition_behavior_name.to_string(),
+                        link_type: LinkType::Needs,
+                        satisfied: false,
+                        cyclic: true,
+                        criticality: None,
+                    }),
+                }
+                continue;
+            }
+
             // Create link from provider component to condition behavior (needs condition)
             if !self.link_exists(provider_name, condition_behavior_name, LinkType::Needs) {
-                self.links.push(GraphLink {
+                self.push_link(GraphLink {
                     source: provider_name.to_string(),
                     target: condition_behavior_name.to_string(),
                     link_type: LinkType::Needs,
                     satisfied: condition_satisfied,
+                    cyclic: false,
+                    criticality: None,
                 });
             } else {
                 // Update existing link's satisfied status if needed
@@ -182,15 +256,42 @@ impl GraphBuilder {
             }
 
             // Recursively process the condition's resolution
-            self.process_resolution(condition_behavior_name, condition);
+            self.process_resolution(graph, condition_id, visited);
+        }
+    }
+
+    /// Add a node and a `contains` link for every member of the SuperAgent
+    /// component `parent`, all the way down. Member ids are prefixed with
+    /// their parent's, as the same agent can be inside several instances.
+    fn add_members(&mut self, parent: &str, members: &[SuperAgentMember]) {
+        for m in members {
+            let id = format!("{}/{}", parent, m.name);
+            self.node_map.insert(id.clone(), self.nodes.len());
+            self.nodes.push(GraphNode {
+                id: id.clone(),
+                label: m.name.clone(),
+                node_type: NodeType::Component,
+                satisfied: true,
+                parent: Some(parent.to_string()),
+            });
+            self.push_link(GraphLink {
+                source: parent.to_string(),
+                target: id.clone(),
+                link_type: LinkType::Contains,
+                satisfied: true,
+                cyclic: false,
+                criticality: None,
+            });
+            self.add_members(&id, &m.members);
         }
     }
 
     /// Build the final graph data
-    fn build(self) -> GraphData {
+    fn build(self, skipped_wants: Vec<SkippedWant>) -> GraphData {
         GraphData {
             nodes: self.nodes,
             links: self.links,
+            skipped_wants,
         }
     }
 }
@@ -207,63 +308,63 @@ pub fn network_diagram(tracker: &Tracker) -> GraphData {
         return GraphData::new();
     }
 
-    let agent_names = tracker.get_working_agent_names();
-    if agent_names.is_empty() {
+    let report = tracker.resolve_all();
+    if report.agents.is_empty() {
         return GraphData::new();
     }
 
     let mut builder = GraphBuilder::new();
 
     // Process each agent (component)
-    for agent_name in agent_names {
+    for agent in &report.agents {
+        let agent_name = &agent.agent_name;
         // Create component node
         builder.get_or_create_node(agent_name, NodeType::Component);
 
-        // Get what this agent wants
-        let wants = tracker.get_agent_wants(agent_name.clone());
-
-        for want_behavior in wants {
+        for want in &agent.wants {
+            let want_behavior = &want.behavior_name;
             // Create behavior node
-            builder.get_or_create_node(&want_behavior, NodeType::Behavior);
+            builder.get_or_create_node(want_behavior, NodeType::Behavior);
 
             // Create link from component to behavior (wants relationship)
-            // We'll determine if it's satisfied after resolving
-            if !builder.link_exists(agent_name, &want_behavior, LinkType::Wants) {
-                builder.links.push(GraphLink {
+            if !builder.link_exists(agent_name, want_behavior, LinkType::Wants) {
+                builder.push_link(GraphLink {
                     source: agent_name.clone(),
                     target: want_behavior.clone(),
                     link_type: LinkType::Wants,
-                    satisfied: true, // Will be updated after resolution
+                    satisfied: want.is_satisfied(),
+                    cyclic: false,
+                    criticality: Some(want.criticality),
                 });
             }
 
-            // Resolve the behavior to find providers and nested relationships
-            let resolution = tracker.resolve(&want_behavior);
-            let has_satisfied_providers = !resolution.get_satisfying_offers().is_empty();
-
-            // Update the wants link based on whether there are satisfied providers
-            if let Some(idx) = builder.find_link(agent_name, &want_behavior, LinkType::Wants) {
-                builder.links[idx].satisfied = has_satisfied_providers;
-            }
-
             // Update behavior node satisfaction
-            if !has_satisfied_providers {
-                if let Some(&idx) = builder.node_map.get(&want_behavior) {
+            if !want.is_satisfied() {
+                if let Some(&idx) = builder.node_map.get(want_behavior) {
                     builder.nodes[idx].satisfied = false;
                 }
             }
 
-            builder.process_resolution(&want_behavior, &resolution);
+            // Walk the resolution to find providers and nested relationships
+            let resolution = &want.resolution;
+            builder.process_resolution(resolution, resolution.get_root(), &mut HashSet::new());
         }
     }
 
-    builder.build()
+    for agent in &report.agents {
+        builder.add_members(
+            &agent.agent_name,
+            &tracker.get_superagent_members(&agent.agent_name),
+        );
+    }
+
+    builder.build(report.get_skipped_wants().into_iter().cloned().collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::components::{Agent, Behavior};
+    use crate::components::{Agent, Behavior, SuperAgent};
 
     #[test]
     fn test_empty_tracker() {
@@ -338,4 +439,150 @@ mod tests {
         let behavior_node = graph.nodes.iter().find(|n| n.id == "b1").unwrap();
         assert!(!behavior_node.satisfied);
     }
+
+    #[test]
+    fn test_skipped_want() {
+        let mut tracker = Tracker::new();
+        tracker.add_agent(Agent::build("a1").with_wants(vec![
+            Behavior::build("b1").with_conditions(vec![String::from("flag")]),
+            Behavior::build("b2"),
+        ]));
+
+        let graph = network_diagram(&tracker);
+
+        assert!(!graph.nodes.iter().any(|n| n.id == "b1"));
+        assert!(graph.nodes.iter().any(|n| n.id == "b2"));
+        assert_eq!(graph.skipped_wants.len(), 1);
+        assert_eq!(graph.skipped_wants[0].behavior_name, "b1");
+        assert_eq!(
+            graph.skipped_wants[0].get_reason(),
+            "only wanted when flag is provided"
+        );
+    }
+
+    #[test]
+    fn test_superagent_members() {
+        let mut tracker = Tracker::new();
+        tracker.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        tracker.add_agent(Agent::build("a2"));
+        tracker.add_superagent(SuperAgent::new(String::from("inner")).with_agent("a1"));
+        tracker.add_superagent(
+            SuperAgent::new(String::from("outer"))
+                .with_agent("inner")
+                .with_agent("a2"),
+        );
+
+        let graph = network_diagram(&tracker);
+
+        let ids: Vec<(&str, Option<&str>)> = graph
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), n.parent.as_deref()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("outer", None),
+                ("outer/inner", Some("outer")),
+                ("outer/inner/a1", Some("outer/inner")),
+                ("outer/a2", Some("outer")),
+            ]
+        );
+        assert_eq!(
+            graph
+                .links
+                .iter()
+                .filter(|l| l.link_type == LinkType::Contains)
+                .count(),
+            3
+        );
+        assert_eq!(graph.nodes[2].label, "a1");
+    }
+
+    #[test]
+    fn test_want_criticality() {
+        let mut tracker = Tracker::new();
+        tracker.add_agent(Agent::build("a1").with_wants(vec![
+            Behavior::build("b1").with_criticality(Criticality::Optional),
+            Behavior::build("b2"),
+        ]));
+
+        let graph = network_diagram(&tracker);
+
+        let criticality = |target: &str| {
+            graph
+                .links
+                .iter()
+                .find(|l| l.link_type == LinkType::Wants && l.target == target)
+                .unwrap()
+                .criticality
+        };
+        assert_eq!(criticality("b1"), Some(Criticality::Optional));
+        assert_eq!(criticality("b2"), Some(Criticality::Required));
+        let json = serde_json::to_string(&graph).unwrap();
+        assert!(json.contains("\"criticality\":\"optional\""));
+    }
+
+    #[test]
+    fn test_shared_conditions() {
+        let mut tracker = Tracker::new();
+
+        // two providers per level, each needing the next level
+        let depth = 24;
+        let mut top = Agent::new("top".to_string());
+        top.add_want(Behavior::new("b0".to_string()));
+        tracker.add_agent(top);
+        for i in 0..depth {
+            for provider in ["p", "q"] {
+                tracker.add_agent(
+                    Agent::build(&format!("{}{}", provider, i))
+                        .with_provides(vec![Behavior::build(&format!("b{}", i))
+                            .with_conditions(vec![format!("b{}", i + 1)])]),
+                );
+            }
+        }
+
+        let graph = network_diagram(&tracker);
+
+        // top, b0..=b24, p0..p23, q0..q23
+        assert_eq!(graph.nodes.len(), 1 + (depth + 1) + 2 * depth);
+        // wants, then provides and needs for every provider
+        assert_eq!(graph.links.len(), 1 + 4 * depth);
+    }
+
+    #[test]
+    fn test_cyclic_want() {
+        let mut tracker = Tracker::new();
+
+        // a1 wants b1, a2 provides b1 if b2, a3 provides b2 if b1
+        let mut a1 = Agent::new("a1".to_string());
+        a1.add_want(Behavior::new("b1".to_string()));
+        tracker.add_agent(a1);
+        tracker.add_agent(Agent::build("a2").with_provides(vec![
+            Behavior::build("b1").with_conditions(vec!["b2".to_string()]),
+        ]));
+        tracker.add_agent(Agent::build("a3").with_provides(vec![
+            Behavior::build("b2").with_conditions(vec!["b1".to_string()]),
+        ]));
+
+        let graph = network_diagram(&tracker);
+
+        let cyclic_link = graph
+            .links
+            .iter()
+            .find(|l| l.source == "a3" && l.target == "b1")
+            .unwrap();
+        assert_eq!(cyclic_link.link_type, LinkType::Needs);
+        assert!(cyclic_link.cyclic);
+        assert!(!cyclic_link.satisfied);
+
+        let other_link = graph
+            .links
+            .iter()
+            .find(|l| l.source == "a2" && l.target == "b2")
+            .unwrap();
+        assert!(!other_link.cyclic);
+
+        assert!(!graph.nodes.iter().find(|n| n.id == "b1").unwrap().satisfied);
+    }
 }
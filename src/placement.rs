@@ -0,0 +1,175 @@
+//! Locality and placement constraints.
+//!
+//! A want, or a condition of a provide, can require its provider to share
+//! some labels with the agent asking for it (`sameAs: [zone]`) or to have
+//! given labels (`match: {region: eu}`). Before resolving, a `Placement` is
+//! turned into a `LabelFilter` using the labels of the asking agent; the
+//! providers it rejects are recorded as `Rejection`s along with the reason.
+
+use crate::selector::Labels;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where a provider has to be, relative to the agent asking for it
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(deny_unknown_fields)]
+pub struct Placement {
+    /// Labels the provider must have with the same value as the asking agent
+    #[serde(default)]
+    #[serde(rename = "sameAs")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub same_as: Vec<String>,
+    /// Labels the provider must have with exactly these values
+    #[serde(default)]
+    #[serde(rename = "match")]
+    #[serde(skip_serializing_if = "Labels::is_empty")]
+    pub match_labels: Labels,
+}
+
+impl Placement {
+    pub fn is_empty(&self) -> bool {
+        self.same_as.is_empty() && self.match_labels.is_empty()
+    }
+
+    /// The labels a provider needs when `labels` are the asking agent's
+    pub(crate) fn to_filter(&self, labels: &Labels) -> LabelFilter {
+        let mut ret: Vec<(String, Option<String>)> = self
+            .same_as
+            .iter()
+            .map(|k| (k.clone(), labels.get(k).cloned()))
+            .collect();
+        for (k, v) in &self.match_labels {
+            if !ret.iter().any(|(x, _)| x == k) {
+                ret.push((k.clone(), Some(v.clone())));
+            }
+        }
+        ret.sort();
+        LabelFilter(ret)
+    }
+}
+
+/// Label values a provider must have, in key order. A value of None means
+/// the asking agent has no such label to match, so nothing matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct LabelFilter(Vec<(String, Option<String>)>);
+
+impl LabelFilter {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// None if the provider's labels pass, otherwise why they don't
+    pub(crate) fn check(&self, agent_name: &str, labels: &Labels) -> Option<Rejection> {
+        let reasons: Vec<String> = self
+            .0
+            .iter()
+            .filter_map(|(k, wanted)| match (wanted, labels.get(k)) {
+                (None, _) => Some(format!("nothing to match {} against", k)),
+                (Some(wanted), Some(v)) if v == wanted => None,
+                (Some(wanted), Some(v)) => Some(format!("{} is {}, not {}", k, v, wanted)),
+                (Some(wanted), None) => Some(format!("no {} label, wanted {}", k, wanted)),
+            })
+            .collect();
+        if reasons.is_empty() {
+            return None;
+        }
+        Some(Rejection {
+            agent_name: String::from(agent_name),
+            reason: reasons.join(", "),
+        })
+    }
+}
+
+// e.g. `region=eu,zone=eu-west-1`, with `zone=?` for labels the asking
+// agent doesn't have
+impl fmt::Display for LabelFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v.as_deref().unwrap_or("?")))
+            .collect();
+        f.write_str(&parts.join(","))
+    }
+}
+
+/// A provider that was left out because of where it is
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rejection {
+    pub agent_name: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.agent_name, self.reason)
+    }
+}
+
+/// Why a behavior is only provided in the wrong place, e.g. `needs
+/// zone=eu-west: pg (zone is us-east, not eu-west)`
+pub(crate) fn rejection_string(placement: &str, rejections: &[Rejection]) -> String {
+    format!(
+        "needs {}: {}",
+        placement,
+        rejections
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+/// How such a behavior is drawn, e.g. `? (needs zone=eu-west: pg (zone is
+/// us-east, not eu-west))`
+pub(crate) fn rejection_label(placement: &str, rejections: &[Rejection]) -> String {
+    format!("? ({})", rejection_string(placement, rejections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> Labels {
+        pairs
+            .iter()
+            .map(|(k, v)| (String::from(*k), String::from(*v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_filter() {
+        let placement: Placement =
+            serde_yaml::from_str("sameAs: [zone]\nmatch: {region: eu}").unwrap();
+        let filter = placement.to_filter(&labels(&[("zone", "eu-west")]));
+        assert_eq!(filter.to_string(), "region=eu,zone=eu-west");
+        assert_eq!(
+            filter.check("a", &labels(&[("zone", "eu-west"), ("region", "eu")])),
+            None
+        );
+        let rejection = filter.check("b", &labels(&[("zone", "us-east")])).unwrap();
+        assert_eq!(
+            rejection.to_string(),
+            "b (no region label, wanted eu, zone is us-east, not eu-west)"
+        );
+        assert_eq!(
+            rejection_label(&filter.to_string(), &[rejection]),
+            "? (needs region=eu,zone=eu-west: b (no region label, wanted eu, zone is us-east, not eu-west))"
+        );
+
+        // the asking agent has no zone, so nothing can be in the same one
+        let filter = placement.to_filter(&Labels::new());
+        assert_eq!(filter.to_string(), "region=eu,zone=?");
+        assert_eq!(
+            filter
+                .check("a", &labels(&[("zone", "eu-west"), ("region", "eu")]))
+                .unwrap()
+                .reason,
+            "nothing to match zone against"
+        );
+        assert!(Placement::default().to_filter(&Labels::new()).is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+//! Single points of failure.
+//!
+//! A satisfied want can still hang off a single agent somewhere down its
+//! conditions. `redundancy` works out, for every satisfied want, the minimal
+//! sets of agents whose failure would break it (see
+//! `ResolutionGraph::get_cut_sets`), flags the wants that a single agent can
+//! break, and scores every agent by how many failures its wants survive.
+
+use crate::components::Criticality;
+use crate::Tracker;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A satisfied want and what it takes to break it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WantRedundancy {
+    pub agent_name: String,
+    pub behavior_name: String,
+    pub criticality: Criticality,
+    /// See `ResolutionGraph::get_cut_sets`
+    pub cut_sets: Vec<Vec<String>>,
+}
+
+impl WantRedundancy {
+    /// The fewest agents that have to fail to break it
+    pub fn get_redundancy(&self) -> usize {
+        self.cut_sets.first().map_or(0, Vec::len)
+    }
+
+    /// The agents that break it on their own, sorted
+    pub fn get_single_points_of_failure(&self) -> Vec<&str> {
+        self.cut_sets
+            .iter()
+            .filter(|set| set.len() == 1)
+            .map(|set| set[0].as_str())
+            .collect()
+    }
+
+    /// True if a single agent failing is enough to break it
+    pub fn is_single_point_of_failure(&self) -> bool {
+        self.get_redundancy() == 1
+    }
+
+    /// The cut sets as a single line, e.g. `breaks if a1 or a2 + a3 fail`
+    pub fn to_breaks_if_string(&self) -> String {
+        let sets: Vec<String> = self.cut_sets.iter().map(|set| set.join(" + ")).collect();
+        format!("breaks if {} fail", sets.join(" or "))
+    }
+}
+
+impl std::fmt::Display for WantRedundancy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} wants {}: {}",
+            self.agent_name,
+            self.behavior_name,
+            self.to_breaks_if_string()
+        )
+    }
+}
+
+/// How exposed a single agent is, and how much depends on it alone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentRedundancy {
+    pub agent_name: String,
+    /// The fewest agent failures that break one of its satisfied wants, or
+    /// None if it has none
+    pub score: Option<usize>,
+    /// How many wants of other agents break if it alone fails
+    pub single_point_for: usize,
+}
+
+impl std::fmt::Display for AgentRedundancy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: ", self.agent_name)?;
+        match self.score {
+            Some(score) => write!(f, "redundancy {}", score)?,
+            None => write!(f, "no satisfied wants")?,
+        }
+        if self.single_point_for > 0 {
+            write!(
+                f,
+                ", single point of failure for {} want{}",
+                self.single_point_for,
+                if self.single_point_for == 1 { "" } else { "s" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Every satisfied want (by agent, then behavior name) and every working
+/// agent (sorted by name)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedundancyReport {
+    pub wants: Vec<WantRedundancy>,
+    pub agents: Vec<AgentRedundancy>,
+}
+
+impl RedundancyReport {
+    /// The wants that a single agent failing is enough to break
+    pub fn get_single_points_of_failure(&self) -> Vec<&WantRedundancy> {
+        self.wants
+            .iter()
+            .filter(|w| w.is_single_point_of_failure())
+            .collect()
+    }
+
+    pub fn get_agent(&self, agent_name: &str) -> Option<&AgentRedundancy> {
+        self.agents.iter().find(|a| a.agent_name == agent_name)
+    }
+}
+
+/// Works out the cut sets of every satisfied want of every working agent,
+/// and from them a score for every agent. Wants that are not satisfied
+/// already are left out.
+pub fn redundancy(tracker: &Tracker) -> RedundancyReport {
+    let mut ret = RedundancyReport::default();
+    let mut agents: BTreeMap<String, AgentRedundancy> = BTreeMap::new();
+    for agent in tracker.resolve_all().agents {
+        let mut score = None;
+        for want in agent.wants.iter().filter(|w| w.is_satisfied()) {
+            let want = WantRedundancy {
+                agent_name: agent.agent_name.clone(),
+                behavior_name: want.behavior_name.clone(),
+                criticality: want.criticality,
+                cut_sets: want.resolution.get_cut_sets(),
+            };
+            let redundancy = want.get_redundancy();
+            score = Some(score.map_or(redundancy, |s: usize| s.min(redundancy)));
+            ret.wants.push(want);
+        }
+        agents.insert(
+            agent.agent_name.clone(),
+            AgentRedundancy {
+                agent_name: agent.agent_name,
+                score,
+                single_point_for: 0,
+            },
+        );
+    }
+    for want in &ret.wants {
+        for name in want.get_single_points_of_failure() {
+            if name == want.agent_name {
+                continue;
+            }
+            if let Some(a) = agents.get_mut(name) {
+                a.single_point_for += 1;
+            }
+        }
+    }
+    ret.agents = agents.into_values().collect();
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Agent, Behavior};
+
+    #[test]
+    fn test_redundancy() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("db").with_provides(vec![Behavior::build("storage")]));
+        t.add_agent(Agent::build("api1").with_provides(vec![
+            Behavior::build("http").with_conditions(vec![String::from("storage")]),
+        ]));
+        t.add_agent(Agent::build("api2").with_provides(vec![
+            Behavior::build("http").with_conditions(vec![String::from("storage")]),
+        ]));
+        t.add_agent(Agent::build("cdn1").with_provides(vec![Behavior::build("static")]));
+        t.add_agent(Agent::build("cdn2").with_provides(vec![Behavior::build("static")]));
+        t.add_agent(Agent::build("web").with_wants(vec![
+            Behavior::build("http"),
+            Behavior::build("static"),
+            Behavior::build("missing"),
+        ]));
+
+        let report = redundancy(&t);
+        assert_eq!(
+            report
+                .wants
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<String>>(),
+            [
+                "web wants http: breaks if db or api1 + api2 fail",
+                "web wants static: breaks if cdn1 + cdn2 fail",
+            ]
+        );
+        assert_eq!(report.get_single_points_of_failure().len(), 1);
+        assert_eq!(report.wants[0].get_single_points_of_failure(), vec!["db"]);
+        assert_eq!(report.get_agent("web").unwrap().score, Some(1));
+        assert_eq!(report.get_agent("db").unwrap().single_point_for, 1);
+        assert_eq!(report.get_agent("api1").unwrap().single_point_for, 0);
+        assert_eq!(
+            report
+                .agents
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>(),
+            [
+                "api1: no satisfied wants",
+                "api2: no satisfied wants",
+                "cdn1: no satisfied wants",
+                "cdn2: no satisfied wants",
+                "db: no satisfied wants, single point of failure for 1 want",
+                "web: redundancy 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Whole-network satisfaction reports.
+//!
+//! A `SatisfactionReport` resolves every want of every working agent once,
+//! so that every front end counts satisfied and unsatisfied wants the same
+//! way. Resolutions are kept as `ResolutionGraph`s so that large networks
+//! with a lot of shared conditions stay small.
+
+use crate::allocation::Allocation;
+use crate::components::Criticality;
+use crate::resolution_graph::ResolutionGraph;
+use serde::{Deserialize, Serialize};
+
+/// How a single want resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WantStatus {
+    /// At least one provider can deliver it
+    Satisfied,
+    /// Something provides it, but none of the providers have their conditions met
+    Unsatisfied,
+    /// Nothing provides it at all
+    NoProvider,
+    /// It is provided, but only at versions that don't meet the requirement
+    VersionMismatch,
+    /// It is provided, but only by providers in the wrong place (e.g.
+    /// another zone)
+    PlacementMismatch,
+}
+
+/// A want of an agent and how it resolved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WantReport {
+    pub behavior_name: String,
+    pub status: WantStatus,
+    /// How badly the agent needs it
+    #[serde(default)]
+    pub criticality: Criticality,
+    pub resolution: ResolutionGraph,
+    /// See `ResolutionGraph::get_root_causes`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub root_causes: Vec<Vec<String>>,
+}
+
+impl WantReport {
+    pub fn new(resolution: ResolutionGraph) -> WantReport {
+        let root = resolution.get_root_node();
+        let status = if root.is_satisfied() {
+            WantStatus::Satisfied
+        } else if root.is_version_mismatch() {
+            WantStatus::VersionMismatch
+        } else if root.is_placement_mismatch() {
+            WantStatus::PlacementMismatch
+        } else if !root.has_offers() && !root.is_cyclic() {
+            WantStatus::NoProvider
+        } else {
+            WantStatus::Unsatisfied
+        };
+        WantReport {
+            behavior_name: String::from(root.get_behavior_name()),
+            status,
+            criticality: Criticality::Required,
+            root_causes: resolution.get_root_causes(),
+            resolution,
+        }
+    }
+
+    pub fn with_criticality(mut self, criticality: Criticality) -> WantReport {
+        self.criticality = criticality;
+        self
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.status == WantStatus::Satisfied
+    }
+
+    /// The version it was wanted at, if any
+    pub fn get_requirement(&self) -> Option<&str> {
+        self.resolution.get_root_node().get_requirement()
+    }
+
+    pub fn get_placement(&self) -> Option<&str> {
+        self.resolution.get_root_node().get_placement()
+    }
+}
+
+/// A conditional want that is left out because its conditions are not
+/// provided anywhere in the network, or its own conditions are not provided
+/// by the agent itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedWant {
+    pub agent_name: String,
+    pub behavior_name: String,
+    /// For each alternative set of the want's conditions (see
+    /// `Behavior::get_alternatives`), the ones that nothing provides, sorted.
+    /// Providing all of any one of them is enough.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inactive_conditions: Vec<Vec<String>>,
+    /// The want's own conditions that the agent doesn't provide, sorted
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inactive_own_conditions: Vec<String>,
+}
+
+impl SkippedWant {
+    /// Why the want is skipped, e.g. `only wanted when b2 or b3 is provided`
+    /// or `only wanted when a1 provides b1`
+    pub fn get_reason(&self) -> String {
+        let mut reasons = vec![];
+        if !self.inactive_own_conditions.is_empty() {
+            reasons.push(format!(
+                "{} provides {}",
+                self.agent_name,
+                self.inactive_own_conditions.join(" and ")
+            ));
+        }
+        if !self.inactive_conditions.is_empty() {
+            let several = self.inactive_conditions.len() > 1;
+            let alternatives: Vec<String> = self
+                .inactive_conditions
+                .iter()
+                .map(|a| match a.len() {
+                    1 => a[0].clone(),
+                    _ if several => format!("({})", a.join(" and ")),
+                    _ => a.join(" and "),
+                })
+                .collect();
+            reasons.push(format!(
+                "{} {} provided",
+                alternatives.join(" or "),
+                if !several && self.inactive_conditions[0].len() > 1 {
+                    "are"
+                } else {
+                    "is"
+                }
+            ));
+        }
+        format!("only wanted when {}", reasons.join(", and when "))
+    }
+}
+
+/// All of the wants of a single agent, sorted by behavior name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentReport {
+    pub agent_name: String,
+    pub wants: Vec<WantReport>,
+    /// Conditional wants that don't apply, sorted by behavior name. They are
+    /// not counted in the totals.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedWant>,
+}
+
+/// Number of wants in each `WantStatus`, and of unmet wants at each
+/// `Criticality`. A behavior wanted by several agents is counted once per
+/// agent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportTotals {
+    pub wants: usize,
+    pub satisfied: usize,
+    pub unsatisfied: usize,
+    pub no_provider: usize,
+    #[serde(default)]
+    pub version_mismatch: usize,
+    #[serde(default)]
+    pub placement_mismatch: usize,
+    #[serde(default)]
+    pub unmet_required: usize,
+    #[serde(default)]
+    pub unmet_degraded: usize,
+    #[serde(default)]
+    pub unmet_optional: usize,
+}
+
+impl ReportTotals {
+    fn count(&mut self, want: &WantReport) {
+        self.wants += 1;
+        match want.status {
+            WantStatus::Satisfied => {
+                self.satisfied += 1;
+                return;
+            }
+            WantStatus::Unsatisfied => self.unsatisfied += 1,
+            WantStatus::NoProvider => self.no_provider += 1,
+            WantStatus::VersionMismatch => self.version_mismatch += 1,
+            WantStatus::PlacementMismatch => self.placement_mismatch += 1,
+        }
+        match want.criticality {
+            Criticality::Required => self.unmet_required += 1,
+            Criticality::Degraded => self.unmet_degraded += 1,
+            Criticality::Optional => self.unmet_optional += 1,
+        }
+    }
+
+    /// True if any agent is missing something it can't work without
+    pub fn has_unmet_required(&self) -> bool {
+        self.unmet_required > 0
+    }
+
+    /// One line summary, e.g. `5 wants: 3 satisfied, 1 unsatisfied, 1 with
+    /// no provider`. Version and placement mismatches are only mentioned if
+    /// there are any.
+    pub fn to_summary_string(&self) -> String {
+        let mut ret = format!(
+            "{} wants: {} satisfied, {} unsatisfied, {} with no provider",
+            self.wants, self.satisfied, self.unsatisfied, self.no_provider
+        );
+        if self.version_mismatch > 0 {
+            ret.push_str(&format!(
+                ", {} with a version mismatch",
+                self.version_mismatch
+            ));
+        }
+        if self.placement_mismatch > 0 {
+            ret.push_str(&format!(
+                ", {} with a placement mismatch",
+                self.placement_mismatch
+            ));
+        }
+        ret
+    }
+
+    /// How the unmet wants break down by criticality, e.g. `unmet: 1
+    /// required, 2 degraded, 0 optional`. None if every want is met.
+    pub fn to_unmet_string(&self) -> Option<String> {
+        if self.satisfied == self.wants {
+            return None;
+        }
+        Some(format!(
+            "unmet: {} required, {} degraded, {} optional",
+            self.unmet_required, self.unmet_degraded, self.unmet_optional
+        ))
+    }
+}
+
+/// Every working agent (sorted by name) with its resolved wants, plus totals
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SatisfactionReport {
+    pub agents: Vec<AgentReport>,
+    pub totals: ReportTotals,
+    /// Only there if capacity was checked, see `Tracker::allocate`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allocation: Option<Allocation>,
+}
+
+impl SatisfactionReport {
+    pub fn new() -> SatisfactionReport {
+        SatisfactionReport::default()
+    }
+
+    pub fn with_allocation(mut self, allocation: Allocation) -> SatisfactionReport {
+        self.allocation = Some(allocation);
+        self
+    }
+
+    /// True if capacity was checked and some provider can't take its demand
+    pub fn is_oversubscribed(&self) -> bool {
+        self.allocation
+            .as_ref()
+            .is_some_and(|a| a.is_oversubscribed())
+    }
+
+    pub fn add_agent(&mut self, agent: AgentReport) {
+        for w in &agent.wants {
+            self.totals.count(w);
+        }
+        self.agents.push(agent);
+    }
+
+    pub fn get_agent(&self, agent_name: &str) -> Option<&AgentReport> {
+        self.agents.iter().find(|a| a.agent_name == agent_name)
+    }
+
+    /// Every skipped want of every agent, by agent then behavior name
+    pub fn get_skipped_wants(&self) -> Vec<&SkippedWant> {
+        self.agents.iter().flat_map(|a| a.skipped.iter()).collect()
+    }
+
+    /// Every wanted behavior once per version requirement and placement,
+    /// sorted by name, at the most critical level any agent wants it at
+    pub fn get_distinct_wants(&self) -> Vec<&WantReport> {
+        let mut ret: Vec<&WantReport> = self.agents.iter().flat_map(|a| a.wants.iter()).collect();
+        ret.sort_by(|a, b| {
+            a.behavior_name
+                .cmp(&b.behavior_name)
+                .then(a.get_requirement().cmp(&b.get_requirement()))
+                .then(a.get_placement().cmp(&b.get_placement()))
+                .then(a.criticality.cmp(&b.criticality))
+        });
+        ret.dedup_by(|a, b| {
+            a.behavior_name == b.behavior_name
+                && a.get_requirement() == b.get_requirement()
+                && a.get_placement() == b.get_placement()
+        });
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Agent, Behavior};
+    use crate::resolve::{Offer, Resolution};
+    use crate::Tracker;
+
+    fn status(r: Resolution) -> WantStatus {
+        WantReport::new(ResolutionGraph::from(&r)).status
+    }
+
+    #[test]
+    fn test_want_status() {
+        let r = Resolution::new("b1");
+        assert_eq!(status(r), WantStatus::NoProvider);
+
+        let r = Resolution::new("b1").add_satisfying_offer(Offer::new("a1"));
+        assert_eq!(status(r), WantStatus::Satisfied);
+
+        let r = Resolution::new("b1")
+            .add_unsatisfying_offer(Offer::new_conditional("a1", vec![Resolution::new("c1")]));
+        assert_eq!(status(r), WantStatus::Unsatisfied);
+
+        let r = Resolution::new_cyclic("b1", vec![String::from("b1"), String::from("b1")]);
+        assert_eq!(status(r), WantStatus::Unsatisfied);
+    }
+
+    #[test]
+    fn test_resolve_all() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("p1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("p2").with_provides(vec![
+            Behavior::build("b2").with_conditions(vec![String::from("b3")]),
+        ]));
+        t.add_agent(Agent::build("w2").with_wants(vec![
+            Behavior::build("b2"),
+            Behavior::build("b1"),
+            Behavior::build("b4"),
+        ]));
+        t.add_agent(Agent::build("w1").with_wants(vec![Behavior::build("b1")]));
+
+        let report = t.resolve_all();
+        assert_eq!(
+            report
+                .agents
+                .iter()
+                .map(|a| a.agent_name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["p1", "p2", "w1", "w2"]
+        );
+        assert!(report.get_agent("p1").unwrap().wants.is_empty());
+        let w2 = report.get_agent("w2").unwrap();
+        assert_eq!(
+            w2.wants
+                .iter()
+                .map(|w| (w.behavior_name.as_str(), w.status))
+                .collect::<Vec<(&str, WantStatus)>>(),
+            vec![
+                ("b1", WantStatus::Satisfied),
+                ("b2", WantStatus::Unsatisfied),
+                ("b4", WantStatus::NoProvider),
+            ]
+        );
+        assert_eq!(w2.wants[1].resolution, t.resolve_graph("b2"));
+        assert_eq!(w2.wants[1].root_causes, vec![vec!["b3"]]);
+        assert!(w2.wants[0].root_causes.is_empty());
+        assert_eq!(w2.wants[1].resolution.to_resolution(), t.resolve("b2"));
+        assert_eq!(
+            report.totals,
+            ReportTotals {
+                wants: 4,
+                satisfied: 2,
+                unsatisfied: 1,
+                no_provider: 1,
+                version_mismatch: 0,
+                placement_mismatch: 0,
+                unmet_required: 2,
+                unmet_degraded: 0,
+                unmet_optional: 0,
+            }
+        );
+        assert_eq!(
+            report.totals.to_summary_string(),
+            "4 wants: 2 satisfied, 1 unsatisfied, 1 with no provider"
+        );
+        assert_eq!(
+            report
+                .get_distinct_wants()
+                .iter()
+                .map(|w| w.behavior_name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["b1", "b2", "b4"]
+        );
+        assert!(report.get_agent("nope").is_none());
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("\"status\":\"no_provider\""));
+        let back: SatisfactionReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, report);
+    }
+
+    #[test]
+    fn test_criticality() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("p1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("w1").with_wants(vec![
+            Behavior::build("b1").with_criticality(Criticality::Optional),
+            Behavior::build("b2").with_criticality(Criticality::Degraded),
+            Behavior::build("b3").with_criticality(Criticality::Optional),
+        ]));
+        t.add_agent(Agent::build("w2").with_wants(vec![Behavior::build("b3")]));
+
+        let report = t.resolve_all();
+        let w1 = report.get_agent("w1").unwrap();
+        assert_eq!(
+            w1.wants
+                .iter()
+                .map(|w| w.criticality)
+                .collect::<Vec<Criticality>>(),
+            vec![
+                Criticality::Optional,
+                Criticality::Degraded,
+                Criticality::Optional
+            ]
+        );
+        assert_eq!(
+            report.totals.to_unmet_string(),
+            Some(String::from("unmet: 1 required, 1 degraded, 1 optional"))
+        );
+        assert!(report.totals.has_unmet_required());
+        // b3 is required by w2
+        assert_eq!(
+            report
+                .get_distinct_wants()
+                .iter()
+                .map(|w| (w.behavior_name.as_str(), w.criticality))
+                .collect::<Vec<(&str, Criticality)>>(),
+            vec![
+                ("b1", Criticality::Optional),
+                ("b2", Criticality::Degraded),
+                ("b3", Criticality::Required),
+            ]
+        );
+
+        t.remove_agent("w2");
+        let report = t.resolve_all();
+        assert!(!report.totals.has_unmet_required());
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("\"criticality\":\"degraded\""));
+    }
+}
@@ -0,0 +1,1516 @@
+//! Graph-shaped resolution results.
+//!
+//! A `ResolutionGraph` stores every sub-resolution once and has offers refer
+//! to their conditions by id. Behaviors that many providers depend on are
+//! therefore resolved and rendered once, instead of being copied into every
+//! branch of a nested `Resolution` tree.
+
+use crate::components::Location;
+use crate::interner::Symbol;
+use crate::placement::{rejection_label, rejection_string, Rejection};
+use crate::resolve::{Offer, Resolution};
+use crate::similarity::unmatched_label;
+use crate::version::{mismatch_label, mismatch_string, VersionMismatch};
+use colored::{ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+/// Index of a node inside a `ResolutionGraph`
+pub type ResolutionId = usize;
+
+/// A single resolved behavior. Conditions of its offers point at other nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResolutionNode {
+    behavior_name: String,
+    satisfying_offers: Vec<OfferNode>,
+    unsatisfying_offers: Vec<OfferNode>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cycle: Vec<String>,
+    // Provided behaviors this one was probably meant to be. Only set when
+    // nothing provides it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<String>,
+    // The version the behavior was wanted at, if any
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requirement: Option<String>,
+    // Providers left out because their version doesn't meet the requirement
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mismatches: Vec<VersionMismatch>,
+    // The labels the provider had to have, if it was wanted somewhere in
+    // particular
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    placement: Option<String>,
+    // Providers left out because of where they are
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rejections: Vec<Rejection>,
+}
+
+impl ResolutionNode {
+    pub(crate) fn new(behavior_name: &str) -> ResolutionNode {
+        ResolutionNode {
+            behavior_name: String::from(behavior_name),
+            satisfying_offers: vec![],
+            unsatisfying_offers: vec![],
+            cycle: vec![],
+            suggestions: vec![],
+            requirement: None,
+            mismatches: vec![],
+            placement: None,
+            rejections: vec![],
+        }
+    }
+
+    pub(crate) fn new_cyclic(behavior_name: &str, cycle: Vec<String>) -> ResolutionNode {
+        ResolutionNode {
+            cycle,
+            ..ResolutionNode::new(behavior_name)
+        }
+    }
+
+    // The same node with the conditions of its offers mapped to other ids
+    fn renumbered(&self, id: impl Fn(ResolutionId) -> ResolutionId) -> ResolutionNode {
+        let renumber_offer = |o: &OfferNode| OfferNode {
+            agent_name: o.agent_name.clone(),
+            conditions: o.conditions.iter().map(|c| id(*c)).collect(),
+            any_of: o
+                .any_of
+                .iter()
+                .map(|g| g.iter().map(|c| id(*c)).collect())
+                .collect(),
+            location: o.location.clone(),
+        };
+        ResolutionNode {
+            behavior_name: self.behavior_name.clone(),
+            satisfying_offers: self.satisfying_offers.iter().map(renumber_offer).collect(),
+            unsatisfying_offers: self
+                .unsatisfying_offers
+                .iter()
+                .map(renumber_offer)
+                .collect(),
+            cycle: self.cycle.clone(),
+            suggestions: self.suggestions.clone(),
+            requirement: self.requirement.clone(),
+            mismatches: self.mismatches.clone(),
+            placement: self.placement.clone(),
+            rejections: self.rejections.clone(),
+        }
+    }
+
+    pub(crate) fn with_suggestions(mut self, suggestions: Vec<String>) -> ResolutionNode {
+        self.suggestions = suggestions;
+        self
+    }
+
+    pub(crate) fn with_requirement(mut self, requirement: Option<String>) -> ResolutionNode {
+        self.requirement = requirement;
+        self
+    }
+
+    pub(crate) fn add_mismatch(&mut self, mismatch: VersionMismatch) {
+        self.mismatches.push(mismatch);
+    }
+
+    pub(crate) fn with_placement(mut self, placement: Option<String>) -> ResolutionNode {
+        self.placement = placement;
+        self
+    }
+
+    pub(crate) fn add_rejection(&mut self, rejection: Rejection) {
+        self.rejections.push(rejection);
+    }
+
+    pub(crate) fn add_satisfying_offer(&mut self, offer: OfferNode) {
+        self.satisfying_offers.push(offer);
+    }
+
+    pub(crate) fn add_unsatisfying_offer(&mut self, offer: OfferNode) {
+        self.unsatisfying_offers.push(offer);
+    }
+
+    /// Returns the behavior name
+    pub fn get_behavior_name(&self) -> &str {
+        &self.behavior_name
+    }
+
+    /// Returns a reference to the satisfying offers
+    pub fn get_satisfying_offers(&self) -> &[OfferNode] {
+        &self.satisfying_offers
+    }
+
+    /// Returns a reference to the unsatisfying offers
+    pub fn get_unsatisfying_offers(&self) -> &[OfferNode] {
+        &self.unsatisfying_offers
+    }
+
+    /// Returns the loop path, empty if not cyclic
+    pub fn get_cycle(&self) -> &[String] {
+        &self.cycle
+    }
+
+    pub fn is_cyclic(&self) -> bool {
+        !self.cycle.is_empty()
+    }
+
+    /// Returns the closest provided behavior names, best first. Empty unless
+    /// nothing provides this behavior.
+    pub fn get_suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+
+    /// Returns `did you mean: a, b` if there are suggestions
+    pub fn to_suggestion_string(&self) -> Option<String> {
+        if self.suggestions.is_empty() {
+            return None;
+        }
+        Some(format!("did you mean: {}", self.suggestions.join(", ")))
+    }
+
+    /// Returns the version requirement, if the behavior was wanted at one
+    pub fn get_requirement(&self) -> Option<&str> {
+        self.requirement.as_deref()
+    }
+
+    /// Returns the providers left out because of their version
+    pub fn get_mismatches(&self) -> &[VersionMismatch] {
+        &self.mismatches
+    }
+
+    /// True if the behavior is provided, but only at versions that don't
+    /// meet the requirement
+    pub fn is_version_mismatch(&self) -> bool {
+        !self.has_offers() && !self.mismatches.is_empty()
+    }
+
+    /// Returns `needs ^2: a has 1.4.0` if only the version is wrong
+    pub fn to_mismatch_string(&self) -> Option<String> {
+        match &self.requirement {
+            Some(requirement) if self.is_version_mismatch() => {
+                Some(mismatch_string(requirement, &self.mismatches))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the labels the provider had to have, e.g. `zone=eu-west`, if
+    /// the behavior was wanted somewhere in particular
+    pub fn get_placement(&self) -> Option<&str> {
+        self.placement.as_deref()
+    }
+
+    /// Returns the providers left out because of where they are
+    pub fn get_rejections(&self) -> &[Rejection] {
+        &self.rejections
+    }
+
+    /// True if the behavior is provided at the right version, but only in
+    /// places that don't meet the placement
+    pub fn is_placement_mismatch(&self) -> bool {
+        !self.has_offers() && self.mismatches.is_empty() && !self.rejections.is_empty()
+    }
+
+    /// Returns `needs zone=eu-west: a (zone is us-east, not eu-west)` if only
+    /// the placement is wrong
+    pub fn to_rejection_string(&self) -> Option<String> {
+        match &self.placement {
+            Some(placement) if self.is_placement_mismatch() => {
+                Some(rejection_string(placement, &self.rejections))
+            }
+            _ => None,
+        }
+    }
+
+    /// What a behavior without offers is drawn with: `? (needs ^2: a has
+    /// 1.4.0)` if the version is wrong, `? (needs zone=eu-west: a (...))` if
+    /// the placement is, `?` or `? (did you mean: a, b)` otherwise
+    pub fn get_unmatched_label(&self) -> String {
+        match (&self.requirement, &self.placement) {
+            (Some(requirement), _) if !self.mismatches.is_empty() => {
+                mismatch_label(requirement, &self.mismatches)
+            }
+            (_, Some(placement)) if !self.rejections.is_empty() => {
+                rejection_label(placement, &self.rejections)
+            }
+            _ => unmatched_label(&self.suggestions),
+        }
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        !self.satisfying_offers.is_empty()
+    }
+
+    pub fn has_offers(&self) -> bool {
+        !self.satisfying_offers.is_empty() || !self.unsatisfying_offers.is_empty()
+    }
+}
+
+/// How required conditions are drawn
+pub(crate) const CONDITION_ARROW: &str = "&->";
+
+/// How the members of an `anyOf` group (counting from 0) are drawn:
+/// `1->`, `2->`, ...
+pub(crate) fn any_of_arrow(group: usize) -> String {
+    format!("{}->", group + 1)
+}
+
+/// An agent's offer of a behavior, with its conditions given as node ids
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OfferNode {
+    agent_name: String,
+    conditions: Vec<ResolutionId>,
+    // One of these groups must be satisfied as well as every condition
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    any_of: Vec<Vec<ResolutionId>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+}
+
+impl OfferNode {
+    pub(crate) fn new(agent_name: &str, conditions: Vec<ResolutionId>) -> OfferNode {
+        OfferNode {
+            agent_name: String::from(agent_name),
+            conditions,
+            any_of: vec![],
+            location: None,
+        }
+    }
+
+    pub(crate) fn with_any_of(mut self, any_of: Vec<Vec<ResolutionId>>) -> OfferNode {
+        self.any_of = any_of;
+        self
+    }
+
+    pub(crate) fn with_location(mut self, location: Option<Location>) -> OfferNode {
+        self.location = location;
+        self
+    }
+
+    /// Returns the agent name
+    pub fn get_agent_name(&self) -> &str {
+        &self.agent_name
+    }
+
+    /// Returns the ids of the resolved conditions
+    pub fn get_conditions(&self) -> &[ResolutionId] {
+        &self.conditions
+    }
+
+    /// Returns the ids of the resolved conditions of each `anyOf` group
+    pub fn get_any_of(&self) -> &[Vec<ResolutionId>] {
+        &self.any_of
+    }
+
+    pub fn is_unconditional(&self) -> bool {
+        self.conditions.is_empty() && self.any_of.is_empty()
+    }
+
+    /// Every condition along with the arrow it is drawn with: `&->` for the
+    /// required ones, then `1->`, `2->`, ... for each `anyOf` group
+    pub fn get_edges(&self) -> Vec<(String, ResolutionId)> {
+        let mut ret: Vec<(String, ResolutionId)> = self
+            .conditions
+            .iter()
+            .map(|c| (String::from(CONDITION_ARROW), *c))
+            .collect();
+        for (i, group) in self.any_of.iter().enumerate() {
+            ret.extend(group.iter().map(|c| (any_of_arrow(i), *c)));
+        }
+        ret
+    }
+
+    // Every condition is satisfied, and so is every condition of at least one
+    // anyOf group
+    pub(crate) fn is_met(&self, is_satisfied: impl Fn(ResolutionId) -> bool) -> bool {
+        self.conditions.iter().all(|c| is_satisfied(*c))
+            && (self.any_of.is_empty()
+                || self
+                    .any_of
+                    .iter()
+                    .any(|g| g.iter().all(|c| is_satisfied(*c))))
+    }
+
+    /// Returns where the offered behavior was defined, if known
+    pub fn get_location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// Returns the agent name followed by its location, e.g. `db (contracts/db.yaml:14)`
+    pub fn get_label(&self) -> String {
+        match &self.location {
+            Some(location) => format!("{} ({})", self.agent_name, location),
+            None => self.agent_name.clone(),
+        }
+    }
+}
+
+/// The provider picked for one behavior of an `Assignment`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignmentStep {
+    pub behavior_name: String,
+    pub agent_name: String,
+}
+
+/// One complete way of satisfying a resolution: a provider for the behavior,
+/// and one for every condition (and anyOf group member) that provider needs,
+/// all the way down. Steps are in the order they are reached, depth first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub steps: Vec<AssignmentStep>,
+}
+
+impl Assignment {
+    /// The agents it uses, sorted
+    pub fn get_agent_names(&self) -> Vec<&str> {
+        let mut ret: Vec<&str> = self.steps.iter().map(|s| s.agent_name.as_str()).collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+}
+
+impl std::fmt::Display for Assignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .map(|s| format!("{} <- {}", s.behavior_name, s.agent_name))
+            .collect();
+        f.write_str(&steps.join(", "))
+    }
+}
+
+/// Some of the assignments of a resolution, see
+/// `ResolutionGraph::get_assignments`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignmentPage {
+    /// How many assignments come before these ones
+    pub offset: usize,
+    pub assignments: Vec<Assignment>,
+    /// True if there are more after these ones
+    pub has_more: bool,
+}
+
+/// The resolution of one behavior as a graph of shared nodes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolutionGraph {
+    root: ResolutionId,
+    nodes: Vec<ResolutionNode>,
+}
+
+impl ResolutionGraph {
+    pub fn get_root(&self) -> ResolutionId {
+        self.root
+    }
+
+    pub fn get_root_node(&self) -> &ResolutionNode {
+        &self.nodes[self.root]
+    }
+
+    pub fn get_node(&self, id: ResolutionId) -> &ResolutionNode {
+        &self.nodes[id]
+    }
+
+    pub fn get_nodes(&self) -> &[ResolutionNode] {
+        &self.nodes
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.get_root_node().is_satisfied()
+    }
+
+    /// Returns true if every condition of the offer is satisfied, along with
+    /// every condition of one of its `anyOf` groups
+    pub fn is_offer_satisfied(&self, offer: &OfferNode) -> bool {
+        offer.is_met(|c| self.nodes[c].is_satisfied())
+    }
+
+    /// Returns the minimal sets of missing leaf behaviors (behaviors with no
+    /// provider at all) that block this resolution: providing every behavior
+    /// of any one set would satisfy it. Each set is sorted and the sets are
+    /// ordered smallest first. A satisfied resolution has no root causes; an
+    /// unsatisfied one without any can only be fixed by breaking a dependency
+    /// cycle.
+    pub fn get_root_causes(&self) -> Vec<Vec<String>> {
+        if self.is_satisfied() {
+            return vec![];
+        }
+        self.root_cause_sets(self.root, &mut HashMap::new())
+            .into_iter()
+            .map(|set| set.into_iter().map(String::from).collect())
+            .collect()
+    }
+
+    /// The root causes as a single line, e.g. `blocked by: b3 or b4 + b5`.
+    /// Returns None if the resolution is satisfied.
+    pub fn to_blocked_by_string(&self) -> Option<String> {
+        if self.is_satisfied() {
+            return None;
+        }
+        let root_causes = self.get_root_causes();
+        if root_causes.is_empty() {
+            return Some(String::from("blocked by: dependency cycle"));
+        }
+        let sets: Vec<String> = root_causes.iter().map(|set| set.join(" + ")).collect();
+        Some(format!("blocked by: {}", sets.join(" or ")))
+    }
+
+    /// Returns the minimal sets of agents whose failure together would leave
+    /// this resolution unsatisfied (its minimal cut sets): every satisfying
+    /// offer has to lose its agent or one of the conditions it needs. Each
+    /// set is sorted and the sets are ordered smallest first. An unsatisfied
+    /// resolution has none.
+    pub fn get_cut_sets(&self) -> Vec<Vec<String>> {
+        if !self.is_satisfied() {
+            return vec![];
+        }
+        self.cut_sets(self.root, &mut HashMap::new())
+            .into_iter()
+            .map(|set| set.into_iter().map(String::from).collect())
+            .collect()
+    }
+
+    /// Returns the minimal sets of agents that are enough on their own to
+    /// satisfy this resolution: one satisfying offer's agent, plus a set for
+    /// each condition it needs. Each set is sorted and the sets are ordered
+    /// smallest first. An unsatisfied resolution has none.
+    pub fn get_support_sets(&self) -> Vec<Vec<String>> {
+        if !self.is_satisfied() {
+            return vec![];
+        }
+        self.support_sets(self.root, &mut HashMap::new())
+            .into_iter()
+            .map(|set| set.into_iter().map(String::from).collect())
+            .collect()
+    }
+
+    // Any satisfying offer will do (their sets add up), and an offer needs its
+    // agent along with all of its conditions (their sets multiply)
+    fn support_sets<'a>(
+        &'a self,
+        id: ResolutionId,
+        memo: &mut HashMap<ResolutionId, Vec<BTreeSet<&'a str>>>,
+    ) -> Vec<BTreeSet<&'a str>> {
+        if let Some(sets) = memo.get(&id) {
+            return sets.clone();
+        }
+        // can't be satisfied through itself
+        memo.insert(id, vec![]);
+        let node = &self.nodes[id];
+        let mut sets = vec![];
+        for offer in &node.satisfying_offers {
+            let mut offer_sets = vec![BTreeSet::from([offer.agent_name.as_str()])];
+            for c in &offer.conditions {
+                offer_sets = combine_sets(&offer_sets, &self.support_sets(*c, memo));
+            }
+            if !offer.any_of.is_empty() {
+                let mut any_of_sets = vec![];
+                for group in &offer.any_of {
+                    let mut group_sets = vec![BTreeSet::new()];
+                    for c in group {
+                        group_sets = combine_sets(&group_sets, &self.support_sets(*c, memo));
+                    }
+                    any_of_sets.extend(group_sets);
+                }
+                offer_sets = combine_sets(&offer_sets, &minimal_sets(any_of_sets));
+            }
+            sets.extend(offer_sets);
+        }
+        let sets = minimal_sets(sets);
+        memo.insert(id, sets.clone());
+        sets
+    }
+
+    // The dual of `root_cause_sets` over the satisfied part of the graph:
+    // every satisfying offer of a node has to be cut (their sets multiply),
+    // and an offer is cut by its agent or by any of its conditions (their
+    // sets add up). Unsatisfied conditions and anyOf groups are cut already.
+    fn cut_sets<'a>(
+        &'a self,
+        id: ResolutionId,
+        memo: &mut HashMap<ResolutionId, Vec<BTreeSet<&'a str>>>,
+    ) -> Vec<BTreeSet<&'a str>> {
+        if let Some(sets) = memo.get(&id) {
+            return sets.clone();
+        }
+        // can't be cut through itself
+        memo.insert(id, vec![]);
+        let node = &self.nodes[id];
+        let mut sets = vec![BTreeSet::new()];
+        for offer in &node.satisfying_offers {
+            let mut offer_sets = vec![BTreeSet::from([offer.agent_name.as_str()])];
+            for c in &offer.conditions {
+                offer_sets.extend(self.cut_sets(*c, memo));
+            }
+            if !offer.any_of.is_empty() {
+                let mut any_of_sets = vec![BTreeSet::new()];
+                for group in offer
+                    .any_of
+                    .iter()
+                    .filter(|g| g.iter().all(|c| self.nodes[*c].is_satisfied()))
+                {
+                    let mut group_sets = vec![];
+                    for c in group {
+                        group_sets.extend(self.cut_sets(*c, memo));
+                    }
+                    any_of_sets = combine_sets(&any_of_sets, &minimal_sets(group_sets));
+                }
+                offer_sets.extend(any_of_sets);
+            }
+            sets = combine_sets(&sets, &minimal_sets(offer_sets));
+        }
+        memo.insert(id, sets.clone());
+        sets
+    }
+
+    // Satisfied nodes need nothing (a single empty set), missing leaves need
+    // themselves, and cyclic nodes can't be fixed by adding leaves (no sets).
+    // Alternatives (offers, anyOf groups) add up their sets, conditions of an
+    // offer multiply them.
+    fn root_cause_sets<'a>(
+        &'a self,
+        id: ResolutionId,
+        memo: &mut HashMap<ResolutionId, Vec<BTreeSet<&'a str>>>,
+    ) -> Vec<BTreeSet<&'a str>> {
+        if let Some(sets) = memo.get(&id) {
+            return sets.clone();
+        }
+        let node = &self.nodes[id];
+        let sets = if node.is_satisfied() {
+            vec![BTreeSet::new()]
+        } else if node.is_cyclic() {
+            vec![]
+        } else if !node.has_offers() {
+            vec![BTreeSet::from([node.behavior_name.as_str()])]
+        } else {
+            let mut sets = vec![];
+            for offer in &node.unsatisfying_offers {
+                if offer.is_unconditional() {
+                    continue;
+                }
+                sets.extend(self.offer_root_cause_sets(offer, memo));
+            }
+            minimal_sets(sets)
+        };
+        memo.insert(id, sets.clone());
+        sets
+    }
+
+    // The root cause sets of a single unsatisfying offer
+    fn offer_root_cause_sets<'a>(
+        &'a self,
+        offer: &OfferNode,
+        memo: &mut HashMap<ResolutionId, Vec<BTreeSet<&'a str>>>,
+    ) -> Vec<BTreeSet<&'a str>> {
+        let mut offer_sets = self.all_of_sets(&offer.conditions, memo);
+        if !offer.any_of.is_empty() {
+            let mut any_of_sets = vec![];
+            for group in &offer.any_of {
+                any_of_sets.extend(self.all_of_sets(group, memo));
+            }
+            offer_sets = combine_sets(&offer_sets, &minimal_sets(any_of_sets));
+        }
+        offer_sets
+    }
+
+    /// The agents of the root's offers that are satisfied, or only held up
+    /// by a dependency cycle (nothing missing would fix them), so the ones
+    /// that could come up. Offers that need something missing are left out.
+    pub(crate) fn get_live_agent_names(&self) -> Vec<&str> {
+        let node = self.get_root_node();
+        let mut memo = HashMap::new();
+        let mut ret: Vec<&str> = node
+            .satisfying_offers
+            .iter()
+            .map(|o| o.agent_name.as_str())
+            .collect();
+        for offer in &node.unsatisfying_offers {
+            if !offer.is_unconditional() && self.offer_root_cause_sets(offer, &mut memo).is_empty()
+            {
+                ret.push(offer.agent_name.as_str());
+            }
+        }
+        ret
+    }
+
+    // The sets needed to satisfy every one of `conditions`
+    fn all_of_sets<'a>(
+        &'a self,
+        conditions: &[ResolutionId],
+        memo: &mut HashMap<ResolutionId, Vec<BTreeSet<&'a str>>>,
+    ) -> Vec<BTreeSet<&'a str>> {
+        let mut sets = vec![BTreeSet::new()];
+        for c in conditions {
+            sets = combine_sets(&sets, &self.root_cause_sets(*c, memo));
+        }
+        sets
+    }
+
+    /// Lists the distinct complete `Assignment`s of providers that satisfy
+    /// this resolution, skipping the first `offset` and returning at most
+    /// `limit`. They come in a fixed order: offers are tried in the order
+    /// they are listed (agent name order for a `Tracker`), and so are anyOf
+    /// groups, earlier behaviors varying slowest. A behavior reached more
+    /// than once gets the same provider everywhere. An unsatisfied
+    /// resolution has none.
+    pub fn get_assignments(&self, offset: usize, limit: usize) -> AssignmentPage {
+        let mut walk = AssignmentWalk {
+            graph: self,
+            skip: offset,
+            limit,
+            chosen: vec![],
+            emitted: HashSet::new(),
+            page: AssignmentPage {
+                offset,
+                ..AssignmentPage::default()
+            },
+        };
+        if self.is_satisfied() {
+            walk.assign(vec![self.root]);
+        }
+        walk.page
+    }
+
+    /// Expands the graph into a nested `Resolution` tree. Shared nodes are
+    /// copied into every place they are used.
+    pub fn to_resolution(&self) -> Resolution {
+        self.expand(self.root)
+    }
+
+    fn expand(&self, id: ResolutionId) -> Resolution {
+        let node = &self.nodes[id];
+        if node.is_cyclic() {
+            return Resolution::new_cyclic(&node.behavior_name, node.cycle.clone());
+        }
+        let expand_offer = |o: &OfferNode| {
+            if o.is_unconditional() {
+                Offer::new(&o.agent_name).with_location(o.location.clone())
+            } else {
+                Offer::new_conditional(
+                    &o.agent_name,
+                    o.conditions.iter().map(|c| self.expand(*c)).collect(),
+                )
+                .with_any_of(
+                    o.any_of
+                        .iter()
+                        .map(|g| g.iter().map(|c| self.expand(*c)).collect())
+                        .collect(),
+                )
+                .with_location(o.location.clone())
+            }
+        };
+        Resolution::new(&node.behavior_name)
+            .with_suggestions(node.suggestions.clone())
+            .with_requirement(node.requirement.clone())
+            .with_mismatches(node.mismatches.clone())
+            .with_placement(node.placement.clone())
+            .with_rejections(node.rejections.clone())
+            .add_satisfying_offers(node.satisfying_offers.iter().map(expand_offer).collect())
+            .add_unsatisfying_offers(node.unsatisfying_offers.iter().map(expand_offer).collect())
+    }
+
+    // Same layout as `Resolution::to_colorized_strings`, except that a node
+    // with offers is only expanded the first time it is printed. Later uses
+    // are printed as `behavior (see above)`.
+    pub fn to_colorized_strings(&self) -> Vec<String> {
+        self.colorized_resolution_lines(self.root, &mut HashSet::new())
+    }
+
+    fn colorize(&self, s: &str, satisfied: bool) -> ColoredString {
+        if satisfied {
+            s.green()
+        } else {
+            s.red()
+        }
+    }
+
+    fn colorized_resolution_lines(
+        &self,
+        id: ResolutionId,
+        expanded: &mut HashSet<ResolutionId>,
+    ) -> Vec<String> {
+        let node = &self.nodes[id];
+        let behavior = self.colorize(&node.behavior_name, node.is_satisfied());
+        if node.is_cyclic() {
+            return vec![
+                behavior.to_string(),
+                format!(
+                    "  {} {}",
+                    "|->".red(),
+                    format!("cycle: {}", node.cycle.join(" -> ")).red()
+                ),
+            ];
+        }
+        if !node.has_offers() {
+            return vec![
+                behavior.to_string(),
+                format!("  {} {}", "|->".red(), node.get_unmatched_label().red()),
+            ];
+        }
+        if !expanded.insert(id) {
+            return vec![format!(
+                "{} {}",
+                behavior,
+                self.colorize("(see above)", node.is_satisfied())
+            )];
+        }
+        let mut ret = vec![behavior.to_string()];
+        for (offers, satisfied) in [
+            (&node.satisfying_offers, true),
+            (&node.unsatisfying_offers, false),
+        ] {
+            for offer in offers {
+                let mut offer_lines = self.colorized_offer_lines(offer, expanded);
+                offer_lines[0] =
+                    format!("  {} {}", self.colorize("|->", satisfied), &offer_lines[0]);
+                for line in offer_lines.iter_mut().skip(1) {
+                    line.insert_str(0, "  ");
+                }
+                ret.extend(offer_lines);
+            }
+        }
+        ret
+    }
+
+    fn colorized_offer_lines(
+        &self,
+        offer: &OfferNode,
+        expanded: &mut HashSet<ResolutionId>,
+    ) -> Vec<String> {
+        let label = offer.get_label();
+        if offer.is_unconditional() {
+            return vec![label.green().to_string()];
+        }
+        let mut ret = vec![self
+            .colorize(&label, self.is_offer_satisfied(offer))
+            .to_string()];
+        for (arrow, condition) in offer.get_edges() {
+            let mut condition_lines = self.colorized_resolution_lines(condition, expanded);
+            condition_lines[0] = format!(
+                "  {} {}",
+                self.colorize(&arrow, self.nodes[condition].is_satisfied()),
+                &condition_lines[0]
+            );
+            for line in condition_lines.iter_mut().skip(1) {
+                line.insert_str(0, "  ");
+            }
+            ret.extend(condition_lines);
+        }
+        ret
+    }
+
+    // Same layout as `Resolution::to_colorized_compressed_strings`, with
+    // repeated nodes printed as `behavior |-> (see above)`.
+    pub fn to_colorized_compressed_strings(&self) -> Vec<String> {
+        self.compressed_resolution_lines(self.root, &mut HashSet::new())
+    }
+
+    fn compressed_resolution_lines(
+        &self,
+        id: ResolutionId,
+        expanded: &mut HashSet<ResolutionId>,
+    ) -> Vec<String> {
+        let node = &self.nodes[id];
+        if node.is_cyclic() {
+            return vec![format!(
+                "{} {} {}",
+                node.behavior_name.red(),
+                "|->".red(),
+                format!("cycle: {}", node.cycle.join(" -> ")).red()
+            )];
+        }
+        if !node.has_offers() {
+            return vec![format!(
+                "{} {} {}",
+                node.behavior_name.red(),
+                "|->".red(),
+                node.get_unmatched_label().red()
+            )];
+        }
+        let satisfied = node.is_satisfied();
+        if !expanded.insert(id) {
+            return vec![format!(
+                "{} {} {}",
+                self.colorize(&node.behavior_name, satisfied),
+                self.colorize("|->", satisfied),
+                self.colorize("(see above)", satisfied)
+            )];
+        }
+        let colorized_behavior = self.colorize(&node.behavior_name, satisfied);
+        let spacer_behavior = self.colorize(&" ".repeat(node.behavior_name.len()), satisfied);
+        let mut ret: Vec<String> = vec![];
+        for (offers, offer_satisfied) in [
+            (&node.satisfying_offers, true),
+            (&node.unsatisfying_offers, false),
+        ] {
+            for offer in offers {
+                let mut offer_lines = self.compressed_offer_lines(offer, expanded);
+                offer_lines[0] = format!(
+                    "{} {} {}",
+                    if ret.is_empty() {
+                        &colorized_behavior
+                    } else {
+                        &spacer_behavior
+                    },
+                    self.colorize("|->", offer_satisfied),
+                    &offer_lines[0],
+                );
+                let spacer_offer = self.colorize("   ", offer_satisfied);
+                for line in offer_lines.iter_mut().skip(1) {
+                    *line = format!("{} {} {}", &spacer_behavior, &spacer_offer, line);
+                }
+                ret.extend(offer_lines);
+            }
+        }
+        ret
+    }
+
+    fn compressed_offer_lines(
+        &self,
+        offer: &OfferNode,
+        expanded: &mut HashSet<ResolutionId>,
+    ) -> Vec<String> {
+        let label = offer.get_label();
+        if offer.is_unconditional() {
+            return vec![label.green().to_string()];
+        }
+        let satisfied = self.is_offer_satisfied(offer);
+        let colorized_agent = self.colorize(&label, satisfied);
+        let spacer_agent = self.colorize(&" ".repeat(label.len()), satisfied);
+        let mut ret: Vec<String> = vec![];
+        for (arrow, condition) in offer.get_edges() {
+            let condition_satisfied = self.nodes[condition].is_satisfied();
+            let mut condition_lines = self.compressed_resolution_lines(condition, expanded);
+            condition_lines[0] = format!(
+                "{} {} {}",
+                if ret.is_empty() {
+                    &colorized_agent
+                } else {
+                    &spacer_agent
+                },
+                self.colorize(&arrow, condition_satisfied),
+                &condition_lines[0],
+            );
+            let spacer_condition = self.colorize(&" ".repeat(arrow.len()), condition_satisfied);
+            for line in condition_lines.iter_mut().skip(1) {
+                *line = format!("{} {} {}", &spacer_agent, &spacer_condition, line);
+            }
+            ret.extend(condition_lines);
+        }
+        ret
+    }
+}
+
+// Identical subtrees of the Resolution are stored as a single node
+impl From<&Resolution> for ResolutionGraph {
+    fn from(resolution: &Resolution) -> Self {
+        fn intern(
+            r: &Resolution,
+            nodes: &mut Vec<ResolutionNode>,
+            seen: &mut HashMap<ResolutionNode, ResolutionId>,
+        ) -> ResolutionId {
+            let mut node = if r.is_cyclic() {
+                ResolutionNode::new_cyclic(r.get_behavior_name(), r.get_cycle().to_vec())
+            } else {
+                ResolutionNode::new(r.get_behavior_name())
+                    .with_suggestions(r.get_suggestions().to_vec())
+                    .with_requirement(r.get_requirement().map(String::from))
+                    .with_placement(r.get_placement().map(String::from))
+            };
+            node.mismatches = r.get_mismatches().to_vec();
+            node.rejections = r.get_rejections().to_vec();
+            let mut intern_offer = |o: &Offer| {
+                let conditions = o
+                    .get_resolved_conditions()
+                    .iter()
+                    .map(|c| intern(c, nodes, seen))
+                    .collect();
+                let any_of = o
+                    .get_any_of()
+                    .iter()
+                    .map(|g| g.iter().map(|c| intern(c, nodes, seen)).collect())
+                    .collect();
+                OfferNode::new(o.get_agent_name(), conditions)
+                    .with_any_of(any_of)
+                    .with_location(o.get_location().cloned())
+            };
+            for o in r.get_satisfying_offers() {
+                node.add_satisfying_offer(intern_offer(o));
+            }
+            for o in r.get_unsatisfying_offers() {
+                node.add_unsatisfying_offer(intern_offer(o));
+            }
+            if let Some(id) = seen.get(&node) {
+                return *id;
+            }
+            nodes.push(node.clone());
+            seen.insert(node, nodes.len() - 1);
+            nodes.len() - 1
+        }
+        let mut nodes = vec![];
+        let root = intern(resolution, &mut nodes, &mut HashMap::new());
+        ResolutionGraph { root, nodes }
+    }
+}
+
+// Picks a satisfying offer for each behavior in turn, backtracking over
+// the other choices
+struct AssignmentWalk<'a> {
+    graph: &'a ResolutionGraph,
+    skip: usize,
+    limit: usize,
+    chosen: Vec<(ResolutionId, &'a str)>,
+    // Different anyOf groups can end up choosing the same providers, e.g.
+    // when every group only has behaviors that are needed anyway
+    emitted: HashSet<Vec<(ResolutionId, &'a str)>>,
+    page: AssignmentPage,
+}
+
+impl<'a> AssignmentWalk<'a> {
+    // `pending` is a stack of behaviors still to pick a provider for. Returns
+    // false once the page is full.
+    fn assign(&mut self, mut pending: Vec<ResolutionId>) -> bool {
+        let id = loop {
+            match pending.pop() {
+                None => return self.emit(),
+                Some(id) if self.chosen.iter().any(|(c, _)| *c == id) => continue,
+                Some(id) => break id,
+            }
+        };
+        let graph = self.graph;
+        for offer in &graph.nodes[id].satisfying_offers {
+            let groups: Vec<&[ResolutionId]> = if offer.any_of.is_empty() {
+                vec![&[]]
+            } else {
+                offer
+                    .any_of
+                    .iter()
+                    .filter(|g| g.iter().all(|c| graph.nodes[*c].is_satisfied()))
+                    .map(Vec::as_slice)
+                    .collect()
+            };
+            self.chosen.push((id, offer.agent_name.as_str()));
+            for group in groups {
+                let mut next = pending.clone();
+                next.extend(group.iter().rev());
+                next.extend(offer.conditions.iter().rev());
+                if !self.assign(next) {
+                    return false;
+                }
+            }
+            self.chosen.pop();
+        }
+        true
+    }
+
+    fn emit(&mut self) -> bool {
+        let mut key = self.chosen.clone();
+        key.sort();
+        if !self.emitted.insert(key) {
+            return true;
+        }
+        if self.skip > 0 {
+            self.skip -= 1;
+            return true;
+        }
+        if self.page.assignments.len() == self.limit {
+            self.page.has_more = true;
+            return false;
+        }
+        self.page.assignments.push(Assignment {
+            steps: self
+                .chosen
+                .iter()
+                .map(|(id, agent_name)| AssignmentStep {
+                    behavior_name: self.graph.nodes[*id].behavior_name.clone(),
+                    agent_name: String::from(*agent_name),
+                })
+                .collect(),
+        });
+        true
+    }
+}
+
+// Every union of one set from `a` and one from `b`, kept minimal
+pub(crate) fn combine_sets<T: Ord + Clone>(
+    a: &[BTreeSet<T>],
+    b: &[BTreeSet<T>],
+) -> Vec<BTreeSet<T>> {
+    let mut combined = vec![];
+    for x in a {
+        for y in b {
+            combined.push(x.union(y).cloned().collect());
+        }
+    }
+    minimal_sets(combined)
+}
+
+// Drops duplicates and any set that contains another one, smallest first
+pub(crate) fn minimal_sets<T: Ord>(mut sets: Vec<BTreeSet<T>>) -> Vec<BTreeSet<T>> {
+    sets.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    let mut ret: Vec<BTreeSet<T>> = vec![];
+    for set in sets {
+        if !ret.iter().any(|kept| kept.is_subset(&set)) {
+            ret.push(set);
+        }
+    }
+    ret
+}
+
+/// Every node resolved so far by a Tracker, plus which behaviors can be
+/// answered straight from it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResolutionArena {
+    nodes: Vec<ResolutionNode>,
+    resolved: HashMap<Symbol, ResolutionId>,
+    // Nodes only needed until the lookup in progress is done
+    temporary: BTreeSet<ResolutionId>,
+}
+
+impl ResolutionArena {
+    pub(crate) fn get_resolved(&self, behavior: Symbol) -> Option<ResolutionId> {
+        self.resolved.get(&behavior).copied()
+    }
+
+    pub(crate) fn set_resolved(&mut self, behavior: Symbol, id: ResolutionId) {
+        self.resolved.insert(behavior, id);
+    }
+
+    pub(crate) fn is_satisfied(&self, id: ResolutionId) -> bool {
+        self.nodes[id].is_satisfied()
+    }
+
+    pub(crate) fn push(&mut self, node: ResolutionNode) -> ResolutionId {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Same as `push`, but the node is dropped by `drop_temporary`
+    pub(crate) fn push_temporary(&mut self, node: ResolutionNode) -> ResolutionId {
+        let id = self.push(node);
+        self.temporary.insert(id);
+        id
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn is_temporary(&self, id: ResolutionId) -> bool {
+        self.temporary.contains(&id)
+    }
+
+    /// Drops every temporary node and renumbers the ones after the first of
+    /// them. Nodes that are kept must not refer to temporary ones.
+    pub(crate) fn drop_temporary(&mut self) {
+        let Some(&first) = self.temporary.first() else {
+            return;
+        };
+        let drained: Vec<ResolutionNode> = self.nodes.drain(first..).collect();
+        let mut renumbered: HashMap<ResolutionId, ResolutionId> = HashMap::new();
+        for (i, node) in drained.into_iter().enumerate() {
+            if self.temporary.contains(&(first + i)) {
+                continue;
+            }
+            renumbered.insert(first + i, self.nodes.len());
+            let node = node.renumbered(|c| if c < first { c } else { renumbered[&c] });
+            self.nodes.push(node);
+        }
+        for id in self.resolved.values_mut() {
+            if *id >= first {
+                *id = renumbered[id];
+            }
+        }
+        self.temporary.clear();
+    }
+
+    /// Copies out the nodes reachable from `root`, renumbered from zero
+    pub(crate) fn extract(&self, root: ResolutionId) -> ResolutionGraph {
+        let mut renumbered: HashMap<ResolutionId, ResolutionId> = HashMap::from([(root, 0)]);
+        let mut order = vec![root];
+        let mut i = 0;
+        while i < order.len() {
+            let node = &self.nodes[order[i]];
+            for offer in node
+                .satisfying_offers
+                .iter()
+                .chain(node.unsatisfying_offers.iter())
+            {
+                for c in offer.conditions.iter().chain(offer.any_of.iter().flatten()) {
+                    if !renumbered.contains_key(c) {
+                        renumbered.insert(*c, order.len());
+                        order.push(*c);
+                    }
+                }
+            }
+            i += 1;
+        }
+        let nodes = order
+            .iter()
+            .map(|id| self.nodes[*id].renumbered(|c| renumbered[&c]))
+            .collect();
+        ResolutionGraph { root: 0, nodes }
+    }
+}
+
+/// Per-Tracker cache of resolutions. Uses a Mutex rather than a RefCell so a
+/// Tracker can still be shared across threads; cloning copies the contents.
+#[derive(Debug, Default)]
+pub(crate) struct ResolutionCache(Mutex<ResolutionArena>);
+
+impl ResolutionCache {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, ResolutionArena> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clone for ResolutionCache {
+    fn clone(&self) -> Self {
+        ResolutionCache(Mutex::new(self.lock().clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared() -> Resolution {
+        Resolution::new("b1").add_satisfying_offer(Offer::new_conditional(
+            "a1",
+            vec![
+                Resolution::new("b2").add_satisfying_offer(Offer::new_conditional(
+                    "a2",
+                    vec![Resolution::new("b4").add_satisfying_offer(Offer::new("a4"))],
+                )),
+                Resolution::new("b3").add_unsatisfying_offer(Offer::new_conditional(
+                    "a3",
+                    vec![
+                        Resolution::new("b4").add_satisfying_offer(Offer::new("a4")),
+                        Resolution::new("b5"),
+                    ],
+                )),
+            ],
+        ))
+    }
+
+    #[test]
+    fn test_from_resolution() {
+        let g = ResolutionGraph::from(&shared());
+        // b1, b2, b3, b4 (shared), b5
+        assert_eq!(g.get_nodes().len(), 5);
+        assert_eq!(g.get_root_node().get_behavior_name(), "b1");
+        assert!(g.is_satisfied());
+        assert_eq!(g.to_resolution(), shared());
+    }
+
+    #[test]
+    fn test_cyclic_roundtrip() {
+        let r = Resolution::new("x").add_unsatisfying_offer(Offer::new_conditional(
+            "a",
+            vec![Resolution::new_cyclic(
+                "x",
+                vec![String::from("x"), String::from("x")],
+            )],
+        ));
+        let g = ResolutionGraph::from(&r);
+        assert!(g
+            .get_node(g.get_root_node().get_unsatisfying_offers()[0].get_conditions()[0])
+            .is_cyclic());
+        assert_eq!(g.to_resolution(), r);
+    }
+
+    #[test]
+    fn test_to_colorized_strings() {
+        let g = ResolutionGraph::from(&shared());
+        assert_eq!(
+            g.to_colorized_strings(),
+            vec![
+                "b1".green().to_string(),
+                format!("  {} {}", "|->".green(), "a1".red()),
+                format!("    {} {}", "&->".green(), "b2".green()),
+                format!("      {} {}", "|->".green(), "a2".green()),
+                format!("        {} {}", "&->".green(), "b4".green()),
+                format!("          {} {}", "|->".green(), "a4".green()),
+                format!("    {} {}", "&->".red(), "b3".red()),
+                format!("      {} {}", "|->".red(), "a3".red()),
+                format!(
+                    "        {} {} {}",
+                    "&->".green(),
+                    "b4".green(),
+                    "(see above)".green()
+                ),
+                format!("        {} {}", "&->".red(), "b5".red()),
+                format!("          {} {}", "|->".red(), "?".red()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_tree_rendering_without_sharing() {
+        let r = Resolution::new("b1")
+            .add_satisfying_offer(Offer::new("a1"))
+            .add_unsatisfying_offer(Offer::new_conditional(
+                "a2",
+                vec![
+                    Resolution::new("b2").add_satisfying_offer(Offer::new("a3")),
+                    Resolution::new("b3"),
+                ],
+            ));
+        let g = ResolutionGraph::from(&r);
+        assert_eq!(g.to_colorized_strings(), r.to_colorized_strings());
+        assert_eq!(
+            g.to_colorized_compressed_strings(),
+            r.to_colorized_compressed_strings()
+        );
+    }
+
+    #[test]
+    fn test_root_causes() {
+        // satisfied
+        let g = ResolutionGraph::from(&shared());
+        assert!(g.get_root_causes().is_empty());
+        assert_eq!(g.to_blocked_by_string(), None);
+
+        // nothing provides it
+        let g = ResolutionGraph::from(&Resolution::new("b1"));
+        assert_eq!(g.get_root_causes(), vec![vec!["b1"]]);
+        assert_eq!(g.to_blocked_by_string().unwrap(), "blocked by: b1");
+
+        // a1 needs b2 and b3, a2 needs b4 which needs b2 (through a4) or b5
+        // and b6 (through a5); b3 is provided
+        let b2 = || Resolution::new("b2");
+        let r = Resolution::new("b1")
+            .add_unsatisfying_offer(Offer::new_conditional(
+                "a1",
+                vec![
+                    b2(),
+                    Resolution::new("b3").add_satisfying_offer(Offer::new("a3")),
+                ],
+            ))
+            .add_unsatisfying_offer(Offer::new_conditional(
+                "a2",
+                vec![Resolution::new("b4")
+                    .add_unsatisfying_offer(Offer::new_conditional("a4", vec![b2()]))
+                    .add_unsatisfying_offer(Offer::new_conditional(
+                        "a5",
+                        vec![Resolution::new("b5"), Resolution::new("b6")],
+                    ))],
+            ));
+        let g = ResolutionGraph::from(&r);
+        assert_eq!(g.get_root_causes(), vec![vec!["b2"], vec!["b5", "b6"]]);
+        assert_eq!(
+            g.to_blocked_by_string().unwrap(),
+            "blocked by: b2 or b5 + b6"
+        );
+        assert_eq!(r.get_root_causes(), g.get_root_causes());
+    }
+
+    #[test]
+    fn test_cut_sets() {
+        // unsatisfied
+        assert!(ResolutionGraph::from(&Resolution::new("b1"))
+            .get_cut_sets()
+            .is_empty());
+
+        // a chain of single providers
+        let r = Resolution::new("b1").add_satisfying_offer(Offer::new_conditional(
+            "a1",
+            vec![Resolution::new("b2").add_satisfying_offer(Offer::new("a2"))],
+        ));
+        assert_eq!(r.get_cut_sets(), vec![vec!["a1"], vec!["a2"]]);
+
+        // a1 needs b2 (from a2 or a4), a3 needs nothing, and a5 is dead
+        let r = Resolution::new("b1")
+            .add_satisfying_offer(Offer::new_conditional(
+                "a1",
+                vec![Resolution::new("b2")
+                    .add_satisfying_offers(vec![Offer::new("a2"), Offer::new("a4")])],
+            ))
+            .add_satisfying_offer(Offer::new("a3"))
+            .add_unsatisfying_offer(Offer::new_conditional("a5", vec![Resolution::new("b5")]));
+        assert_eq!(
+            r.get_cut_sets(),
+            vec![vec!["a1", "a3"], vec!["a2", "a3", "a4"]]
+        );
+
+        // either of two anyOf groups, one of which is dead
+        let r = Resolution::new("b1").add_satisfying_offer(Offer::new("a1").with_any_of(vec![
+            vec![Resolution::new("b2").add_satisfying_offer(Offer::new("a2"))],
+            vec![Resolution::new("b3")],
+            vec![
+                Resolution::new("b4").add_satisfying_offer(Offer::new("a4")),
+                Resolution::new("b5").add_satisfying_offer(Offer::new("a5")),
+            ],
+        ]));
+        assert_eq!(
+            r.get_cut_sets(),
+            vec![vec!["a1"], vec!["a2", "a4"], vec!["a2", "a5"]]
+        );
+    }
+
+    #[test]
+    fn test_support_sets() {
+        assert!(ResolutionGraph::from(&Resolution::new("b1"))
+            .get_support_sets()
+            .is_empty());
+
+        // a1 needs b2 (from a2 or a4), a3 needs nothing, and a5 is dead
+        let r = Resolution::new("b1")
+            .add_satisfying_offer(Offer::new_conditional(
+                "a1",
+                vec![Resolution::new("b2")
+                    .add_satisfying_offers(vec![Offer::new("a2"), Offer::new("a4")])],
+            ))
+            .add_satisfying_offer(Offer::new("a3"))
+            .add_unsatisfying_offer(Offer::new_conditional("a5", vec![Resolution::new("b5")]));
+        assert_eq!(
+            ResolutionGraph::from(&r).get_support_sets(),
+            vec![vec!["a3"], vec!["a1", "a2"], vec!["a1", "a4"]]
+        );
+
+        // either of two anyOf groups, one of which is dead
+        let r = Resolution::new("b1").add_satisfying_offer(Offer::new("a1").with_any_of(vec![
+            vec![Resolution::new("b2").add_satisfying_offer(Offer::new("a2"))],
+            vec![Resolution::new("b3")],
+            vec![
+                Resolution::new("b4").add_satisfying_offer(Offer::new("a4")),
+                Resolution::new("b5").add_satisfying_offer(Offer::new("a5")),
+            ],
+        ]));
+        assert_eq!(
+            ResolutionGraph::from(&r).get_support_sets(),
+            vec![vec!["a1", "a2"], vec!["a1", "a4", "a5"]]
+        );
+    }
+
+    #[test]
+    fn test_assignments() {
+        assert!(ResolutionGraph::from(&Resolution::new("b1"))
+            .get_assignments(0, 10)
+            .assignments
+            .is_empty());
+
+        // b1 from a1 (needing b2 and b3) or a5; b2 from a2 or a3, both
+        // needing b4 from a4; b3 from a4 or from a6 with b5 or b6
+        let b4 = || Resolution::new("b4").add_satisfying_offer(Offer::new("a4"));
+        let r = Resolution::new("b1")
+            .add_satisfying_offer(Offer::new_conditional(
+                "a1",
+                vec![
+                    Resolution::new("b2").add_satisfying_offers(vec![
+                        Offer::new_conditional("a2", vec![b4()]),
+                        Offer::new_conditional("a3", vec![b4()]),
+                    ]),
+                    Resolution::new("b3").add_satisfying_offers(vec![
+                        Offer::new("a4"),
+                        Offer::new("a6").with_any_of(vec![
+                            vec![Resolution::new("b5").add_satisfying_offer(Offer::new("a5"))],
+                            vec![Resolution::new("b6")],
+                            vec![Resolution::new("b7").add_satisfying_offer(Offer::new("a7"))],
+                        ]),
+                    ]),
+                ],
+            ))
+            .add_satisfying_offer(Offer::new("a5"))
+            .add_unsatisfying_offer(Offer::new_conditional("a8", vec![Resolution::new("b8")]));
+        let g = ResolutionGraph::from(&r);
+        let all = g.get_assignments(0, 100);
+        assert!(!all.has_more);
+        assert_eq!(
+            all.assignments
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>(),
+            [
+                "b1 <- a1, b2 <- a2, b4 <- a4, b3 <- a4",
+                "b1 <- a1, b2 <- a2, b4 <- a4, b3 <- a6, b5 <- a5",
+                "b1 <- a1, b2 <- a2, b4 <- a4, b3 <- a6, b7 <- a7",
+                "b1 <- a1, b2 <- a3, b4 <- a4, b3 <- a4",
+                "b1 <- a1, b2 <- a3, b4 <- a4, b3 <- a6, b5 <- a5",
+                "b1 <- a1, b2 <- a3, b4 <- a4, b3 <- a6, b7 <- a7",
+                "b1 <- a5",
+            ]
+        );
+        assert_eq!(all.assignments[0].get_agent_names(), ["a1", "a2", "a4"]);
+
+        // paged
+        let page = g.get_assignments(2, 3);
+        assert_eq!(page.offset, 2);
+        assert_eq!(page.assignments, all.assignments[2..5]);
+        assert!(page.has_more);
+        let page = g.get_assignments(5, 3);
+        assert_eq!(page.assignments, all.assignments[5..]);
+        assert!(!page.has_more);
+        assert!(g.get_assignments(7, 3).assignments.is_empty());
+
+        // both groups only have behaviors that are conditions anyway
+        let b2 = || Resolution::new("b2").add_satisfying_offer(Offer::new("a2"));
+        let b3 = || Resolution::new("b3").add_satisfying_offer(Offer::new("a3"));
+        let r = Resolution::new("b1").add_satisfying_offer(
+            Offer::new_conditional("a1", vec![b2(), b3()])
+                .with_any_of(vec![vec![b2()], vec![b3()]]),
+        );
+        let page = ResolutionGraph::from(&r).get_assignments(0, 10);
+        assert_eq!(
+            page.assignments
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>(),
+            ["b1 <- a1, b2 <- a2, b3 <- a3"]
+        );
+        assert!(ResolutionGraph::from(&r)
+            .get_assignments(1, 10)
+            .assignments
+            .is_empty());
+    }
+
+    #[test]
+    fn test_root_causes_cycle() {
+        let r = Resolution::new("x").add_unsatisfying_offer(Offer::new_conditional(
+            "a",
+            vec![Resolution::new_cyclic(
+                "x",
+                vec![String::from("x"), String::from("x")],
+            )],
+        ));
+        let g = ResolutionGraph::from(&r);
+        assert!(g.get_root_causes().is_empty());
+        assert_eq!(
+            g.to_blocked_by_string().unwrap(),
+            "blocked by: dependency cycle"
+        );
+
+        // the cycle is one alternative, a missing leaf the other
+        let r = r.add_unsatisfying_offer(Offer::new_conditional("b", vec![Resolution::new("y")]));
+        assert_eq!(r.get_root_causes(), vec![vec!["y"]]);
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut arena = ResolutionArena::default();
+        let unused = arena.push(ResolutionNode::new("unused"));
+        let leaf = arena.push(ResolutionNode::new("b2"));
+        let mut root = ResolutionNode::new("b1");
+        root.add_unsatisfying_offer(OfferNode::new("a1", vec![leaf]));
+        root.add_unsatisfying_offer(OfferNode::new("a2", vec![leaf]));
+        let root = arena.push(root);
+        assert_ne!(unused, root);
+
+        let g = arena.extract(root);
+        assert_eq!(g.get_root(), 0);
+        assert_eq!(g.get_nodes().len(), 2);
+        assert_eq!(
+            g.get_root_node().get_unsatisfying_offers()[1].get_conditions(),
+            [1]
+        );
+        assert_eq!(g.get_node(1).get_behavior_name(), "b2");
+    }
+}
@@ -1,3 +1,8 @@
+use crate::components::Location;
+use crate::placement::{rejection_label, Rejection};
+use crate::resolution_graph::{any_of_arrow, ResolutionGraph, CONDITION_ARROW};
+use crate::similarity::unmatched_label;
+use crate::version::{mismatch_label, VersionMismatch};
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
 
@@ -6,6 +11,33 @@ pub struct Resolution {
     behavior_name: String,
     satisfying_offers: Vec<Offer>,
     unsatisfying_offers: Vec<Offer>,
+    // The active resolution path that led back to this behavior, starting and
+    // ending with it. Only set when resolution stopped here because of a loop.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cycle: Vec<String>,
+    // Provided behaviors this one was probably meant to be. Only set when
+    // nothing provides it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<String>,
+    // The version the behavior was wanted at, if any
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requirement: Option<String>,
+    // Providers left out because their version doesn't meet the requirement
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mismatches: Vec<VersionMismatch>,
+    // The labels the provider had to have, if it was wanted somewhere in
+    // particular
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    placement: Option<String>,
+    // Providers left out because of where they are
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rejections: Vec<Rejection>,
 }
 
 impl Resolution {
@@ -14,9 +46,56 @@ impl Resolution {
             behavior_name: String::from(behavior_name),
             satisfying_offers: vec![],
             unsatisfying_offers: vec![],
+            cycle: vec![],
+            suggestions: vec![],
+            requirement: None,
+            mismatches: vec![],
+            placement: None,
+            rejections: vec![],
         }
     }
 
+    /// Builds a Resolution for a behavior that is already being resolved
+    /// further up the active path. It never has offers and is never satisfied.
+    pub fn new_cyclic(behavior_name: &str, cycle: Vec<String>) -> Resolution {
+        Resolution {
+            behavior_name: String::from(behavior_name),
+            satisfying_offers: vec![],
+            unsatisfying_offers: vec![],
+            cycle,
+            suggestions: vec![],
+            requirement: None,
+            mismatches: vec![],
+            placement: None,
+            rejections: vec![],
+        }
+    }
+
+    pub fn with_suggestions(mut self, suggestions: Vec<String>) -> Resolution {
+        self.suggestions = suggestions;
+        self
+    }
+
+    pub fn with_requirement(mut self, requirement: Option<String>) -> Resolution {
+        self.requirement = requirement;
+        self
+    }
+
+    pub fn with_mismatches(mut self, mismatches: Vec<VersionMismatch>) -> Resolution {
+        self.mismatches = mismatches;
+        self
+    }
+
+    pub fn with_placement(mut self, placement: Option<String>) -> Resolution {
+        self.placement = placement;
+        self
+    }
+
+    pub fn with_rejections(mut self, rejections: Vec<Rejection>) -> Resolution {
+        self.rejections = rejections;
+        self
+    }
+
     pub fn add_satisfying_offer(mut self, offer: Offer) -> Resolution {
         self.satisfying_offers.push(offer);
         self
@@ -32,6 +111,11 @@ impl Resolution {
         self
     }
 
+    pub fn add_unsatisfying_offers(mut self, offers: Vec<Offer>) -> Resolution {
+        self.unsatisfying_offers.extend(offers);
+        self
+    }
+
     pub fn is_satisfied(&self) -> bool {
         self.satisfying_offers.len() > 0
     }
@@ -51,17 +135,91 @@ impl Resolution {
         &self.unsatisfying_offers
     }
 
+    /// Returns true if resolution stopped here because the behavior loops back on itself
+    pub fn is_cyclic(&self) -> bool {
+        !self.cycle.is_empty()
+    }
+
+    /// Returns the loop path (e.g. `["x", "y", "x"]`), empty if not cyclic
+    pub fn get_cycle(&self) -> &[String] {
+        &self.cycle
+    }
+
+    /// Returns the closest provided behavior names, best first. Empty unless
+    /// nothing provides this behavior.
+    pub fn get_suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+
+    /// Returns the version requirement, if the behavior was wanted at one
+    pub fn get_requirement(&self) -> Option<&str> {
+        self.requirement.as_deref()
+    }
+
+    /// Returns the providers left out because of their version
+    pub fn get_mismatches(&self) -> &[VersionMismatch] {
+        &self.mismatches
+    }
+
+    /// Returns the labels the provider had to have, if the behavior was
+    /// wanted somewhere in particular
+    pub fn get_placement(&self) -> Option<&str> {
+        self.placement.as_deref()
+    }
+
+    /// Returns the providers left out because of where they are
+    pub fn get_rejections(&self) -> &[Rejection] {
+        &self.rejections
+    }
+
+    // See `ResolutionNode::get_unmatched_label`
+    fn unmatched_label(&self) -> String {
+        match (&self.requirement, &self.placement) {
+            (Some(requirement), _) if !self.mismatches.is_empty() => {
+                mismatch_label(requirement, &self.mismatches)
+            }
+            (_, Some(placement)) if !self.rejections.is_empty() => {
+                rejection_label(placement, &self.rejections)
+            }
+            _ => unmatched_label(&self.suggestions),
+        }
+    }
+
+    /// Minimal sets of missing leaf behaviors blocking this resolution. See
+    /// `ResolutionGraph::get_root_causes`.
+    pub fn get_root_causes(&self) -> Vec<Vec<String>> {
+        ResolutionGraph::from(self).get_root_causes()
+    }
+
+    /// Minimal sets of agents whose failure would leave this resolution
+    /// unsatisfied. See `ResolutionGraph::get_cut_sets`.
+    pub fn get_cut_sets(&self) -> Vec<Vec<String>> {
+        ResolutionGraph::from(self).get_cut_sets()
+    }
+
+    fn cycle_string(&self) -> String {
+        format!("cycle: {}", self.cycle.join(" -> "))
+    }
+
     // resolve strings is of the format
     // behavior |-> offerer ...
     // offer is of the format
     //
     pub fn to_colorized_compressed_strings(&self) -> Vec<String> {
+        if self.is_cyclic() {
+            return vec![format!(
+                "{} {} {}",
+                self.behavior_name.red(),
+                "|->".red(),
+                self.cycle_string().red()
+            )];
+        }
         if self.satisfying_offers.len() == 0 && self.unsatisfying_offers.len() == 0 {
             return vec![format!(
                 "{} {} {}",
                 self.behavior_name.red(),
                 "|->".red(),
-                "?".red()
+                self.unmatched_label().red()
             )];
         }
         let (colorized_behavior, spacer_behavior) = if self.is_satisfied() {
@@ -129,7 +287,11 @@ impl Resolution {
                 } else {
                     "|->".to_string()
                 },
-                "?"
+                if self.is_cyclic() {
+                    self.cycle_string()
+                } else {
+                    self.unmatched_label()
+                }
             )];
         }
         let mut ret = vec![];
@@ -172,10 +334,16 @@ impl Resolution {
     }
 
     pub fn to_colorized_strings(&self) -> Vec<String> {
+        if self.is_cyclic() {
+            return vec![
+                self.behavior_name.red().to_string(),
+                format!("  {} {}", "|->".red(), self.cycle_string().red()),
+            ];
+        }
         if self.satisfying_offers.len() == 0 && self.unsatisfying_offers.len() == 0 {
             return vec![
                 self.behavior_name.red().to_string(),
-                format!("  {} {}", "|->".red(), "?".red()),
+                format!("  {} {}", "|->".red(), self.unmatched_label().red()),
             ];
         };
         let mut ret = vec![if self.is_satisfied() {
@@ -203,11 +371,22 @@ impl Resolution {
     }
 }
 
+// Suggestions depend on what else is in the Tracker, not on how this
+// behavior resolved, so they are not compared
 impl PartialEq for Resolution {
     fn eq(&self, other: &Self) -> bool {
         if self.behavior_name != other.behavior_name {
             return false;
         }
+        if self.cycle != other.cycle {
+            return false;
+        }
+        if self.requirement != other.requirement || self.mismatches != other.mismatches {
+            return false;
+        }
+        if self.placement != other.placement || self.rejections != other.rejections {
+            return false;
+        }
         if self.satisfying_offers.len() != other.satisfying_offers.len() {
             return false;
         }
@@ -316,6 +495,34 @@ mod tests_resolution {
             Resolution::new("b1"),
             Resolution::new("b1").add_unsatisfying_offer(Offer::new("a1"))
         );
+        // cyclic vs plain empty
+        assert_ne!(
+            Resolution::new("b1"),
+            Resolution::new_cyclic("b1", vec![String::from("b1"), String::from("b1")])
+        );
+    }
+
+    #[test]
+    fn test_cyclic() {
+        let r = Resolution::new_cyclic(
+            "b1",
+            vec![String::from("b1"), String::from("b2"), String::from("b1")],
+        );
+        assert!(r.is_cyclic());
+        assert!(!r.is_satisfied());
+        assert_eq!(r.get_cycle(), ["b1", "b2", "b1"]);
+        assert!(!Resolution::new("b1").is_cyclic());
+        assert_eq!(
+            r.to_strings_compressed(false),
+            vec!["b1 |-> cycle: b1 -> b2 -> b1".to_string()]
+        );
+        assert_eq!(
+            r.to_colorized_strings(),
+            vec![
+                "b1".red().to_string(),
+                format!("  {} {}", "|->".red(), "cycle: b1 -> b2 -> b1".red()),
+            ]
+        );
     }
 }
 
@@ -323,6 +530,13 @@ mod tests_resolution {
 pub struct Offer {
     agent_name: String,
     resolved_conditions: Vec<Resolution>,
+    // One of these groups must be satisfied as well as every condition
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    any_of: Vec<Vec<Resolution>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
 }
 
 impl Offer {
@@ -330,6 +544,8 @@ impl Offer {
         Offer {
             agent_name: String::from(agent_name),
             resolved_conditions: vec![],
+            any_of: vec![],
+            location: None,
         }
     }
 
@@ -337,9 +553,22 @@ impl Offer {
         Offer {
             agent_name: String::from(agent_name),
             resolved_conditions,
+            any_of: vec![],
+            location: None,
         }
     }
 
+    /// Adds the resolved conditions of each `anyOf` group
+    pub fn with_any_of(mut self, any_of: Vec<Vec<Resolution>>) -> Offer {
+        self.any_of = any_of;
+        self
+    }
+
+    pub fn with_location(mut self, location: Option<Location>) -> Offer {
+        self.location = location;
+        self
+    }
+
     /// Returns the agent name
     pub fn get_agent_name(&self) -> &str {
         &self.agent_name
@@ -350,45 +579,88 @@ impl Offer {
         &self.resolved_conditions
     }
 
+    /// Returns the resolved conditions of each `anyOf` group
+    pub fn get_any_of(&self) -> &[Vec<Resolution>] {
+        &self.any_of
+    }
+
+    pub fn is_unconditional(&self) -> bool {
+        self.resolved_conditions.is_empty() && self.any_of.is_empty()
+    }
+
+    /// Returns true if every condition is satisfied, along with every
+    /// condition of one of the `anyOf` groups
+    pub fn is_satisfied(&self) -> bool {
+        self.resolved_conditions.iter().all(|c| c.is_satisfied())
+            && (self.any_of.is_empty()
+                || self
+                    .any_of
+                    .iter()
+                    .any(|g| g.iter().all(|c| c.is_satisfied())))
+    }
+
+    // Every condition along with the arrow it is drawn with, as in
+    // `OfferNode::get_edges`
+    fn edges(&self) -> Vec<(String, &Resolution)> {
+        let mut ret: Vec<(String, &Resolution)> = self
+            .resolved_conditions
+            .iter()
+            .map(|c| (String::from(CONDITION_ARROW), c))
+            .collect();
+        for (i, group) in self.any_of.iter().enumerate() {
+            ret.extend(group.iter().map(|c| (any_of_arrow(i), c)));
+        }
+        ret
+    }
+
+    /// Returns where the offered behavior was defined, if known
+    pub fn get_location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// Returns the agent name followed by its location, e.g. `db (contracts/db.yaml:14)`
+    pub fn get_label(&self) -> String {
+        match &self.location {
+            Some(location) => format!("{} ({})", self.agent_name, location),
+            None => self.agent_name.clone(),
+        }
+    }
+
     pub fn to_strings_compressed(&self, use_color: bool) -> Vec<String> {
-        if self.resolved_conditions.len() == 0 {
-            return vec![format!("{}", self.agent_name)];
+        let label = self.get_label();
+        if self.is_unconditional() {
+            return vec![format!("{}", label)];
         }
         let mut ret = vec![];
-        for condition in &self.resolved_conditions {
+        for (arrow, condition) in self.edges() {
             let mut children = condition.to_strings_compressed(use_color);
-            children[0].insert_str(0, &format!("{} &-> ", &" ".repeat(self.agent_name.len())));
+            children[0].insert_str(0, &format!("{} {} ", &" ".repeat(label.len()), arrow));
             for child in &mut children[1..] {
-                child.insert_str(0, &" ".repeat(self.agent_name.len() + 5));
+                child.insert_str(0, &" ".repeat(label.len() + arrow.len() + 2));
             }
             ret.extend(children);
         }
-        ret[0].replace_range(0..self.agent_name.len(), &self.agent_name);
+        ret[0].replace_range(0..label.len(), &label);
         ret
     }
 
     pub fn to_colorized_compressed_strings(&self) -> Vec<String> {
-        if self.resolved_conditions.len() == 0 {
-            return vec![format!("{}", self.agent_name.green())];
+        let label = self.get_label();
+        if self.is_unconditional() {
+            return vec![format!("{}", label.green())];
         }
-        let satisfied = self.resolved_conditions.iter().all(|c| c.is_satisfied());
-        let (colorized_agent, spacer_agent) = if satisfied {
-            (
-                self.agent_name.green(),
-                " ".repeat(self.agent_name.len()).green(),
-            )
+        let (colorized_agent, spacer_agent) = if self.is_satisfied() {
+            (label.green(), " ".repeat(label.len()).green())
         } else {
-            (
-                self.agent_name.red(),
-                " ".repeat(self.agent_name.len()).red(),
-            )
+            (label.red(), " ".repeat(label.len()).red())
         };
         let mut ret = vec![];
-        for condition in &self.resolved_conditions {
+        for (arrow, condition) in self.edges() {
+            let spacer = " ".repeat(arrow.len());
             let (colorized_condition, spacer_condition) = if condition.is_satisfied() {
-                ("&->".green(), "   ".green())
+                (arrow.green(), spacer.green())
             } else {
-                ("&->".red(), "   ".red())
+                (arrow.red(), spacer.red())
             };
             let mut condition_lines = condition.to_colorized_compressed_strings();
             condition_lines[0] = format!(
@@ -413,23 +685,23 @@ impl Offer {
     }
 
     pub fn to_colorized_strings(&self) -> Vec<String> {
-        if self.resolved_conditions.len() == 0 {
-            return vec![format!("{}", self.agent_name.green())];
+        let label = self.get_label();
+        if self.is_unconditional() {
+            return vec![format!("{}", label.green())];
         };
-        let satisfied = self.resolved_conditions.iter().all(|c| c.is_satisfied());
-        let mut ret = vec![if satisfied {
-            self.agent_name.green().to_string()
+        let mut ret = vec![if self.is_satisfied() {
+            label.green().to_string()
         } else {
-            self.agent_name.red().to_string()
+            label.red().to_string()
         }];
-        for condition in &self.resolved_conditions {
+        for (arrow, condition) in self.edges() {
             let mut condition_lines = condition.to_colorized_strings();
             condition_lines[0] = format!(
                 "  {} {}",
                 if condition.is_satisfied() {
-                    "&->".green()
+                    arrow.green()
                 } else {
-                    "&->".red()
+                    arrow.red()
                 },
                 &condition_lines[0]
             );
@@ -447,23 +719,36 @@ impl PartialEq for Offer {
         if self.agent_name != other.agent_name {
             return false;
         }
-        if self.resolved_conditions.len() != other.resolved_conditions.len() {
+        if !same_conditions(&self.resolved_conditions, &other.resolved_conditions) {
             return false;
         }
-        for self_condition in &self.resolved_conditions {
-            let mut found = false;
-            for other_condition in &other.resolved_conditions {
-                if self_condition == other_condition {
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
-                return false;
+        self.any_of.len() == other.any_of.len()
+            && self
+                .any_of
+                .iter()
+                .zip(other.any_of.iter())
+                .all(|(a, b)| same_conditions(a, b))
+    }
+}
+
+// The same resolutions in any order
+fn same_conditions(a: &[Resolution], b: &[Resolution]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    for self_condition in a {
+        let mut found = false;
+        for other_condition in b {
+            if self_condition == other_condition {
+                found = true;
+                break;
             }
         }
-        true
+        if !found {
+            return false;
+        }
     }
+    true
 }
 impl Eq for Offer {}
 
@@ -0,0 +1,121 @@
+//! Strongly connected components over string-keyed directed graphs.
+
+use std::collections::HashMap;
+
+/// Returns the strongly connected components of `graph` using an iterative
+/// version of Tarjan's algorithm, so deep dependency chains don't exhaust the
+/// stack. Nodes that only appear as edge targets are included. Components are
+/// returned in reverse topological order (a component comes before anything
+/// that depends on it) and each component is sorted.
+pub(crate) fn strongly_connected_components(
+    graph: &HashMap<String, Vec<String>>,
+) -> Vec<Vec<String>> {
+    let mut names: Vec<&String> = graph.keys().collect();
+    for targets in graph.values() {
+        names.extend(targets.iter());
+    }
+    names.sort();
+    names.dedup();
+    let ids: HashMap<&String, usize> = names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    let edges: Vec<Vec<usize>> = names
+        .iter()
+        .map(|n| match graph.get(*n) {
+            Some(targets) => targets.iter().map(|t| ids[t]).collect(),
+            None => vec![],
+        })
+        .collect();
+
+    let mut index = vec![usize::MAX; names.len()];
+    let mut lowlink = vec![0; names.len()];
+    let mut on_stack = vec![false; names.len()];
+    let mut stack = vec![];
+    let mut next_index = 0;
+    let mut components = vec![];
+
+    for start in 0..names.len() {
+        if index[start] != usize::MAX {
+            continue;
+        }
+        // (node, position of the next edge to look at)
+        let mut work = vec![(start, 0)];
+        while let Some((node, edge)) = work.pop() {
+            if edge == 0 {
+                index[node] = next_index;
+                lowlink[node] = next_index;
+                next_index += 1;
+                stack.push(node);
+                on_stack[node] = true;
+            }
+            if let Some(&target) = edges[node].get(edge) {
+                work.push((node, edge + 1));
+                if index[target] == usize::MAX {
+                    work.push((target, 0));
+                } else if on_stack[target] {
+                    lowlink[node] = lowlink[node].min(index[target]);
+                }
+                continue;
+            }
+            if lowlink[node] == index[node] {
+                let mut component = vec![];
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(names[member].clone());
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort();
+                components.push(component);
+            }
+            if let Some(&(parent, _)) = work.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[node]);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        edges
+            .iter()
+            .map(|(from, to)| {
+                (
+                    from.to_string(),
+                    to.iter().map(|t| t.to_string()).collect::<Vec<String>>(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_acyclic() {
+        let components =
+            strongly_connected_components(&graph(&[("a", &["b", "c"]), ("b", &["c"])]));
+        assert_eq!(components, vec![vec!["c"], vec!["b"], vec!["a"]]);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let components = strongly_connected_components(&graph(&[
+            ("a", &["b"]),
+            ("b", &["c"]),
+            ("c", &["a", "d"]),
+            ("d", &["d"]),
+            ("e", &["a"]),
+        ]));
+        assert_eq!(components, vec![vec!["d"], vec!["a", "b", "c"], vec!["e"]]);
+    }
+
+    #[test]
+    fn test_deep_chain() {
+        let mut g = HashMap::new();
+        for i in 0..20_000 {
+            g.insert(format!("n{}", i), vec![format!("n{}", i + 1)]);
+        }
+        assert_eq!(strongly_connected_components(&g).len(), 20_001);
+    }
+}
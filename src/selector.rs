@@ -0,0 +1,223 @@
+//! Label selectors.
+//!
+//! Agents, SuperAgents and their instances can carry free form `labels`
+//! (owner team, tier, environment, ...). A `Selector` picks agents by their
+//! labels with the same syntax as Kubernetes label selectors: a comma
+//! separated list of requirements that must all hold, e.g.
+//! `team=payments,tier!=batch,env in (prod, staging),!deprecated`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Labels as they are stored on agents, sorted by key
+pub type Labels = BTreeMap<String, String>;
+
+/// A single requirement of a `Selector`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Requirement {
+    /// `key=value` or `key==value`
+    Equals(String, String),
+    /// `key!=value`; also holds if the label is missing
+    NotEquals(String, String),
+    /// `key in (a, b)`
+    In(String, Vec<String>),
+    /// `key notin (a, b)`; also holds if the label is missing
+    NotIn(String, Vec<String>),
+    /// `key`
+    Exists(String),
+    /// `!key`
+    DoesNotExist(String),
+}
+
+impl Requirement {
+    pub fn matches(&self, labels: &Labels) -> bool {
+        match self {
+            Requirement::Equals(k, v) => labels.get(k) == Some(v),
+            Requirement::NotEquals(k, v) => labels.get(k) != Some(v),
+            Requirement::In(k, vs) => labels.get(k).is_some_and(|v| vs.contains(v)),
+            Requirement::NotIn(k, vs) => !labels.get(k).is_some_and(|v| vs.contains(v)),
+            Requirement::Exists(k) => labels.contains_key(k),
+            Requirement::DoesNotExist(k) => !labels.contains_key(k),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Requirement::Equals(k, v) => write!(f, "{}={}", k, v),
+            Requirement::NotEquals(k, v) => write!(f, "{}!={}", k, v),
+            Requirement::In(k, vs) => write!(f, "{} in ({})", k, vs.join(",")),
+            Requirement::NotIn(k, vs) => write!(f, "{} notin ({})", k, vs.join(",")),
+            Requirement::Exists(k) => write!(f, "{}", k),
+            Requirement::DoesNotExist(k) => write!(f, "!{}", k),
+        }
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let key = |k: &str| -> Result<String, String> {
+            let k = k.trim();
+            if k.is_empty() || k.contains(|c: char| c.is_whitespace() || "=!(),".contains(c)) {
+                return Err(format!("invalid label key in {:?}", s));
+            }
+            Ok(String::from(k))
+        };
+        let value = |v: &str| String::from(v.trim());
+        if let Some((k, v)) = s.split_once("!=") {
+            return Ok(Requirement::NotEquals(key(k)?, value(v)));
+        }
+        if let Some((k, v)) = s.split_once("==").or_else(|| s.split_once('=')) {
+            return Ok(Requirement::Equals(key(k)?, value(v)));
+        }
+        if let Some(k) = s.strip_prefix('!') {
+            return Ok(Requirement::DoesNotExist(key(k)?));
+        }
+        for (operator, is_in) in [(" notin ", false), (" in ", true)] {
+            let Some((k, vs)) = s.split_once(operator) else {
+                continue;
+            };
+            let vs = vs
+                .trim()
+                .strip_prefix('(')
+                .and_then(|vs| vs.strip_suffix(')'))
+                .ok_or_else(|| format!("expected a (list) of values in {:?}", s))?;
+            let vs = vs.split(',').map(value).collect();
+            return Ok(if is_in {
+                Requirement::In(key(k)?, vs)
+            } else {
+                Requirement::NotIn(key(k)?, vs)
+            });
+        }
+        Ok(Requirement::Exists(key(s)?))
+    }
+}
+
+/// Requirements that must all hold. The empty selector matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selector {
+    requirements: Vec<Requirement>,
+}
+
+impl Selector {
+    pub fn get_requirements(&self) -> &[Requirement] {
+        &self.requirements
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.requirements.iter().all(|r| r.matches(labels))
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let requirements: Vec<String> = self.requirements.iter().map(|r| r.to_string()).collect();
+        f.write_str(&requirements.join(","))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    // Splits on the commas that aren't inside an `in (...)` list
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut requirements = vec![];
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => return Err(format!("unbalanced ) in {:?}", s)),
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    requirements.push(&s[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        if depth != 0 {
+            return Err(format!("unbalanced ( in {:?}", s));
+        }
+        requirements.push(&s[start..]);
+        Ok(Selector {
+            requirements: requirements
+                .into_iter()
+                .filter(|r| !r.trim().is_empty())
+                .map(|r| r.parse())
+                .collect::<Result<Vec<Requirement>, String>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> Labels {
+        pairs
+            .iter()
+            .map(|(k, v)| (String::from(*k), String::from(*v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let s: Selector = "team=payments, tier!=batch,env in (prod, staging),!deprecated,owner"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            s.get_requirements(),
+            [
+                Requirement::Equals(String::from("team"), String::from("payments")),
+                Requirement::NotEquals(String::from("tier"), String::from("batch")),
+                Requirement::In(
+                    String::from("env"),
+                    vec![String::from("prod"), String::from("staging")]
+                ),
+                Requirement::DoesNotExist(String::from("deprecated")),
+                Requirement::Exists(String::from("owner")),
+            ]
+        );
+        assert_eq!(
+            s.to_string(),
+            "team=payments,tier!=batch,env in (prod,staging),!deprecated,owner"
+        );
+        assert_eq!(s.to_string().parse::<Selector>().unwrap(), s);
+        assert_eq!(
+            "a==b".parse::<Selector>().unwrap(),
+            "a=b".parse::<Selector>().unwrap()
+        );
+        assert!("".parse::<Selector>().unwrap().is_empty());
+        assert!("env in (prod".parse::<Selector>().is_err());
+        assert!("env in prod".parse::<Selector>().is_err());
+        assert!("=prod".parse::<Selector>().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let payments = labels(&[("team", "payments"), ("tier", "web"), ("env", "prod")]);
+        let batch = labels(&[("team", "payments"), ("tier", "batch")]);
+        let unlabeled = labels(&[]);
+        let s: Selector = "team=payments,tier!=batch".parse().unwrap();
+        assert!(s.matches(&payments));
+        assert!(!s.matches(&batch));
+        assert!(!s.matches(&unlabeled));
+        let s: Selector = "env notin (prod),!owner".parse().unwrap();
+        assert!(!s.matches(&payments));
+        assert!(s.matches(&batch));
+        assert!(s.matches(&unlabeled));
+        assert!(Selector::default().matches(&unlabeled));
+    }
+}
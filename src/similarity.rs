@@ -0,0 +1,142 @@
+// Helpers for comparing behavior names that were probably meant to be the same
+
+/// Number of single character insertions, deletions or substitutions needed
+/// to turn `a` into `b`
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Different names that are the same apart from case
+pub(crate) fn differ_only_by_case(a: &str, b: &str) -> bool {
+    a != b && a.to_lowercase() == b.to_lowercase()
+}
+
+/// Different names that are one edit apart, ignoring very short names where
+/// that is more likely to be a coincidence
+pub(crate) fn is_probable_typo(a: &str, b: &str) -> bool {
+    a != b && a.chars().count() >= 4 && edit_distance(a, b) == 1
+}
+
+/// Most suggestions offered for a single name
+pub(crate) const MAX_SUGGESTIONS: usize = 3;
+
+// Lowercased words of a behavior name, e.g. `postgres-conn | eu` has
+// `postgres`, `conn` and `eu`
+fn tokens(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Words of `a` that are also in `b`, counting abbreviations (`conn` for
+// `connection`) as the same word
+fn token_overlap(a: &[String], b: &[String]) -> usize {
+    a.iter()
+        .filter(|ta| {
+            b.iter().any(|tb| {
+                ta == &tb
+                    || (ta.len().min(tb.len()) >= 3
+                        && (ta.starts_with(tb.as_str()) || tb.starts_with(ta.as_str())))
+            })
+        })
+        .count()
+}
+
+/// The candidates that `name` was most likely meant to be, best first: those
+/// sharing the most words with it, then the fewest edits away. Candidates
+/// that share no words are only kept when they are a few edits away.
+pub(crate) fn suggestions<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<String> {
+    let name_tokens = tokens(name);
+    let lower = name.to_lowercase();
+    let max_distance = (lower.chars().count() / 3).max(1);
+    let mut ranked: Vec<(usize, usize, &str)> = candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .filter_map(|c| {
+            let overlap = token_overlap(&name_tokens, &tokens(c));
+            let distance = edit_distance(&lower, &c.to_lowercase());
+            if overlap > 0 || (lower.chars().count() >= 4 && distance <= max_distance) {
+                Some((overlap, distance, c))
+            } else {
+                None
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(b.2)));
+    ranked.dedup_by(|a, b| a.2 == b.2);
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, c)| String::from(c))
+        .collect()
+}
+
+/// What is shown in place of the providers of a behavior that nobody
+/// provides, e.g. `? (did you mean: postgres-connection, postgres-backup)`
+pub(crate) fn unmatched_label(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::from("?")
+    } else {
+        format!("? (did you mean: {})", suggestions.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("postgres", "postgress"), 1);
+        assert_eq!(edit_distance("db", "bd"), 2);
+    }
+
+    #[test]
+    fn test_similar() {
+        assert!(differ_only_by_case("Storage", "storage"));
+        assert!(!differ_only_by_case("storage", "storage"));
+        assert!(is_probable_typo("storage", "storge"));
+        assert!(!is_probable_typo("db", "dc"));
+        assert!(!is_probable_typo("storage", "backups"));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let provided = [
+            "postgres-connection",
+            "postgres-backup",
+            "storage | ssd",
+            "storage | hdd",
+            "dns",
+        ];
+        assert_eq!(
+            suggestions("postgres-conn", provided),
+            vec!["postgres-connection", "postgres-backup"]
+        );
+        assert_eq!(
+            suggestions("storage", provided),
+            vec!["storage | hdd", "storage | ssd"]
+        );
+        assert_eq!(suggestions("Dns", provided), vec!["dns"]);
+        assert_eq!(suggestions("dns", provided), Vec::<String>::new());
+        assert_eq!(suggestions("queue", provided), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,238 @@
+//! Bring-up order.
+//!
+//! A condition says that an agent can only provide something once another
+//! behavior exists, so one of the agents that provide that behavior has to
+//! be up first. `Tracker::get_startup_order` turns the resolved conditions of
+//! every working agent into waves of agents that can start in parallel.
+
+use crate::scc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The working agents in the order they can be started
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartupOrder {
+    /// Each wave only waits for agents in earlier waves. Every working agent
+    /// is in exactly one wave, and each wave is sorted.
+    pub waves: Vec<Vec<String>>,
+    /// Groups of agents that wait for each other (strongly connected
+    /// components), sorted. They can't be ordered, so each group shares a
+    /// wave.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl StartupOrder {
+    /// Which wave (counting from 1, as in `to_strings`) the agent starts in,
+    /// if it is a working agent
+    pub fn get_wave(&self, agent_name: &str) -> Option<usize> {
+        self.waves
+            .iter()
+            .position(|wave| wave.iter().any(|a| a == agent_name))
+            .map(|i| i + 1)
+    }
+
+    pub fn has_cycles(&self) -> bool {
+        !self.cycles.is_empty()
+    }
+
+    /// One line per wave, e.g. `wave 1: db, queue`, then one per cycle, e.g.
+    /// `cycle: a, b`
+    pub fn to_strings(&self) -> Vec<String> {
+        let mut ret: Vec<String> = self
+            .waves
+            .iter()
+            .enumerate()
+            .map(|(i, wave)| format!("wave {}: {}", i + 1, wave.join(", ")))
+            .collect();
+        ret.extend(
+            self.cycles
+                .iter()
+                .map(|cycle| format!("cycle: {}", cycle.join(", "))),
+        );
+        ret
+    }
+}
+
+/// What one provide of an agent waits for. Each condition is the list of
+/// agents that can provide it, any one of which will do.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Need {
+    /// Every one of these conditions has to be met
+    pub(crate) conditions: Vec<Vec<String>>,
+    /// And every condition of one of these groups, if there are any
+    pub(crate) any_of: Vec<Vec<Vec<String>>>,
+}
+
+impl Need {
+    fn is_met(&self, up: &HashSet<&str>) -> bool {
+        let met = |providers: &Vec<String>| providers.iter().any(|p| up.contains(p.as_str()));
+        self.conditions.iter().all(met)
+            && (self.any_of.is_empty() || self.any_of.iter().any(|g| g.iter().all(met)))
+    }
+
+    // The providers of every condition that isn't met yet
+    fn waits_for<'a>(&'a self, up: &HashSet<&str>) -> impl Iterator<Item = &'a String> {
+        let met = |providers: &Vec<String>| providers.iter().any(|p| up.contains(p.as_str()));
+        let any_of_met = self.any_of.is_empty() || self.any_of.iter().any(|g| g.iter().all(met));
+        let unmet: Vec<&'a Vec<String>> = self
+            .conditions
+            .iter()
+            .chain(self.any_of.iter().flatten().filter(|_| !any_of_met))
+            .filter(|providers| !met(providers))
+            .collect();
+        unmet.into_iter().flatten()
+    }
+}
+
+/// Works out the waves from what each agent's provides wait for. Each round
+/// starts every agent whose needs are all met by the agents already up. A
+/// group of agents that only wait for each other (a strongly connected
+/// component with nothing left to wait for outside of it) starts in the same
+/// round, as a cycle.
+pub(crate) fn startup_order(needs: &HashMap<String, Vec<Need>>) -> StartupOrder {
+    let mut ret = StartupOrder::default();
+    let mut up: HashSet<&str> = HashSet::new();
+    while up.len() < needs.len() {
+        let mut waiting: HashMap<String, Vec<String>> = HashMap::new();
+        for (agent_name, agent_needs) in needs.iter().filter(|(a, _)| !up.contains(a.as_str())) {
+            let mut waits_for: Vec<String> = agent_needs
+                .iter()
+                .filter(|n| !n.is_met(&up))
+                .flat_map(|n| n.waits_for(&up))
+                .filter(|p| *p != agent_name && needs.contains_key(*p))
+                .cloned()
+                .collect();
+            waits_for.sort();
+            waits_for.dedup();
+            waiting.insert(agent_name.clone(), waits_for);
+        }
+        let mut wave = vec![];
+        for component in scc::strongly_connected_components(&waiting) {
+            let waits_outside = component
+                .iter()
+                .flat_map(|a| &waiting[a])
+                .any(|d| !component.contains(d));
+            if waits_outside {
+                continue;
+            }
+            if component.len() > 1 {
+                ret.cycles.push(component.clone());
+            }
+            wave.extend(component);
+        }
+        // a component with nothing outside to wait for is always found, so
+        // every round starts something
+        wave.sort();
+        for a in &wave {
+            up.insert(needs.get_key_value(a).unwrap().0.as_str());
+        }
+        ret.waves.push(wave);
+    }
+    ret.cycles.sort();
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn providers(p: &[&str]) -> Vec<String> {
+        p.iter().map(|p| String::from(*p)).collect()
+    }
+
+    fn needs(agents: Vec<(&str, Vec<Need>)>) -> HashMap<String, Vec<Need>> {
+        agents
+            .into_iter()
+            .map(|(a, n)| (String::from(a), n))
+            .collect()
+    }
+
+    fn needing(conditions: &[&[&str]]) -> Need {
+        Need {
+            conditions: conditions.iter().map(|c| providers(c)).collect(),
+            any_of: vec![],
+        }
+    }
+
+    #[test]
+    fn test_waves() {
+        let order = startup_order(&needs(vec![
+            ("db", vec![]),
+            ("cache", vec![needing(&[&["db"]])]),
+            ("api", vec![needing(&[&["db"], &["cache"]])]),
+            ("web", vec![needing(&[&["api"]])]),
+        ]));
+        assert_eq!(
+            order.to_strings(),
+            ["wave 1: db", "wave 2: cache", "wave 3: api", "wave 4: web"]
+        );
+        assert_eq!(order.get_wave("db"), Some(1));
+        assert_eq!(order.get_wave("web"), Some(4));
+        assert_eq!(order.get_wave("nope"), None);
+        assert!(!order.has_cycles());
+        assert_eq!(startup_order(&HashMap::new()), StartupOrder::default());
+    }
+
+    #[test]
+    fn test_any_provider() {
+        // either replica will do, so the slow one doesn't hold api up
+        let order = startup_order(&needs(vec![
+            ("disk", vec![]),
+            ("fast", vec![]),
+            ("slow", vec![needing(&[&["disk"]])]),
+            ("api", vec![needing(&[&["fast", "slow"]])]),
+        ]));
+        assert_eq!(
+            order.to_strings(),
+            ["wave 1: disk, fast", "wave 2: api, slow"]
+        );
+
+        // one anyOf group is enough
+        let order = startup_order(&needs(vec![
+            ("disk", vec![]),
+            ("slow", vec![needing(&[&["disk"]])]),
+            ("fast", vec![]),
+            (
+                "api",
+                vec![Need {
+                    conditions: vec![],
+                    any_of: vec![vec![providers(&["slow"])], vec![providers(&["fast"])]],
+                }],
+            ),
+        ]));
+        assert_eq!(order.get_wave("api"), Some(2));
+    }
+
+    #[test]
+    fn test_cycles() {
+        // x and y wait for each other, and z for both of them
+        let order = startup_order(&needs(vec![
+            ("db", vec![]),
+            ("x", vec![needing(&[&["y"], &["db"]])]),
+            ("y", vec![needing(&[&["x"]])]),
+            ("z", vec![needing(&[&["x"], &["y"]])]),
+            ("a", vec![needing(&[&["b"]])]),
+            ("b", vec![needing(&[&["a"]])]),
+        ]));
+        assert_eq!(
+            order.to_strings(),
+            [
+                "wave 1: a, b, db",
+                "wave 2: x, y",
+                "wave 3: z",
+                "cycle: a, b",
+                "cycle: x, y",
+            ]
+        );
+
+        // a way around the cycle breaks it
+        let order = startup_order(&needs(vec![
+            ("c", vec![]),
+            ("a", vec![needing(&[&["b", "c"]])]),
+            ("b", vec![needing(&[&["a"]])]),
+        ]));
+        assert_eq!(order.to_strings(), ["wave 1: c", "wave 2: a", "wave 3: b"]);
+    }
+}
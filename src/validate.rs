@@ -0,0 +1,626 @@
+//! Semantic checks over everything that was added to a Tracker.
+//!
+//! Deserializing only proves that each document is a well formed `Item`.
+//! `validate` looks at the items together and reports the mistakes that
+//! would otherwise only show up as surprising resolutions.
+
+use crate::components::{Behavior, Location, Provenance};
+use crate::version;
+use crate::Tracker;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// How bad a `Diagnostic` is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The contracts are wrong and something will be missing from the network
+    Error,
+    /// The contracts work, but probably not the way they were meant to
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// What a `Diagnostic` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+    /// A SuperAgent lists an agent that was never defined
+    UnknownSuperAgentMember,
+    /// SuperAgents that contain each other
+    SuperAgentCycle,
+    /// Two SuperAgent instances have the same name and would be merged
+    DuplicateInstanceName,
+    /// A provide, want or condition with an empty name
+    EmptyBehaviorName,
+    /// An agent defined more than once with different comments
+    ConflictingComments,
+    /// A want that only an agent hidden inside a SuperAgent provides
+    WantedInsideSuperAgent,
+    /// A version or version requirement that isn't valid semver
+    InvalidVersion,
+    /// Lint: a provide that nothing wants or depends on
+    UnwantedProvide,
+    /// Lint: a behavior that lists itself as a condition
+    SelfCondition,
+    /// Lint: conditions repeated on every provide instead of `globalConditions`
+    FactorableGlobalConditions,
+    /// Lint: behavior names that differ only by case or a typo
+    SimilarBehaviorNames,
+}
+
+impl DiagnosticCode {
+    pub const ALL: [DiagnosticCode; 11] = [
+        DiagnosticCode::UnknownSuperAgentMember,
+        DiagnosticCode::SuperAgentCycle,
+        DiagnosticCode::DuplicateInstanceName,
+        DiagnosticCode::EmptyBehaviorName,
+        DiagnosticCode::ConflictingComments,
+        DiagnosticCode::WantedInsideSuperAgent,
+        DiagnosticCode::InvalidVersion,
+        DiagnosticCode::UnwantedProvide,
+        DiagnosticCode::SelfCondition,
+        DiagnosticCode::FactorableGlobalConditions,
+        DiagnosticCode::SimilarBehaviorNames,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::UnknownSuperAgentMember => "unknown-superagent-member",
+            DiagnosticCode::SuperAgentCycle => "superagent-cycle",
+            DiagnosticCode::DuplicateInstanceName => "duplicate-instance-name",
+            DiagnosticCode::EmptyBehaviorName => "empty-behavior-name",
+            DiagnosticCode::ConflictingComments => "conflicting-comments",
+            DiagnosticCode::WantedInsideSuperAgent => "wanted-inside-superagent",
+            DiagnosticCode::InvalidVersion => "invalid-version",
+            DiagnosticCode::UnwantedProvide => "unwanted-provide",
+            DiagnosticCode::SelfCondition => "self-condition",
+            DiagnosticCode::FactorableGlobalConditions => "factorable-global-conditions",
+            DiagnosticCode::SimilarBehaviorNames => "similar-behavior-names",
+        }
+    }
+}
+
+impl FromStr for DiagnosticCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DiagnosticCode::ALL
+            .into_iter()
+            .find(|c| c.as_str() == s)
+            .ok_or_else(|| format!("unknown diagnostic code: {}", s))
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A single problem found by `validate`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    /// Where the problem is, if the item was loaded from a file
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: DiagnosticCode, message: String) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            message,
+            location: None,
+        }
+    }
+
+    pub fn with_location(mut self, location: Option<&Location>) -> Diagnostic {
+        self.location = location.cloned();
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+// Displayed as `path:line: severity[code]: message`, without the location
+// when there isn't one
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}: ", location)?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// Runs every check over the agents and superagents of the Tracker, as
+/// they were added (before SuperAgents are merged). Diagnostics come out
+/// grouped by check, each in the order the items were added.
+pub fn validate(tracker: &Tracker) -> Vec<Diagnostic> {
+    let mut ret = vec![];
+    ret.extend(check_superagent_members(tracker));
+    ret.extend(check_superagent_cycles(tracker));
+    ret.extend(check_duplicate_instances(tracker));
+    ret.extend(check_empty_behavior_names(tracker));
+    ret.extend(check_conflicting_comments(tracker));
+    ret.extend(check_wanted_inside_superagent(tracker));
+    ret.extend(check_versions(tracker));
+    ret
+}
+
+fn first(provenance: &Provenance) -> Option<&Location> {
+    provenance.get_first()
+}
+
+fn check_superagent_members(tracker: &Tracker) -> Vec<Diagnostic> {
+    // members can be agents or other SuperAgents
+    let agent_names: HashSet<&String> = tracker
+        .available_agents
+        .iter()
+        .map(|a| a.item.get_name())
+        .chain(
+            tracker
+                .available_superagents
+                .iter()
+                .map(|sa| sa.item.get_name()),
+        )
+        .collect();
+    let mut ret = vec![];
+    for sa in tracker.available_superagents.iter().map(|sa| &sa.item) {
+        for member in sa.get_agent_names() {
+            if agent_names.contains(&member) {
+                continue;
+            }
+            ret.push(
+                Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticCode::UnknownSuperAgentMember,
+                    format!(
+                        "SuperAgent {} contains agent {}, which is not defined",
+                        sa.get_name(),
+                        member
+                    ),
+                )
+                .with_location(first(sa.get_provenance())),
+            );
+        }
+    }
+    ret
+}
+
+fn check_superagent_cycles(tracker: &Tracker) -> Vec<Diagnostic> {
+    let mut ret = vec![];
+    for cycle in tracker.get_superagent_cycles() {
+        let sa = tracker
+            .available_superagents
+            .iter()
+            .map(|sa| &sa.item)
+            .find(|sa| *sa.get_name() == cycle[0]);
+        ret.push(
+            Diagnostic::new(
+                Severity::Error,
+                DiagnosticCode::SuperAgentCycle,
+                if cycle.len() == 1 {
+                    format!("SuperAgent {} contains itself", cycle[0])
+                } else {
+                    format!(
+                        "SuperAgents {} contain each other and are not nested",
+                        cycle.join(", ")
+                    )
+                },
+            )
+            .with_location(sa.and_then(|sa| first(sa.get_provenance()))),
+        );
+    }
+    ret
+}
+
+fn check_duplicate_instances(tracker: &Tracker) -> Vec<Diagnostic> {
+    // instance name -> the SuperAgent that first used it
+    let mut seen: HashMap<String, &String> = HashMap::new();
+    let mut ret = vec![];
+    for sa in tracker.available_superagents.iter().map(|sa| &sa.item) {
+        for instance in sa.get_instances() {
+            match seen.get(instance.get_name()) {
+                Some(other) => ret.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::DuplicateInstanceName,
+                        if *other == sa.get_name() {
+                            format!(
+                                "SuperAgent {} has more than one instance named {}",
+                                sa.get_name(),
+                                instance.get_name()
+                            )
+                        } else {
+                            format!(
+                                "instance {} of SuperAgent {} is also an instance of SuperAgent {}",
+                                instance.get_name(),
+                                sa.get_name(),
+                                other
+                            )
+                        },
+                    )
+                    .with_location(first(instance.get_provenance()).or(first(sa.get_provenance()))),
+                ),
+                None => {
+                    seen.insert(instance.get_name().clone(), sa.get_name());
+                }
+            }
+        }
+    }
+    ret
+}
+
+fn check_empty_behavior_names(tracker: &Tracker) -> Vec<Diagnostic> {
+    let mut ret = vec![];
+    let check = |owner: &str, kind: &str, behaviors: &[Behavior], fallback: &Provenance| {
+        for b in behaviors {
+            let location = first(b.get_provenance()).or(first(fallback));
+            let empty_diagnostic = |what: String| {
+                Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticCode::EmptyBehaviorName,
+                    format!("{} {} a behavior with an empty name", owner, what),
+                )
+                .with_location(location)
+            };
+            if b.get_name().trim().is_empty() {
+                ret.push(empty_diagnostic(String::from(kind)));
+            }
+            if b.get_all_conditions()
+                .iter()
+                .chain(b.get_own_conditions())
+                .any(|c| c.trim().is_empty())
+            {
+                ret.push(empty_diagnostic(format!(
+                    "{} {} with a condition on",
+                    kind,
+                    b.get_name()
+                )));
+            }
+        }
+    };
+    for_each_behavior_list(tracker, check);
+    ret
+}
+
+fn check_conflicting_comments(tracker: &Tracker) -> Vec<Diagnostic> {
+    // agent name -> the first definition with a comment
+    let mut commented = HashMap::new();
+    let mut ret = vec![];
+    for a in tracker.available_agents.iter().map(|a| &a.item) {
+        if a.get_comment().is_empty() {
+            continue;
+        }
+        let Some(other) = commented.get(a.get_name()) else {
+            commented.insert(a.get_name(), a);
+            continue;
+        };
+        if other.get_comment() == a.get_comment() {
+            continue;
+        }
+        let defined_at = match first(other.get_provenance()) {
+            Some(location) => format!(" at {}", location),
+            None => String::new(),
+        };
+        ret.push(
+            Diagnostic::new(
+                Severity::Warning,
+                DiagnosticCode::ConflictingComments,
+                format!(
+                    "agent {} has a different comment than where it is defined{}",
+                    a.get_name(),
+                    defined_at
+                ),
+            )
+            .with_location(first(a.get_provenance())),
+        );
+    }
+    ret
+}
+
+fn check_wanted_inside_superagent(tracker: &Tracker) -> Vec<Diagnostic> {
+    // member agent name -> the SuperAgent that wraps it
+    let mut wrapped: HashMap<&String, &String> = HashMap::new();
+    for sa in tracker.available_superagents.iter().map(|sa| &sa.item) {
+        for member in sa.get_agent_names() {
+            if let Some(a) = tracker
+                .available_agents
+                .iter()
+                .find(|a| *a.item.get_name() == member)
+            {
+                wrapped.entry(a.item.get_name()).or_insert(sa.get_name());
+            }
+        }
+    }
+    // behavior -> the first wrapped agent that provides it
+    let mut hidden: HashMap<&String, &String> = HashMap::new();
+    for a in tracker.available_agents.iter().map(|a| &a.item) {
+        if !wrapped.contains_key(a.get_name()) {
+            continue;
+        }
+        for p in a.iter_provides() {
+            hidden.entry(p.get_name()).or_insert(a.get_name());
+        }
+    }
+    let mut ret = vec![];
+    for (agent_name, agents) in sorted(&tracker.working_agents) {
+        for w in agents.iter().flat_map(|a| a.iter_wants()) {
+            let Some(provider) = hidden.get(w.get_name()) else {
+                continue;
+            };
+            if is_provided(tracker, w.get_name()) {
+                continue;
+            }
+            ret.push(
+                Diagnostic::new(
+                    Severity::Warning,
+                    DiagnosticCode::WantedInsideSuperAgent,
+                    format!(
+                        "agent {} wants {}, which only {} provides and it is inside SuperAgent {}",
+                        agent_name,
+                        w.get_name(),
+                        provider,
+                        wrapped[provider]
+                    ),
+                )
+                .with_location(first(w.get_provenance())),
+            );
+        }
+    }
+    ret
+}
+
+fn check_versions(tracker: &Tracker) -> Vec<Diagnostic> {
+    let mut ret = vec![];
+    let check = |owner: &str, kind: &str, behaviors: &[Behavior], fallback: &Provenance| {
+        for b in behaviors {
+            let mut errors = vec![];
+            if let Some(v) = b.get_version() {
+                let parsed = if kind == "provides" {
+                    version::parse_version(v).map(|_| ())
+                } else {
+                    version::parse_requirement(v).map(|_| ())
+                };
+                errors.extend(parsed.err());
+            }
+            for r in b.get_condition_versions().values() {
+                errors.extend(version::parse_requirement(r).err());
+            }
+            for error in errors {
+                ret.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::InvalidVersion,
+                        format!("{} {} {}: {}", owner, kind, b.get_name(), error),
+                    )
+                    .with_location(first(b.get_provenance()).or(first(fallback))),
+                );
+            }
+        }
+    };
+    for_each_behavior_list(tracker, check);
+    ret
+}
+
+// Calls `f(owner, "provides" or "wants", behaviors, owner's provenance)` for
+// every agent and SuperAgent instance, as they were added
+fn for_each_behavior_list(
+    tracker: &Tracker,
+    mut f: impl FnMut(&str, &str, &[Behavior], &Provenance),
+) {
+    for a in tracker.available_agents.iter().map(|a| &a.item) {
+        let owner = format!("agent {}", a.get_name());
+        let provides: Vec<Behavior> = a.iter_provides().cloned().collect();
+        let wants: Vec<Behavior> = a.iter_wants().cloned().collect();
+        f(&owner, "provides", &provides, a.get_provenance());
+        f(&owner, "wants", &wants, a.get_provenance());
+    }
+    for sa in tracker.available_superagents.iter().map(|sa| &sa.item) {
+        for i in sa.get_instances() {
+            let owner = format!("instance {} of SuperAgent {}", i.get_name(), sa.get_name());
+            f(&owner, "provides", i.get_provides(), i.get_provenance());
+            f(&owner, "wants", i.get_wants(), i.get_provenance());
+        }
+    }
+}
+
+fn is_provided(tracker: &Tracker, behavior_name: &str) -> bool {
+    tracker
+        .names
+        .get(behavior_name)
+        .is_some_and(|b| tracker.providers.contains_key(&b))
+}
+
+fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut ret: Vec<(&String, &V)> = map.iter().collect();
+    ret.sort_by(|a, b| a.0.cmp(b.0));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Agent, Item, Position, Span, SuperAgent};
+    use crate::loader::load_items;
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<DiagnosticCode> {
+        diagnostics.iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_clean() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("a2").with_wants(vec![Behavior::build("b1")]));
+        t.add_superagent(SuperAgent::new(String::from("sa")).with_agent("a1"));
+        assert_eq!(validate(&t), vec![]);
+    }
+
+    #[test]
+    fn test_invalid_versions() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![
+            Behavior::build("b1").with_version("2.0.0"),
+            Behavior::build("b2").with_version("v2"),
+        ]));
+        t.add_agent(Agent::build("a2").with_wants(vec![
+            Behavior::build("b1").with_version("^2"),
+            Behavior::build("b3").with_condition_version("b1", "two"),
+        ]));
+        let diagnostics = validate(&t);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![
+                DiagnosticCode::InvalidVersion,
+                DiagnosticCode::InvalidVersion
+            ]
+        );
+        assert!(diagnostics[0]
+            .to_string()
+            .starts_with("error[invalid-version]: agent a1 provides b2: invalid version v2"));
+        assert!(diagnostics[1].to_string().starts_with(
+            "error[invalid-version]: agent a2 wants b3: invalid version requirement two"
+        ));
+    }
+
+    #[test]
+    fn test_superagents() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1").with_provides(vec![Behavior::build("b1")]));
+        t.add_agent(Agent::build("a3").with_wants(vec![Behavior::build("b1")]));
+        t.add_superagent(
+            SuperAgent::new(String::from("sa1"))
+                .with_agent("a1")
+                .with_agent("missing")
+                .with_instance("i1", "", "p", "c", vec![], vec![]),
+        );
+        t.add_superagent(SuperAgent::new(String::from("sa2")).with_instance(
+            "i1",
+            "",
+            "p",
+            "c",
+            vec![],
+            vec![],
+        ));
+        let diagnostics = validate(&t);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![
+                DiagnosticCode::UnknownSuperAgentMember,
+                DiagnosticCode::DuplicateInstanceName,
+                DiagnosticCode::WantedInsideSuperAgent,
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[unknown-superagent-member]: SuperAgent sa1 contains agent missing, which is not defined"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "instance i1 of SuperAgent sa2 is also an instance of SuperAgent sa1"
+        );
+        assert_eq!(
+            diagnostics[2].message,
+            "agent a3 wants b1, which only a1 provides and it is inside SuperAgent sa1"
+        );
+        assert_eq!(diagnostics[2].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_superagent_cycles() {
+        let mut t = Tracker::new();
+        t.add_agent(Agent::build("a1"));
+        t.add_superagent(SuperAgent::new(String::from("inner")).with_agent("a1"));
+        t.add_superagent(SuperAgent::new(String::from("outer")).with_agent("inner"));
+        assert_eq!(validate(&t), vec![]);
+        t.add_superagent(SuperAgent::new(String::from("inner")).with_agent("outer"));
+        t.add_superagent(SuperAgent::new(String::from("self")).with_agent("self"));
+        let diagnostics = validate(&t);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![
+                DiagnosticCode::SuperAgentCycle,
+                DiagnosticCode::SuperAgentCycle
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[superagent-cycle]: SuperAgents inner, outer contain each other and are not nested"
+        );
+        assert_eq!(diagnostics[1].message, "SuperAgent self contains itself");
+    }
+
+    #[test]
+    fn test_from_files() {
+        let mut t = Tracker::new();
+        let a = "kind: Agent
+name: db
+comment: the database
+provides:
+  - name: storage
+    conditions: ['']
+";
+        let b = "kind: Agent
+name: db
+comment: our database
+wants:
+  - name: ''
+";
+        t.add_source_items("a.yaml", load_items("a.yaml", a).unwrap());
+        t.add_source_items("b.yaml", load_items("b.yaml", b).unwrap());
+        t.add_item(Item::Agent(Agent::build("db")));
+        let diagnostics = validate(&t);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<String>>(),
+            vec![
+                "a.yaml:5: error[empty-behavior-name]: agent db provides storage with a condition on a behavior with an empty name",
+                "b.yaml:5: error[empty-behavior-name]: agent db wants a behavior with an empty name",
+                "b.yaml:1: warning[conflicting-comments]: agent db has a different comment than where it is defined at a.yaml:1",
+            ]
+        );
+        assert!(diagnostics[0].is_error());
+        assert_eq!(
+            diagnostics[1].location.as_ref().unwrap().span,
+            Span {
+                start: Position { line: 5, column: 3 },
+                end: Position {
+                    line: 5,
+                    column: 13
+                },
+            }
+        );
+
+        let json = serde_json::to_string(&diagnostics[2]).unwrap();
+        assert!(json.contains("\"severity\":\"warning\",\"code\":\"conflicting-comments\""));
+        let back: Diagnostic = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, diagnostics[2]);
+        assert_eq!(
+            "conflicting-comments".parse(),
+            Ok(DiagnosticCode::ConflictingComments)
+        );
+        assert!("nope".parse::<DiagnosticCode>().is_err());
+    }
+}
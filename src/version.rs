@@ -0,0 +1,100 @@
+//! Versioned behaviors.
+//!
+//! A provide can carry a semver `version`, and a want or a condition a
+//! version requirement. Providers whose version doesn't meet the requirement
+//! are left out of the resolution and recorded as `VersionMismatch`es, so
+//! that a behavior only provided at the wrong version is reported as such
+//! rather than as having no provider.
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+pub(crate) fn parse_version(version: &str) -> Result<Version, String> {
+    Version::parse(version.trim()).map_err(|e| format!("invalid version {}: {}", version, e))
+}
+
+pub(crate) fn parse_requirement(requirement: &str) -> Result<VersionReq, String> {
+    VersionReq::parse(requirement.trim())
+        .map_err(|e| format!("invalid version requirement {}: {}", requirement, e))
+}
+
+/// A provider that was left out because its version doesn't meet the
+/// requirement
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VersionMismatch {
+    pub agent_name: String,
+    /// None if the provide has no (valid) version
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl VersionMismatch {
+    // Unversioned providers never meet a requirement
+    pub(crate) fn check(
+        agent_name: &str,
+        version: Option<&Version>,
+        requirement: &VersionReq,
+    ) -> Option<VersionMismatch> {
+        match version {
+            Some(v) if requirement.matches(v) => None,
+            _ => Some(VersionMismatch {
+                agent_name: String::from(agent_name),
+                version: version.map(|v| v.to_string()),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} has {}", self.agent_name, version),
+            None => write!(f, "{} has no version", self.agent_name),
+        }
+    }
+}
+
+/// Why a behavior is only provided at the wrong version, e.g. `needs ^2: v1
+/// has 1.4.0, v3 has no version`
+pub(crate) fn mismatch_string(requirement: &str, mismatches: &[VersionMismatch]) -> String {
+    format!(
+        "needs {}: {}",
+        requirement,
+        mismatches
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+/// How such a behavior is drawn, e.g. `? (needs ^2: v1 has 1.4.0)`
+pub(crate) fn mismatch_label(requirement: &str, mismatches: &[VersionMismatch]) -> String {
+    format!("? ({})", mismatch_string(requirement, mismatches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let requirement = parse_requirement("^2.1").unwrap();
+        let v = |s: &str| parse_version(s).unwrap();
+        assert_eq!(
+            VersionMismatch::check("a", Some(&v("2.3.0")), &requirement),
+            None
+        );
+        let old = VersionMismatch::check("a", Some(&v("1.4.0")), &requirement).unwrap();
+        assert_eq!(old.to_string(), "a has 1.4.0");
+        let unversioned = VersionMismatch::check("b", None, &requirement).unwrap();
+        assert_eq!(unversioned.to_string(), "b has no version");
+        assert_eq!(
+            mismatch_label("^2.1", &[old, unversioned]),
+            "? (needs ^2.1: a has 1.4.0, b has no version)"
+        );
+        assert!(parse_version("v2").is_err());
+        assert!(parse_requirement(">=").is_err());
+    }
+}
@@ -37,10 +37,10 @@ pub fn get_pt() -> PT {
 #[wasm_bindgen]
 impl PT {
     pub fn add_contract(&mut self, input: &str) {
-        for document in serde_yaml::Deserializer::from_str(input) {
-            let i = Item::deserialize(document).unwrap();
-            self.tracker.add_item(i);
-        }
+        let items = serde_yaml::Deserializer::from_str(input)
+            .map(|document| Item::deserialize(document).unwrap())
+            .collect();
+        self.tracker.add_items(items);
     }
 
     pub fn check(&self, input: &str) -> bool {
@@ -92,6 +92,48 @@ impl PT {
         let r = self.tracker.resolve(input);
         serde_wasm_bindgen::to_value(&r).unwrap()
     }
+
+    pub fn resolve_graph(&mut self, input: &str) -> JsValue {
+        let r = self.tracker.resolve_graph(input);
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
+
+    pub fn resolve_want(&self, agent: &str, want: &str) -> JsValue {
+        let r = self.tracker.resolve_want(agent, want);
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
+
+    pub fn resolve_all(&self) -> JsValue {
+        let r = self.tracker.resolve_all();
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
+
+    pub fn allocate(&self) -> JsValue {
+        let r = self.tracker.allocate();
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
+
+    pub fn impact(&self, agents: Vec<String>) -> JsValue {
+        let agent_names: Vec<&str> = agents.iter().map(String::as_str).collect();
+        let r = promise_tracker::impact::impact(&self.tracker, &agent_names);
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
+
+    pub fn redundancy(&self) -> JsValue {
+        let r = promise_tracker::redundancy::redundancy(&self.tracker);
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
+
+    pub fn minimal_deployment(&self, roots: Vec<String>) -> JsValue {
+        let roots: Vec<&str> = roots.iter().map(String::as_str).collect();
+        let r = promise_tracker::deployment::minimal_deployment(&self.tracker, &roots);
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
+
+    pub fn get_startup_order(&self) -> JsValue {
+        let r = self.tracker.get_startup_order();
+        serde_wasm_bindgen::to_value(&r).unwrap()
+    }
 }
 
 #[cfg(test)]